@@ -16,15 +16,18 @@ pub use self::rust::*;
 
 grammar! rust {
 
+  #[start]
   identifier = !digit !keyword ident_char+ spacing > to_string
   ident_char = ["a-zA-Z0-9_"]
 
+  #[start]
   keyword
     = strict_keyword kw_tail
     / reserved_keyword kw_tail
 
   kw_tail = !ident_char spacing
 
+  #[start]
   special_idents
     = "self"
     / "static"
@@ -97,6 +100,7 @@ grammar! rust {
     / "override"
     / "macro"
 
+  #[start]
   integer
     = decimal
 
@@ -0,0 +1,63 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Character classes compiled into tables. The generated code declares one static `CharClass` per distinct large class of the grammar, the small classes are compiled into comparisons.
+
+use std::cmp::Ordering;
+
+pub struct CharClass
+{
+  /// Bit `c % 64` of `ascii[c / 64]` is set if the ASCII character `c` belongs to the class.
+  pub ascii: [u64; 2],
+  /// Intervals of the non-ASCII characters of the class, sorted and disjoint.
+  pub ranges: &'static [(char, char)]
+}
+
+impl CharClass
+{
+  #[inline]
+  pub fn contains(&self, c: char) -> bool {
+    let code = c as u32;
+    if code < 128 {
+      self.ascii[(code / 64) as usize] & (1 << (code % 64)) != 0
+    }
+    else {
+      self.ranges.binary_search_by(|&(lo, hi)| {
+        if hi < c { Ordering::Less }
+        else if lo > c { Ordering::Greater }
+        else { Ordering::Equal }
+      }).is_ok()
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_char_class() {
+    // `["a-z_λ-μ∀"]`
+    static CLASS: CharClass = CharClass {
+      ascii: [0, (0x7FFFFFE << 32) | (1 << 31)],
+      ranges: &[('λ', 'μ'), ('∀', '∀')]
+    };
+    for c in "az_λμ∀".chars() {
+      assert!(CLASS.contains(c), "{} should be in the class", c);
+    }
+    for c in "AZ`{0 νκ∁\u{10FFFF}".chars() {
+      assert!(!CLASS.contains(c), "{} should not be in the class", c);
+    }
+  }
+}
@@ -0,0 +1,417 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Implementation of a stream over a text split in several chunks, such as a rope. It implements all traits required by `CharStream`.
+
+use stream::*;
+use position::*;
+use std::cmp::{Ordering, min};
+
+/// A text stored as a sequence of `&str`. Chunks can be empty.
+pub trait Chunks
+{
+  fn chunks_len(&self) -> usize;
+  /// `index` must be smaller than `chunks_len`.
+  fn chunk(&self, index: usize) -> &str;
+}
+
+impl<S> Chunks for [S] where
+ S: AsRef<str>
+{
+  fn chunks_len(&self) -> usize {
+    self.len()
+  }
+
+  fn chunk(&self, index: usize) -> &str {
+    self[index].as_ref()
+  }
+}
+
+impl<S> Chunks for Vec<S> where
+ S: AsRef<str>
+{
+  fn chunks_len(&self) -> usize {
+    self.len()
+  }
+
+  fn chunk(&self, index: usize) -> &str {
+    self[index].as_ref()
+  }
+}
+
+/// Chunks of text with the offset of the beginning of each chunk and of each line, so a position is found with a binary search. The streams over the chunks are created with `stream()`.
+pub struct ChunkedText<'a, C: ?Sized + 'a>
+{
+  chunks: &'a C,
+  /// `chunk_starts[i]` is the offset of the chunk `i`, the last element is the length of the text.
+  chunk_starts: Vec<usize>,
+  lines: LineIndex
+}
+
+impl<'a, C: ?Sized> ChunkedText<'a, C> where
+ C: Chunks
+{
+  pub fn new(chunks: &'a C) -> ChunkedText<'a, C> {
+    let mut chunk_starts = Vec::with_capacity(chunks.chunks_len() + 1);
+    let mut offset = 0;
+    chunk_starts.push(offset);
+    for index in 0..chunks.chunks_len() {
+      offset += chunks.chunk(index).len();
+      chunk_starts.push(offset);
+    }
+    ChunkedText {
+      chunks: chunks,
+      chunk_starts: chunk_starts,
+      lines: LineIndex::from_pieces((0..chunks.chunks_len()).map(|index| chunks.chunk(index)))
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    *self.chunk_starts.last().unwrap()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+impl<'a, C: ?Sized> Stream for &'a ChunkedText<'a, C> where
+ C: Chunks
+{
+  type Output = ChunkStream<'a, C>;
+  fn stream(self) -> ChunkStream<'a, C> {
+    ChunkStream::new(self)
+  }
+}
+
+/// Represents a stream from chunks of text. Positions are byte offsets from the beginning of the first chunk, as with `StrStream`.
+pub struct ChunkStream<'a, C: ?Sized + 'a>
+{
+  text: &'a ChunkedText<'a, C>,
+  chunk_index: usize,
+  /// Offset in the current chunk.
+  chunk_offset: usize,
+  bytes_offset: usize
+}
+
+impl<'a, C: ?Sized> ChunkStream<'a, C> where
+ C: Chunks
+{
+  pub fn new(text: &'a ChunkedText<'a, C>) -> ChunkStream<'a, C> {
+    let mut stream = ChunkStream {
+      text: text,
+      chunk_index: 0,
+      chunk_offset: 0,
+      bytes_offset: 0
+    };
+    stream.skip_chunk_ends();
+    stream
+  }
+
+  #[inline(always)]
+  fn assert_same_chunks(&self, other: &ChunkStream<'a, C>) {
+    debug_assert!(self.text as *const ChunkedText<'a, C> == other.text as *const ChunkedText<'a, C>,
+      "Operations between two streams are only defined when they share the same chunks.");
+  }
+
+  fn chunks_len(&self) -> usize {
+    self.text.chunks.chunks_len()
+  }
+
+  fn chunk(&self, index: usize) -> &'a str {
+    self.text.chunks.chunk(index)
+  }
+
+  /// The rest of the current chunk, it is empty only at the end of the input.
+  fn current(&self) -> &'a str {
+    if self.chunk_index < self.chunks_len() {
+      &self.chunk(self.chunk_index)[self.chunk_offset..]
+    }
+    else {
+      ""
+    }
+  }
+
+  /// Moves to the beginning of the next non-empty chunk if the current one is fully read.
+  fn skip_chunk_ends(&mut self) {
+    let len = self.chunks_len();
+    while self.chunk_index < len && self.chunk_offset == self.chunk(self.chunk_index).len() {
+      self.chunk_index += 1;
+      self.chunk_offset = 0;
+    }
+  }
+
+  fn advance(&mut self, bytes: usize) {
+    self.chunk_offset += bytes;
+    self.bytes_offset += bytes;
+    self.skip_chunk_ends();
+  }
+
+  /// `true` if the input following the stream starts with `prefix`. The chunks are only read until `prefix` is matched.
+  fn starts_with(&self, prefix: &[u8]) -> bool {
+    let mut rest = prefix;
+    let mut chunk = self.current().as_bytes();
+    let mut index = self.chunk_index;
+    loop {
+      let len = min(chunk.len(), rest.len());
+      if chunk[..len] != rest[..len] {
+        return false;
+      }
+      rest = &rest[len..];
+      index += 1;
+      if rest.is_empty() {
+        return true;
+      }
+      else if index >= self.chunks_len() {
+        return false;
+      }
+      chunk = self.chunk(index).as_bytes();
+    }
+  }
+
+  pub fn line_column(&self) -> (usize, usize) {
+    let position = self.position();
+    (position.line, position.column)
+  }
+}
+
+impl<'a, C: ?Sized> Clone for ChunkStream<'a, C>
+{
+  fn clone(&self) -> ChunkStream<'a, C> {
+    ChunkStream {
+      text: self.text,
+      chunk_index: self.chunk_index,
+      chunk_offset: self.chunk_offset,
+      bytes_offset: self.bytes_offset
+    }
+  }
+}
+
+impl<'a, C: ?Sized> Iterator for ChunkStream<'a, C> where
+ C: Chunks
+{
+  type Item = char;
+  fn next(&mut self) -> Option<Self::Item> {
+    let current = self.current().chars().next();
+    if let Some(c) = current {
+      self.advance(c.len_utf8());
+    }
+    current
+  }
+}
+
+impl<'a, C: ?Sized> PartialEq for ChunkStream<'a, C> where
+ C: Chunks
+{
+  fn eq(&self, other: &Self) -> bool {
+    self.assert_same_chunks(other);
+    self.bytes_offset == other.bytes_offset
+  }
+}
+
+impl<'a, C: ?Sized> Eq for ChunkStream<'a, C> where C: Chunks {}
+
+impl<'a, C: ?Sized> PartialOrd for ChunkStream<'a, C> where
+ C: Chunks
+{
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    self.assert_same_chunks(other);
+    self.bytes_offset.partial_cmp(&other.bytes_offset)
+  }
+}
+
+impl<'a, C: ?Sized> Ord for ChunkStream<'a, C> where
+ C: Chunks
+{
+  fn cmp(&self, other: &Self) -> Ordering {
+    self.assert_same_chunks(other);
+    self.bytes_offset.cmp(&other.bytes_offset)
+  }
+}
+
+impl<'a, C: ?Sized> Location for ChunkStream<'a, C> where
+ C: Chunks
+{
+  fn location(&self) -> String {
+    format!("{}", self.position())
+  }
+}
+
+impl<'a, C: ?Sized> StreamPosition for ChunkStream<'a, C> where
+ C: Chunks
+{
+  fn position_with(&self, config: &ColumnConfig) -> Position {
+    let line = self.text.lines.line(self.bytes_offset);
+    let mut column = 1;
+    let mut stream = self.at_offset(self.text.lines.line_start(line));
+    while stream.bytes_offset < self.bytes_offset {
+      let c = stream.next().unwrap();
+      column = config.next_column(column, c);
+    }
+    Position::new(self.bytes_offset, line, column)
+  }
+}
+
+impl<'a, C: ?Sized> CodeSnippet for ChunkStream<'a, C> where
+ C: Chunks
+{
+  fn code_snippet(&self, len_hint: usize) -> String {
+    if !self.has_next() {
+      return String::from("<end-of-file>");
+    }
+    let mut snippet = String::new();
+    for c in self.clone() {
+      if snippet.len() + c.len_utf8() > len_hint {
+        break;
+      }
+      snippet.push(c);
+    }
+    snippet
+  }
+}
+
+impl<'a, C: ?Sized> ConsumePrefix<&'static str> for ChunkStream<'a, C> where
+ C: Chunks
+{
+  fn consume_prefix(&mut self, prefix: &'static str) -> bool {
+    if self.starts_with(prefix.as_bytes()) {
+      let mut remaining = prefix.len();
+      while remaining > 0 {
+        let len = min(self.current().len(), remaining);
+        self.advance(len);
+        remaining -= len;
+      }
+      true
+    }
+    else {
+      false
+    }
+  }
+}
+
+impl<'a, C: ?Sized> HasNext for ChunkStream<'a, C> where
+ C: Chunks
+{
+  fn has_next(&self) -> bool {
+    !self.current().is_empty()
+  }
+}
+
+impl<'a, C: ?Sized> StreamOffset for ChunkStream<'a, C> where
+ C: Chunks
+{
+  fn offset(&self) -> usize {
+    self.bytes_offset
+  }
+
+  fn at_offset(&self, offset: usize) -> ChunkStream<'a, C> {
+    assert!(offset <= self.text.len(), "`at_offset`: The offset must be in the chunks.");
+    let mut stream = self.clone();
+    stream.bytes_offset = offset;
+    match self.text.chunk_starts.binary_search(&offset) {
+      Ok(index) => {
+        stream.chunk_index = index;
+        stream.chunk_offset = 0;
+      }
+      Err(index) => {
+        stream.chunk_index = index - 1;
+        stream.chunk_offset = offset - self.text.chunk_starts[index - 1];
+      }
+    }
+    stream.skip_chunk_ends();
+    stream
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use stream::*;
+
+  #[test]
+  fn test_chunks_traversal() {
+    let chunks = vec!["", "ab", "", "", "é\nc", "d", ""];
+    let text: String = chunks.concat();
+    let chunked = ChunkedText::new(&chunks);
+    let mut stream = chunked.stream();
+    let start = stream.clone();
+    assert_eq!(stream.clone().collect::<String>(), text);
+    assert!(stream.has_next());
+    stream.next();
+    stream.next();
+    assert!(stream > start);
+    assert_eq!(stream.offset(), 2);
+    assert_eq!(stream.code_snippet(3), "é\n");
+    stream.next();
+    stream.next();
+    assert_eq!(stream.location(), "2:1");
+    assert_eq!(stream.code_snippet(10), "cd");
+    stream.next();
+    stream.next();
+    assert!(!stream.has_next());
+    assert_eq!(stream.next(), None);
+    assert_eq!(stream.code_snippet(10), "<end-of-file>");
+    assert_eq!(stream.location(), "2:3");
+    let config = ColumnConfig { unit: ColumnUnit::Utf16, tab_width: 4 };
+    let chunks = vec!["a\t", "é\n𝄞", "\tb"];
+    let chunked = ChunkedText::new(&chunks);
+    let end = chunked.stream().at_offset(chunked.len());
+    assert_eq!(end.position_with(&config), Position::new(11, 2, 6));
+    assert_eq!(chunked.stream().at_offset(4).position_with(&config), Position::new(4, 1, 6));
+  }
+
+  #[test]
+  fn test_consume_prefix_across_chunks() {
+    let chunks = ["le", "t", " x", " = 1"];
+    let chunked = ChunkedText::new(&chunks[..]);
+    let stream = chunked.stream();
+    let mut s = stream.clone();
+    assert!(!s.consume_prefix("lex"));
+    assert!(s == stream);
+    assert!(s.consume_prefix(""));
+    assert!(s.consume_prefix("let x"));
+    assert_eq!(s.offset(), 5);
+    assert!(!s.consume_prefix(" = 12"));
+    assert!(s.consume_prefix(" = 1"));
+    assert!(!s.has_next());
+  }
+
+  #[test]
+  fn test_stream_offset() {
+    let chunks = vec![String::from("aé"), String::new(), String::from(" b")];
+    let chunked = ChunkedText::new(&chunks);
+    let s1 = chunked.stream();
+    let mut s2 = s1.at_offset(3);
+    assert_eq!(s2.next(), Some(' '));
+    assert_eq!(s1.at_offset(1).next(), Some('é'));
+    assert!(s1.at_offset(5) > s2);
+    assert!(!s1.at_offset(5).has_next());
+    assert_eq!(s2.at_offset(0).next(), Some('a'));
+    assert_eq!(s1.at_offset(4).collect::<String>(), "b");
+  }
+
+  #[test]
+  fn test_at_offset_empty_chunks() {
+    let chunks = ["", "ab", "", "", "c", ""];
+    let chunked = ChunkedText::new(&chunks[..]);
+    let stream = chunked.stream();
+    assert_eq!(chunked.len(), 3);
+    let mut s = stream.at_offset(2);
+    assert_eq!(s.offset(), 2);
+    assert!(s.consume_prefix("c"));
+    assert!(s == stream.at_offset(3));
+    assert!(!stream.at_offset(3).has_next());
+    assert!(stream.at_offset(1).consume_prefix("bc"));
+  }
+}
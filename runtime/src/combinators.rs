@@ -16,6 +16,7 @@
 
 use parse_state::ParseState;
 use parse_success::ParseSuccess;
+use parse_error::ParseError;
 use ConsumePrefix;
 
 #[inline]
@@ -59,6 +60,34 @@ pub fn recognize_match_literal<S>(stream: S, lit: &'static str)
   parse_match_literal(stream, lit)
 }
 
+/// Matches the literals `lits` one after the other, as the sequence `l1 ... ln` does. On failure, the error is located at the beginning of the first literal not matched and expects this literal.
+pub fn match_literal_sequence<S>(mut stream: S, lits: &[&'static str])
+  -> ParseState<S, ()> where
+ S: Iterator + Clone + ConsumePrefix<&'static str>
+{
+  for &lit in lits {
+    if !stream.consume_prefix(lit) {
+      return ParseState::error(stream, lit);
+    }
+  }
+  ParseState::stateless(stream)
+}
+
+/// Result of a choice of literals `l1 / ... / ln` compiled into a trie, `matched` is the stream following the literal that won. On failure, the error expects the literals in the order they are merged by the choice, from `ln` to `l1`.
+pub fn match_literals<S>(past: S, matched: Option<S>, lits: &[&'static str])
+  -> ParseState<S, ()> where
+ S: Clone
+{
+  match matched {
+    Some(stream) => ParseState::stateless(stream),
+    None => ParseState::from_error(ParseError {
+      farthest_read: past,
+      expected: lits.iter().rev().cloned().collect(),
+      context: vec![]
+    })
+  }
+}
+
 #[inline]
 /// We erase the errors generated inside a `!e` expression because it is hard to correctly use (see paper Maidl & al. 2014 on error reporting).
 pub fn not_predicate<S>(state: ParseState<S, ()>, stream: S)
@@ -93,3 +122,218 @@ pub fn optional_parser<S, T>(state: ParseState<S, T>, stream: S)
     || ParseSuccess::new(stream, None),
     |success| success.map(|data| Some(data)))
 }
+
+/// Operator of an operator-precedence table with the semantic action building the value of the compound expression.
+pub enum OperatorKind<T>
+{
+  Prefix(fn(T) -> T),
+  Postfix(fn(T) -> T),
+  InfixLeft(fn(T, T) -> T),
+  InfixRight(fn(T, T) -> T)
+}
+
+pub struct PrecedenceOperator<T>
+{
+  /// Operators with a higher precedence bind tighter. It must be greater than `0`.
+  pub precedence: usize,
+  pub kind: OperatorKind<T>
+}
+
+/// Semantic action of unary operators used by the recognizer of an operator table.
+pub fn unit_unary_operator(_: ()) {}
+
+/// Semantic action of binary operators used by the recognizer of an operator table.
+pub fn unit_binary_operator(_: (), _: ()) {}
+
+/// Parses `operand` separated or surrounded by operators with a precedence-climbing loop. `operator(i, stream, context)` recognizes the operator `operators[i]`. When several operators match at the same position, the first declared one is chosen, similarly to the ordered choice. If the operand following an operator can not be parsed, the operator is not consumed and the expression parsed so far is returned.
+///
+/// `context` is passed to `operand` and `operator`, it is `()` unless the generated functions take additional arguments such as a memoisation table.
+pub fn precedence_climbing<S, C, T, O, R>(stream: S, context: &mut C, operand: O, operator: R,
+  operators: &[PrecedenceOperator<T>]) -> ParseState<S, T> where
+ S: Clone + Ord,
+ O: Fn(S, &mut C) -> ParseState<S, T>,
+ R: Fn(usize, S, &mut C) -> ParseState<S, ()>
+{
+  climb(stream, context, &operand, &operator, operators, 1)
+}
+
+fn climb<S, C, T, O, R>(stream: S, context: &mut C, operand: &O, operator: &R,
+  operators: &[PrecedenceOperator<T>], min_precedence: usize) -> ParseState<S, T> where
+ S: Clone + Ord,
+ O: Fn(S, &mut C) -> ParseState<S, T>,
+ R: Fn(usize, S, &mut C) -> ParseState<S, ()>
+{
+  let mut state = climb_prefix(stream, context, operand, operator, operators);
+  while let Some(ParseSuccess{stream, data}) = state.success.take() {
+    let mut matched = None;
+    for (idx, op) in operators.iter().enumerate() {
+      if op.precedence < min_precedence { continue; }
+      if let OperatorKind::Prefix(_) = op.kind { continue; }
+      let op_state = operator(idx, stream.clone(), context);
+      state.merge_error_in_place(op_state.error);
+      if let Some(op_success) = op_state.success {
+        matched = Some((op, op_success.stream));
+        break;
+      }
+    }
+    let (op, op_stream) = match matched {
+      Some(matched) => matched,
+      None => {
+        state.success = Some(ParseSuccess::new(stream, data));
+        break;
+      }
+    };
+    let (next_min_precedence, action) = match op.kind {
+      OperatorKind::Postfix(action) => {
+        state.success = Some(ParseSuccess::new(op_stream, action(data)));
+        continue;
+      }
+      OperatorKind::InfixLeft(action) => (op.precedence + 1, action),
+      OperatorKind::InfixRight(action) => (op.precedence, action),
+      OperatorKind::Prefix(_) => unreachable!()
+    };
+    let rhs = climb(op_stream, context, operand, operator, operators, next_min_precedence);
+    state.merge_error_in_place(rhs.error);
+    match rhs.success {
+      Some(rhs) => {
+        state.success = Some(ParseSuccess::new(rhs.stream, action(data, rhs.data)));
+      }
+      None => {
+        state.success = Some(ParseSuccess::new(stream, data));
+        break;
+      }
+    }
+  }
+  state
+}
+
+/// Prefix operators are tried in order and the operand is parsed if none of them succeeds.
+fn climb_prefix<S, C, T, O, R>(stream: S, context: &mut C, operand: &O, operator: &R,
+  operators: &[PrecedenceOperator<T>]) -> ParseState<S, T> where
+ S: Clone + Ord,
+ O: Fn(S, &mut C) -> ParseState<S, T>,
+ R: Fn(usize, S, &mut C) -> ParseState<S, ()>
+{
+  let mut error = ParseError::empty(stream.clone());
+  for (idx, op) in operators.iter().enumerate() {
+    if let OperatorKind::Prefix(action) = op.kind {
+      let op_state = operator(idx, stream.clone(), context);
+      error.merge_in_place(op_state.error);
+      if let Some(op_success) = op_state.success {
+        let state = climb(op_success.stream, context, operand, operator, operators, op.precedence);
+        if state.is_successful() {
+          return state.map_data(action).merge_error(error);
+        }
+        error.merge_in_place(state.error);
+      }
+    }
+  }
+  operand(stream, context).merge_error(error)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use parse_state::ParseState;
+  use str_stream::StrStream;
+  use stream::*;
+
+  #[test]
+  fn test_match_literals() {
+    let stream = "inner".stream();
+    let keywords = ["in", "if", "inner"];
+    // `"in" / "if" / "inner"` matches `in` since the first literal wins.
+    let state = match_literals(stream.clone(), Some(stream.at_offset(2)), &keywords);
+    assert!(state.stream_eq(&stream.at_offset(2)));
+    let chain = parse_match_literal(stream.clone(), "as")
+      .or_else_merge(|| parse_match_literal(stream.clone(), "if"));
+    let error = match_literals(stream.clone(), None, &["as", "if"]).error;
+    assert_eq!(error.expected, chain.error.expected);
+    assert_eq!(error.expected, vec!["if", "as"]);
+  }
+
+  #[test]
+  fn test_match_literal_sequence() {
+    // `"a" "b" "c"` merged into `"abc"` reports the error of the literal that failed.
+    for input in &["abc", "abx", "ax", "x", "ab", ""] {
+      let stream = input.stream();
+      let state = match_literal_sequence(stream.clone(), &["a", "b", "c"]);
+      let chain = parse_match_literal(stream.clone(), "a")
+        .and_then(|success| parse_match_literal(success.stream, "b"))
+        .and_then(|success| parse_match_literal(success.stream, "c"));
+      assert_eq!(state.is_successful(), chain.is_successful());
+      assert_eq!(state.error.farthest_read.offset(), chain.error.farthest_read.offset());
+      assert_eq!(state.error.expected, chain.error.expected);
+    }
+  }
+
+  fn digit<'a>(mut stream: StrStream<'a>) -> ParseState<StrStream<'a>, String> {
+    let past = stream.clone();
+    match stream.next() {
+      Some(c) if c.is_digit(10) => ParseState::success(stream, c.to_string()),
+      _ => ParseState::error(past, "digit")
+    }
+  }
+
+  fn add(l: String, r: String) -> String { format!("({}+{})", l, r) }
+  fn sub(l: String, r: String) -> String { format!("({}-{})", l, r) }
+  fn exp(l: String, r: String) -> String { format!("({}^{})", l, r) }
+  fn neg(e: String) -> String { format!("(-{})", e) }
+  fn fact(e: String) -> String { format!("({}!)", e) }
+
+  fn calc<'a>(input: &'a str) -> ParseState<StrStream<'a>, String> {
+    let operators = [
+      PrecedenceOperator { precedence: 1, kind: OperatorKind::InfixLeft(add) },
+      PrecedenceOperator { precedence: 1, kind: OperatorKind::InfixLeft(sub) },
+      PrecedenceOperator { precedence: 2, kind: OperatorKind::Prefix(neg) },
+      PrecedenceOperator { precedence: 3, kind: OperatorKind::InfixRight(exp) },
+      PrecedenceOperator { precedence: 4, kind: OperatorKind::Postfix(fact) }
+    ];
+    let symbols = ["+", "-", "-", "^", "!"];
+    precedence_climbing(input.stream(), &mut (),
+      |stream, _| digit(stream),
+      |op, stream, _| parse_match_literal(stream, symbols[op]),
+      &operators)
+  }
+
+  fn test_calc(input: &'static str, expected: &'static str) {
+    let state = calc(input);
+    assert!(!state.has_successor(), "`{}` is not fully parsed.", input);
+    assert_eq!(state.unwrap_data(), String::from(expected));
+  }
+
+  #[test]
+  fn test_precedence_climbing() {
+    test_calc("1", "1");
+    test_calc("1+2-3", "((1+2)-3)");
+    test_calc("1^2^3", "(1^(2^3))");
+    test_calc("1+2^3", "(1+(2^3))");
+    test_calc("-1-2", "((-1)-2)");
+    test_calc("-1^2", "(-(1^2))");
+    test_calc("1^2!", "(1^(2!))");
+    test_calc("--1!", "(-(-(1!)))");
+  }
+
+  #[test]
+  fn test_precedence_climbing_partial() {
+    let state = calc("1+2+");
+    assert!(state.has_successor());
+    let mut expected = state.error.expected.clone();
+    expected.sort();
+    assert_eq!(expected, vec!["-", "digit"]);
+    assert_eq!(state.unwrap_data(), String::from("(1+2)"));
+    assert!(!calc("+1").is_successful());
+  }
+
+  #[test]
+  fn test_precedence_climbing_context() {
+    let operators = [PrecedenceOperator { precedence: 1, kind: OperatorKind::InfixLeft(add) }];
+    let mut operand_calls = 0;
+    let state = precedence_climbing("1+2+3".stream(), &mut operand_calls,
+      |stream, calls: &mut usize| { *calls += 1; digit(stream) },
+      |_, stream, _| parse_match_literal(stream, "+"),
+      &operators);
+    assert_eq!(state.unwrap_data(), String::from("((1+2)+3)"));
+    assert_eq!(operand_calls, 3);
+  }
+}
@@ -0,0 +1,410 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lossless concrete syntax tree.
+//!
+//! A grammar with the attribute `#![cst]` notifies the stream each time the recognizer of a rule starts and succeeds (see `RuleEvents`). `CstStream` records these events in a persistent log, which is restored along with the stream when the parser backtracks, so the log only contains the rules that are part of the final result. The tree is built from this log: a node is created for each rule and the input between the children of a node is covered by token leaves. Thus, every byte of the input read is in the tree, including the spaces and comments discarded by the parsers.
+
+use stream::*;
+use str_stream::StrStream;
+use indentation::IndentStream;
+use parse_state::*;
+use parse_success::*;
+use std::rc::Rc;
+use std::cmp::Ordering;
+
+/// Notified when the recognizer of a rule starts and when it succeeds. Most streams ignore these events, only `CstStream` records them.
+pub trait RuleEvents
+{
+  /// `trivia` is `true` if the value of the rule is discarded, it is the case of spaces and comments.
+  fn enter_rule(&mut self, _name: &'static str, _trivia: bool) {}
+  fn exit_rule(&mut self) {}
+}
+
+impl<'a> RuleEvents for StrStream<'a> {}
+
+impl<S> RuleEvents for IndentStream<S> {}
+
+/// Calls `rule` between the events signaling the start and the success of the rule `name`.
+pub fn syntax_rule<S, T, F>(mut stream: S, name: &'static str, trivia: bool, rule: F) -> ParseState<S, T> where
+ S: RuleEvents,
+ F: FnOnce(S) -> ParseState<S, T>
+{
+  stream.enter_rule(name, trivia);
+  let mut state = rule(stream);
+  if let Some(ref mut success) = state.success {
+    success.stream.exit_rule();
+  }
+  state
+}
+
+/// Recognizes `stream` with `recognizer` and builds the concrete syntax tree of the recognized input.
+pub fn concrete_syntax_tree<S, F>(stream: S, recognizer: F) -> ParseState<S, CstNode> where
+ S: StreamOffset,
+ F: FnOnce(CstStream<S>) -> ParseState<CstStream<S>, ()>
+{
+  let start = stream.offset();
+  recognizer(CstStream::new(stream))
+    .map(|success| {
+      let tree = success.stream.syntax_tree(start);
+      ParseSuccess::new(success.stream, tree)
+    })
+    .map_stream(CstStream::into_inner)
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum CstKind
+{
+  Rule(&'static str),
+  /// Rule discarding its value such as spaces or comments.
+  Trivia(&'static str),
+  /// Input between the rules, it is always a leaf.
+  Token
+}
+
+/// Node covering the bytes `start..end` of the input.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CstNode
+{
+  pub kind: CstKind,
+  pub start: usize,
+  pub end: usize,
+  pub children: Vec<CstNode>
+}
+
+impl CstNode
+{
+  fn new(kind: CstKind, start: usize) -> CstNode {
+    CstNode {
+      kind: kind,
+      start: start,
+      end: start,
+      children: vec![]
+    }
+  }
+
+  pub fn is_leaf(&self) -> bool {
+    self.children.is_empty()
+  }
+
+  /// `input` must be the data from which the tree was built.
+  pub fn text<'a>(&self, input: &'a str) -> &'a str {
+    &input[self.start..self.end]
+  }
+
+  /// Concatenates the text of the leaves, it is always equal to `text` since the tree is lossless.
+  pub fn to_source(&self, input: &str) -> String {
+    let mut source = String::new();
+    self.push_source(input, &mut source);
+    source
+  }
+
+  fn push_source(&self, input: &str, source: &mut String) {
+    if self.is_leaf() {
+      source.push_str(self.text(input));
+    }
+    for child in &self.children {
+      child.push_source(input, source);
+    }
+  }
+
+  /// Indented description of the tree, one node per line, for debugging purposes.
+  pub fn pretty(&self, input: &str) -> String {
+    let mut description = String::new();
+    self.push_pretty(input, 0, &mut description);
+    description
+  }
+
+  fn push_pretty(&self, input: &str, depth: usize, description: &mut String) {
+    for _ in 0..depth {
+      description.push_str("  ");
+    }
+    let kind = match self.kind {
+      CstKind::Rule(name) => String::from(name),
+      CstKind::Trivia(name) => format!("{} (trivia)", name),
+      CstKind::Token => format!("\"{}\"", self.text(input).escape_default())
+    };
+    description.push_str(&format!("{} {}..{}\n", kind, self.start, self.end));
+    for child in &self.children {
+      child.push_pretty(input, depth + 1, description);
+    }
+  }
+
+  /// Adds token leaves between the children of a rule and closes it at `end`.
+  fn close(&mut self, end: usize) {
+    self.end = end;
+    let mut children = vec![];
+    let mut current = self.start;
+    for child in self.children.drain(..) {
+      if current < child.start {
+        children.push(CstNode::token(current, child.start));
+      }
+      current = child.end;
+      children.push(child);
+    }
+    if current < end && !children.is_empty() {
+      children.push(CstNode::token(current, end));
+    }
+    self.children = children;
+  }
+
+  fn token(start: usize, end: usize) -> CstNode {
+    CstNode {
+      kind: CstKind::Token,
+      start: start,
+      end: end,
+      children: vec![]
+    }
+  }
+}
+
+#[derive(Debug)]
+enum CstEvent
+{
+  Enter(&'static str, bool, usize),
+  Exit(usize)
+}
+
+#[derive(Debug)]
+struct EventNode
+{
+  event: CstEvent,
+  previous: Option<Rc<EventNode>>
+}
+
+/// Adds to a stream the log of the rules recognized. Streams are compared by their positions only.
+#[derive(Clone, Debug)]
+pub struct CstStream<S>
+{
+  stream: S,
+  log: Option<Rc<EventNode>>
+}
+
+impl<S> CstStream<S>
+{
+  pub fn new(stream: S) -> CstStream<S> {
+    CstStream {
+      stream: stream,
+      log: None
+    }
+  }
+
+  pub fn into_inner(self) -> S {
+    self.stream
+  }
+
+  fn push_event(&mut self, event: CstEvent) {
+    self.log = Some(Rc::new(EventNode {
+      event: event,
+      previous: self.log.take()
+    }));
+  }
+
+  fn events(&self) -> Vec<&CstEvent> {
+    let mut events = vec![];
+    let mut current = self.log.as_ref();
+    while let Some(node) = current {
+      events.push(&node.event);
+      current = node.previous.as_ref();
+    }
+    events.reverse();
+    events
+  }
+}
+
+impl<S> CstStream<S> where
+ S: StreamOffset
+{
+  /// Tree of the rules recognized from `start` to the current position. The root is a token if no rule was recognized and it is a rule if exactly one rule was recognized at the top level.
+  pub fn syntax_tree(&self, start: usize) -> CstNode {
+    let mut root = CstNode::new(CstKind::Token, start);
+    let mut stack = vec![];
+    for event in self.events() {
+      match event {
+        &CstEvent::Enter(name, trivia, offset) => {
+          let kind = if trivia { CstKind::Trivia(name) } else { CstKind::Rule(name) };
+          stack.push(CstNode::new(kind, offset));
+        }
+        &CstEvent::Exit(offset) => {
+          let mut node = stack.pop().expect("Every exit event is preceded by an enter event.");
+          node.close(offset);
+          stack.last_mut().unwrap_or(&mut root).children.push(node);
+        }
+      }
+    }
+    root.close(self.stream.offset());
+    if root.children.len() == 1 && root.children[0].start == root.start && root.children[0].end == root.end {
+      root.children.pop().unwrap()
+    }
+    else {
+      root
+    }
+  }
+}
+
+impl<S> RuleEvents for CstStream<S> where
+ S: StreamOffset
+{
+  fn enter_rule(&mut self, name: &'static str, trivia: bool) {
+    let offset = self.stream.offset();
+    self.push_event(CstEvent::Enter(name, trivia, offset));
+  }
+
+  fn exit_rule(&mut self) {
+    let offset = self.stream.offset();
+    self.push_event(CstEvent::Exit(offset));
+  }
+}
+
+impl<S> Iterator for CstStream<S> where
+ S: Iterator
+{
+  type Item = S::Item;
+  fn next(&mut self) -> Option<Self::Item> {
+    self.stream.next()
+  }
+}
+
+impl<S> PartialEq for CstStream<S> where
+ S: PartialEq
+{
+  fn eq(&self, other: &Self) -> bool {
+    self.stream.eq(&other.stream)
+  }
+}
+
+impl<S> Eq for CstStream<S> where S: Eq {}
+
+impl<S> PartialOrd for CstStream<S> where
+ S: PartialOrd
+{
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    self.stream.partial_cmp(&other.stream)
+  }
+}
+
+impl<S> Ord for CstStream<S> where
+ S: Ord
+{
+  fn cmp(&self, other: &Self) -> Ordering {
+    self.stream.cmp(&other.stream)
+  }
+}
+
+forward_position_traits!(CstStream);
+
+impl<S, P> ConsumePrefix<P> for CstStream<S> where
+ S: ConsumePrefix<P>
+{
+  fn consume_prefix(&mut self, prefix: P) -> bool {
+    self.stream.consume_prefix(prefix)
+  }
+}
+
+impl<S> HasNext for CstStream<S> where
+ S: HasNext
+{
+  fn has_next(&self) -> bool {
+    self.stream.has_next()
+  }
+}
+
+#[cfg(test)]
+mod test
+{
+  use super::*;
+  use str_stream::*;
+  use stream::*;
+  use parse_state::*;
+
+  type CstState<'a> = ParseState<CstStream<StrStream<'a>>, ()>;
+
+  fn literal<'a>(stream: CstStream<StrStream<'a>>, lit: &'static str) -> CstState<'a> {
+    let mut s = stream.clone();
+    if s.consume_prefix(lit) {
+      ParseState::stateless(s)
+    }
+    else {
+      ParseState::error(stream, lit)
+    }
+  }
+
+  // spacing = " "* -> ()
+  fn spacing<'a>(stream: CstStream<StrStream<'a>>) -> CstState<'a> {
+    syntax_rule(stream, "spacing", true, |mut stream| {
+      while stream.consume_prefix(" ") {}
+      ParseState::stateless(stream)
+    })
+  }
+
+  // number = "1" spacing
+  fn number<'a>(stream: CstStream<StrStream<'a>>) -> CstState<'a> {
+    syntax_rule(stream, "number", false, |stream| {
+      literal(stream, "1").and_then(|success| spacing(success.stream))
+    })
+  }
+
+  // sum = number "+" spacing number / number
+  fn sum<'a>(stream: CstStream<StrStream<'a>>) -> CstState<'a> {
+    syntax_rule(stream, "sum", false, |stream| {
+      number(stream.clone())
+        .and_then(|success| literal(success.stream, "+"))
+        .and_then(|success| spacing(success.stream))
+        .and_then(|success| number(success.stream))
+        .or_else_merge(|| number(stream))
+    })
+  }
+
+  #[test]
+  fn test_lossless_tree() {
+    let input = "1  +1 ";
+    let tree = concrete_syntax_tree(input.stream(), sum).unwrap_data();
+    assert_eq!(tree.kind, CstKind::Rule("sum"));
+    assert_eq!((tree.start, tree.end), (0, input.len()));
+    assert_eq!(tree.to_source(input), input);
+    let kinds: Vec<CstKind> = tree.children.iter().map(|child| child.kind.clone()).collect();
+    assert_eq!(kinds, vec![CstKind::Rule("number"), CstKind::Token,
+      CstKind::Trivia("spacing"), CstKind::Rule("number")]);
+    assert_eq!(tree.children[1].text(input), "+");
+    assert_eq!(tree.children[0].children[1].text(input), "  ");
+  }
+
+  #[test]
+  fn test_backtracking_discards_rules() {
+    let input = "1 -";
+    let state = concrete_syntax_tree(input.stream(), sum);
+    let tree = state.unwrap_data();
+    assert_eq!(tree.kind, CstKind::Rule("sum"));
+    assert_eq!(tree.children.len(), 1);
+    assert_eq!(tree.children[0].kind, CstKind::Rule("number"));
+    assert_eq!(tree.to_source(input), "1 ");
+  }
+
+  #[test]
+  fn test_pretty() {
+    let input = "1 ";
+    let tree = concrete_syntax_tree(input.stream(), number).unwrap_data();
+    assert_eq!(tree.pretty(input),
+      "number 0..2\n  \"1\" 0..1\n  spacing (trivia) 1..2\n");
+  }
+
+  #[test]
+  fn test_events_ignored() {
+    let input = "1 +1";
+    let mut stream = input.stream();
+    stream.enter_rule("sum", false);
+    stream.exit_rule();
+    assert_eq!(stream.offset(), 0);
+  }
+}
@@ -0,0 +1,166 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rendering of a parse error with the line of the source where it occurred.
+//!
+//! `ErrorRenderer` prints an error in the style of the Rust compiler, with a caret under the unexpected character:
+//!
+//! ```text
+//! error: unexpected `1`, expecting `=`
+//!  --> calc.txt:2:7
+//!   |
+//! 2 | let x 1 in x
+//!   |       ^
+//!   = note: while parsing let binding (started at 2:1)
+//! ```
+
+use stream::*;
+use parse_error::*;
+
+const BOLD: &'static str = "\x1b[1m";
+const BOLD_RED: &'static str = "\x1b[1;31m";
+const BOLD_BLUE: &'static str = "\x1b[1;34m";
+const RESET: &'static str = "\x1b[0m";
+
+/// Renders parse errors, by default without file name and without colours.
+#[derive(Clone, Debug, Default)]
+pub struct ErrorRenderer
+{
+  file_name: Option<String>,
+  colour: bool
+}
+
+impl ErrorRenderer
+{
+  pub fn new() -> ErrorRenderer {
+    ErrorRenderer::default()
+  }
+
+  /// The file name precedes the position of the error.
+  pub fn file_name(mut self, file_name: &str) -> ErrorRenderer {
+    self.file_name = Some(String::from(file_name));
+    self
+  }
+
+  /// Colours the output with ANSI escape codes if `colour` is `true`.
+  pub fn colour(mut self, colour: bool) -> ErrorRenderer {
+    self.colour = colour;
+    self
+  }
+
+  /// `source` must be the input read by the stream of `error`.
+  pub fn render<S>(&self, source: &str, error: &ParseError<S>) -> String where
+   S: StreamPosition
+  {
+    let position = error.farthest_read.position();
+    let line_start = source[..position.offset].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[position.offset..].find('\n').map_or(source.len(), |i| position.offset + i);
+    let line = source[line_start..line_end].trim_right_matches('\r');
+    let line_no = position.line.to_string();
+    let gutter: String = line_no.chars().map(|_| ' ').collect();
+    // Tabulations are kept so the caret is aligned whatever their width.
+    let caret_indent: String = source[line_start..position.offset].chars()
+      .map(|c| if c == '\t' { '\t' } else { ' ' })
+      .collect();
+
+    let mut message = format!("unexpected {}", unexpected(&source[position.offset..]));
+    if !error.expected.is_empty() {
+      message.push_str(", expecting ");
+      message.push_str(error.expected_items().as_str());
+    }
+    let location = match self.file_name {
+      Some(ref file_name) => format!("{}:{}", file_name, position),
+      None => format!("{}", position)
+    };
+
+    let mut rendered = format!("{}{}\n", self.paint(BOLD_RED, "error"), self.paint(BOLD, &format!(": {}", message)));
+    rendered.push_str(&format!("{}{} {}\n", gutter, self.paint(BOLD_BLUE, "-->"), location));
+    rendered.push_str(&format!("{} {}\n", gutter, self.paint(BOLD_BLUE, "|")));
+    rendered.push_str(&format!("{} {}\n", self.paint(BOLD_BLUE, &line_no), self.paint(BOLD_BLUE, "|") + " " + line));
+    rendered.push_str(&format!("{} {} {}{}\n", gutter, self.paint(BOLD_BLUE, "|"), caret_indent, self.paint(BOLD_RED, "^")));
+    if let Some(context) = error.enclosing_rule() {
+      rendered.push_str(&format!("{} {} {}: while parsing {} (started at {})\n",
+        gutter, self.paint(BOLD_BLUE, "="), self.paint(BOLD, "note"),
+        context.rule.replace("_", " "), context.start.position()));
+    }
+    rendered
+  }
+
+  fn paint(&self, style: &str, text: &str) -> String {
+    if self.colour {
+      format!("{}{}{}", style, text, RESET)
+    }
+    else {
+      String::from(text)
+    }
+  }
+}
+
+/// Describes the character starting `rest`.
+fn unexpected(rest: &str) -> String {
+  match rest.chars().next() {
+    None => String::from("end of input"),
+    Some('\n') | Some('\r') => String::from("end of line"),
+    Some(c) => format!("`{}`", c)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use stream::*;
+  use parse_error::*;
+
+  #[test]
+  fn test_render_plain() {
+    let source = "let a = 1 in\n\tlet x 1 in x";
+    let stream = source.stream();
+    let mut error = ParseError::unique(stream.at_offset(20), "=");
+    error.expected.push("[\"a-z\"]");
+    error.expected.push("=");
+    error.within_rule("let_binding", stream.at_offset(14));
+    let rendered = ErrorRenderer::new().file_name("calc.txt").render(source, &error);
+    assert_eq!(rendered,
+      "error: unexpected `1`, expecting `=` or `[\"a-z\"]`\n \
+       --> calc.txt:2:8\n  \
+        |\n\
+       2 | \tlet x 1 in x\n  \
+        | \t      ^\n  \
+        = note: while parsing let binding (started at 2:2)\n");
+  }
+
+  #[test]
+  fn test_render_end_of_input() {
+    let source = "let x =\r\n";
+    let error = ParseError::unique(source.stream().at_offset(7), "expression");
+    let rendered = ErrorRenderer::new().render(source, &error);
+    assert_eq!(rendered,
+      "error: unexpected end of line, expecting `expression`\n \
+       --> 1:8\n  \
+        |\n\
+       1 | let x =\n  \
+        |        ^\n");
+    let end = ParseError::empty(source.stream().at_offset(source.len()));
+    assert!(ErrorRenderer::new().render(source, &end).starts_with("error: unexpected end of input\n"));
+  }
+
+  #[test]
+  fn test_render_colour() {
+    let source = "x";
+    let error = ParseError::unique(source.stream(), "y");
+    let rendered = ErrorRenderer::new().colour(true).render(source, &error);
+    assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: unexpected `x`, expecting `y`\x1b[0m\n"));
+    assert!(rendered.contains("\x1b[1;31m^\x1b[0m"));
+  }
+}
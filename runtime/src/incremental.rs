@@ -0,0 +1,248 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Incremental reparsing.
+//!
+//! The states of memoised rules can be reused after a modification of the input if the rule did not examine the modified text. `IncrementalStream` records the offset following the last byte examined (the *extent*) in a counter shared by all the streams of an input, so memoised rules know the extent of their states. After an edit, `MemoTable::edit` keeps the states of the rules that stopped examining the input before the edited range and moves the ones produced after it.
+
+use stream::*;
+use indentation::*;
+use cst::RuleEvents;
+use std::rc::Rc;
+use std::cell::Cell;
+use std::cmp::{Ordering, max};
+use std::ops::Range;
+
+/// Replacement of the bytes `start..old_end` of the input by a text of `new_end - start` bytes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TextEdit
+{
+  pub start: usize,
+  pub old_end: usize,
+  pub new_end: usize
+}
+
+impl TextEdit
+{
+  pub fn new(range: Range<usize>, replacement: &str) -> TextEdit {
+    assert!(range.start <= range.end, "The range of a text edit must not be reversed.");
+    TextEdit {
+      start: range.start,
+      old_end: range.end,
+      new_end: range.start + replacement.len()
+    }
+  }
+
+  /// Position in the new input of the offset `offset`, which must not be in the edited range.
+  pub fn shift(&self, offset: usize) -> usize {
+    if offset < self.old_end {
+      offset
+    }
+    else {
+      offset - self.old_end + self.new_end
+    }
+  }
+}
+
+/// Records the extent of the input examined by the parser. Streams are compared by their positions only.
+#[derive(Clone, Debug)]
+pub struct IncrementalStream<S>
+{
+  stream: S,
+  examined: Rc<Cell<usize>>
+}
+
+impl<S> IncrementalStream<S>
+{
+  pub fn new(stream: S) -> IncrementalStream<S> {
+    IncrementalStream {
+      stream: stream,
+      examined: Rc::new(Cell::new(0))
+    }
+  }
+
+  pub fn into_inner(self) -> S {
+    self.stream
+  }
+
+  fn examine_until(&self, extent: usize) {
+    self.examined.set(max(self.examined.get(), extent));
+  }
+}
+
+impl<S> StreamOffset for IncrementalStream<S> where
+ S: StreamOffset
+{
+  fn offset(&self) -> usize {
+    self.stream.offset()
+  }
+
+  fn at_offset(&self, offset: usize) -> IncrementalStream<S> {
+    IncrementalStream {
+      stream: self.stream.at_offset(offset),
+      examined: self.examined.clone()
+    }
+  }
+
+  fn start_examination(&self) -> usize {
+    let previous = self.examined.get();
+    self.examined.set(self.offset());
+    previous
+  }
+
+  fn end_examination(&self, previous: usize) -> Option<usize> {
+    let extent = self.examined.get();
+    self.examine_until(previous);
+    Some(extent)
+  }
+
+  fn examine(&self, extent: usize) {
+    self.examine_until(extent);
+  }
+}
+
+/// Reading a character examines the next byte, even at the end of the input since appending text would change the result.
+impl<S> Iterator for IncrementalStream<S> where
+ S: Iterator + StreamOffset
+{
+  type Item = S::Item;
+  fn next(&mut self) -> Option<Self::Item> {
+    let start = self.stream.offset();
+    let item = self.stream.next();
+    self.examine_until(max(start + 1, self.stream.offset()));
+    item
+  }
+}
+
+impl<S> ConsumePrefix<&'static str> for IncrementalStream<S> where
+ S: ConsumePrefix<&'static str> + StreamOffset
+{
+  fn consume_prefix(&mut self, prefix: &'static str) -> bool {
+    let extent = self.stream.offset() + max(prefix.len(), 1);
+    self.examine_until(extent);
+    self.stream.consume_prefix(prefix)
+  }
+}
+
+impl<S> HasNext for IncrementalStream<S> where
+ S: HasNext + StreamOffset
+{
+  fn has_next(&self) -> bool {
+    self.examine_until(self.stream.offset() + 1);
+    self.stream.has_next()
+  }
+}
+
+impl<S> IndentationStream for IncrementalStream<S> where
+ S: IndentationStream
+{
+  fn indent_stack(&self) -> &IndentStack {
+    self.stream.indent_stack()
+  }
+
+  fn set_indent_stack(&mut self, stack: IndentStack) {
+    self.stream.set_indent_stack(stack);
+  }
+}
+
+impl<S> RuleEvents for IncrementalStream<S> where
+ S: RuleEvents
+{
+  fn enter_rule(&mut self, name: &'static str, trivia: bool) {
+    self.stream.enter_rule(name, trivia);
+  }
+
+  fn exit_rule(&mut self) {
+    self.stream.exit_rule();
+  }
+}
+
+impl<S> PartialEq for IncrementalStream<S> where
+ S: PartialEq
+{
+  fn eq(&self, other: &Self) -> bool {
+    self.stream.eq(&other.stream)
+  }
+}
+
+impl<S> Eq for IncrementalStream<S> where S: Eq {}
+
+impl<S> PartialOrd for IncrementalStream<S> where
+ S: PartialOrd
+{
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    self.stream.partial_cmp(&other.stream)
+  }
+}
+
+impl<S> Ord for IncrementalStream<S> where
+ S: Ord
+{
+  fn cmp(&self, other: &Self) -> Ordering {
+    self.stream.cmp(&other.stream)
+  }
+}
+
+forward_position_traits!(IncrementalStream);
+
+#[cfg(test)]
+mod test
+{
+  use super::*;
+  use stream::*;
+
+  #[test]
+  fn test_text_edit() {
+    let edit = TextEdit::new(2..4, "XYZ");
+    assert_eq!(edit, TextEdit { start: 2, old_end: 4, new_end: 5 });
+    assert_eq!(edit.shift(1), 1);
+    assert_eq!(edit.shift(4), 5);
+    assert_eq!(edit.shift(6), 7);
+    let deletion = TextEdit::new(1..3, "");
+    assert_eq!(deletion.shift(3), 1);
+  }
+
+  #[test]
+  fn test_examined_extent() {
+    let stream = IncrementalStream::new("abcd".stream());
+    let outer = stream.start_examination();
+    let mut inner_stream = stream.clone();
+    let inner = inner_stream.start_examination();
+    assert!(!inner_stream.consume_prefix("abx"));
+    assert!(inner_stream.consume_prefix("a"));
+    assert_eq!(inner_stream.end_examination(inner), Some(3));
+    let mut after = inner_stream.at_offset(1);
+    let start = after.start_examination();
+    after.next();
+    assert_eq!(after.end_examination(start), Some(2));
+    assert_eq!(stream.end_examination(outer), Some(3));
+  }
+
+  #[test]
+  fn test_forward_indent_stack() {
+    let mut stream = IncrementalStream::new(IndentStream::new("a".stream()));
+    stream.set_indent_stack(IndentStack::new().push(2));
+    assert!(stream.indent_stack().contains(2));
+    assert!(stream.into_inner().indent_stack().contains(2));
+  }
+
+  #[test]
+  fn test_end_of_input_examined() {
+    let stream = IncrementalStream::new("a".stream());
+    let mut end = stream.at_offset(1);
+    let start = end.start_examination();
+    assert!(end.next().is_none());
+    assert_eq!(end.end_examination(start), Some(2));
+  }
+}
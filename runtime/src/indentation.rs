@@ -0,0 +1,351 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Indentation-sensitive parsing.
+//!
+//! `IndentStream` wraps a stream with the stack of the indentation levels of the enclosing blocks. The combinators `recognize_indent`, `recognize_samedent` and `recognize_dedent` compare the indentation of the next non-blank line with the top of this stack. Since the stack is a persistent structure stored in the stream, it is restored along with the stream when the parser backtracks.
+//!
+//! The indentation of a line is its number of leading spaces, tabulations are rejected as they have no consistent width. These combinators must be used at the beginning of a line, typically right after the newline character.
+
+use stream::*;
+use parse_state::*;
+use std::rc::Rc;
+use std::cmp::Ordering;
+
+/// Persistent stack of indentation levels (in columns). The empty stack has the implicit level `0`.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct IndentStack
+{
+  top: Option<Rc<IndentLevel>>
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct IndentLevel
+{
+  columns: usize,
+  parent: IndentStack
+}
+
+impl IndentStack
+{
+  pub fn new() -> IndentStack {
+    IndentStack { top: None }
+  }
+
+  pub fn top(&self) -> usize {
+    self.top.as_ref().map_or(0, |level| level.columns)
+  }
+
+  pub fn depth(&self) -> usize {
+    let mut depth = 0;
+    let mut current = self;
+    while let Some(ref level) = current.top {
+      depth += 1;
+      current = &level.parent;
+    }
+    depth
+  }
+
+  pub fn push(&self, columns: usize) -> IndentStack {
+    IndentStack {
+      top: Some(Rc::new(IndentLevel {
+        columns: columns,
+        parent: self.clone()
+      }))
+    }
+  }
+
+  /// Popping the empty stack gives the empty stack.
+  pub fn pop(&self) -> IndentStack {
+    self.top.as_ref().map_or(IndentStack::new(), |level| level.parent.clone())
+  }
+
+  pub fn contains(&self, columns: usize) -> bool {
+    let mut current = self;
+    while let Some(ref level) = current.top {
+      if level.columns == columns {
+        return true;
+      }
+      current = &level.parent;
+    }
+    columns == 0
+  }
+}
+
+/// Access to the indentation stack of a stream. It is required by the indentation combinators.
+pub trait IndentationStream
+{
+  fn indent_stack(&self) -> &IndentStack;
+  fn set_indent_stack(&mut self, stack: IndentStack);
+}
+
+/// Adds an indentation stack to a stream. Streams are compared by their positions only.
+#[derive(Clone, Debug)]
+pub struct IndentStream<S>
+{
+  stream: S,
+  stack: IndentStack
+}
+
+impl<S> IndentStream<S>
+{
+  pub fn new(stream: S) -> IndentStream<S> {
+    IndentStream {
+      stream: stream,
+      stack: IndentStack::new()
+    }
+  }
+
+  pub fn into_inner(self) -> S {
+    self.stream
+  }
+}
+
+impl<S> IndentationStream for IndentStream<S>
+{
+  fn indent_stack(&self) -> &IndentStack {
+    &self.stack
+  }
+
+  fn set_indent_stack(&mut self, stack: IndentStack) {
+    self.stack = stack;
+  }
+}
+
+impl<S> Iterator for IndentStream<S> where
+ S: Iterator
+{
+  type Item = S::Item;
+  fn next(&mut self) -> Option<Self::Item> {
+    self.stream.next()
+  }
+}
+
+impl<S> PartialEq for IndentStream<S> where
+ S: PartialEq
+{
+  fn eq(&self, other: &Self) -> bool {
+    self.stream.eq(&other.stream)
+  }
+}
+
+impl<S> Eq for IndentStream<S> where S: Eq {}
+
+impl<S> PartialOrd for IndentStream<S> where
+ S: PartialOrd
+{
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    self.stream.partial_cmp(&other.stream)
+  }
+}
+
+impl<S> Ord for IndentStream<S> where
+ S: Ord
+{
+  fn cmp(&self, other: &Self) -> Ordering {
+    self.stream.cmp(&other.stream)
+  }
+}
+
+forward_position_traits!(IndentStream);
+
+impl<S, P> ConsumePrefix<P> for IndentStream<S> where
+ S: ConsumePrefix<P>
+{
+  fn consume_prefix(&mut self, prefix: P) -> bool {
+    self.stream.consume_prefix(prefix)
+  }
+}
+
+impl<S> HasNext for IndentStream<S> where
+ S: HasNext
+{
+  fn has_next(&self) -> bool {
+    self.stream.has_next()
+  }
+}
+
+/// Skips the blank lines and the leading spaces of the next non-blank line. Returns the stream positioned on the first character of this line and its indentation. The end of the input has the indentation `0`.
+fn next_line_indentation<S>(stream: &S) -> Result<(S, usize), S> where
+ S: CharStream
+{
+  let mut current = stream.clone();
+  let mut columns = 0;
+  loop {
+    let mut next = current.clone();
+    match next.next() {
+      Some(' ') => { columns += 1; }
+      Some('\r') => (),
+      Some('\n') => { columns = 0; }
+      Some('\t') => { return Err(current); }
+      Some(_) => { return Ok((current, columns)); }
+      None => { return Ok((current, 0)); }
+    }
+    current = next;
+  }
+}
+
+/// Succeeds if the next line is more indented than the current block and pushes its indentation. The blank lines and the indentation are consumed.
+pub fn recognize_indent<S>(stream: S) -> ParseState<S, ()> where
+ S: CharStream + IndentationStream
+{
+  match next_line_indentation(&stream) {
+    Ok((mut line, columns)) if columns > stream.indent_stack().top() => {
+      let stack = stream.indent_stack().push(columns);
+      line.set_indent_stack(stack);
+      ParseState::stateless(line)
+    }
+    Ok((line, _)) => ParseState::error(line, "indented block"),
+    Err(tab) => ParseState::error(tab, "indentation with spaces")
+  }
+}
+
+/// Succeeds if the next line has the indentation of the current block. The blank lines and the indentation are consumed.
+pub fn recognize_samedent<S>(stream: S) -> ParseState<S, ()> where
+ S: CharStream + IndentationStream
+{
+  match next_line_indentation(&stream) {
+    Ok((line, columns)) if columns == stream.indent_stack().top() => {
+      ParseState::stateless(line)
+    }
+    Ok((line, _)) => ParseState::error(line, "line at the same indentation"),
+    Err(tab) => ParseState::error(tab, "indentation with spaces")
+  }
+}
+
+/// Succeeds if the next line is less indented than the current block and closes this block. Nothing is consumed so several blocks can be closed by the same line, the indentation of this line must be the one of an enclosing block.
+pub fn recognize_dedent<S>(mut stream: S) -> ParseState<S, ()> where
+ S: CharStream + IndentationStream
+{
+  match next_line_indentation(&stream) {
+    Ok((line, columns)) if columns < stream.indent_stack().top() => {
+      let stack = stream.indent_stack().pop();
+      if stack.contains(columns) {
+        stream.set_indent_stack(stack);
+        ParseState::stateless(stream)
+      }
+      else {
+        ParseState::error(line, "indentation matching an enclosing block")
+      }
+    }
+    Ok((line, _)) => ParseState::error(line, "end of the indented block"),
+    Err(tab) => ParseState::error(tab, "indentation with spaces")
+  }
+}
+
+#[cfg(test)]
+mod test
+{
+  use super::*;
+  use str_stream::*;
+  use stream::*;
+  use parse_state::*;
+
+  fn block<'a>(stream: IndentStream<StrStream<'a>>) -> ParseState<IndentStream<StrStream<'a>>, ()> {
+    recognize_indent(stream)
+      .and_then(|success| statements(success.stream))
+      .and_then(|success| recognize_dedent(success.stream))
+  }
+
+  // statements = statement (SAMEDENT statement)*
+  fn statements<'a>(stream: IndentStream<StrStream<'a>>) -> ParseState<IndentStream<StrStream<'a>>, ()> {
+    let mut state = statement(stream);
+    while state.is_successful() {
+      let next = state.stream();
+      let next = recognize_samedent(next).and_then(|success| statement(success.stream));
+      if !state.soft_merge(next) {
+        break;
+      }
+    }
+    state
+  }
+
+  // statement = "x" "\n" / "if" "\n" block
+  fn statement<'a>(stream: IndentStream<StrStream<'a>>) -> ParseState<IndentStream<StrStream<'a>>, ()> {
+    let mut s = stream.clone();
+    if s.consume_prefix("x\n") {
+      ParseState::stateless(s)
+    }
+    else if s.consume_prefix("if\n") {
+      block(s)
+    }
+    else {
+      ParseState::error(stream, "statement")
+    }
+  }
+
+  fn program(input: &'static str) -> ParseState<IndentStream<StrStream<'static>>, ()> {
+    statements(IndentStream::new(input.stream()))
+  }
+
+  #[test]
+  fn test_indent_stack() {
+    let empty = IndentStack::new();
+    let one = empty.push(2);
+    let two = one.push(4);
+    assert_eq!(empty.top(), 0);
+    assert_eq!(two.top(), 4);
+    assert_eq!(two.depth(), 2);
+    assert!(two.contains(2) && two.contains(0) && !two.contains(3));
+    assert_eq!(two.pop(), one);
+    assert_eq!(one.pop().pop(), empty);
+    assert_eq!(one.depth(), 1);
+  }
+
+  #[test]
+  fn test_nested_blocks() {
+    let inputs = [
+      "x\n",
+      "x\nif\n  x\n  x\nx\n",
+      "if\n  if\n    x\n\n    x\nx\n",
+      "if\n  if\n    x\n  x\n",
+      "if\n   x\n   if\n        x\n",
+    ];
+    for input in inputs.iter() {
+      let state = program(input);
+      assert!(state.is_successful(), "`{}` should be accepted.", input);
+      let stream = state.stream();
+      assert!(!stream.has_next(), "`{}` should be fully read.", input);
+      assert_eq!(stream.indent_stack().depth(), 0);
+    }
+  }
+
+  #[test]
+  fn test_expected_indentation() {
+    let error_of = |input| {
+      match program(input).into_result() {
+        Ok((success, error)) => {
+          assert!(success.partial_read(), "`{}` should be partially read.", input);
+          error
+        }
+        Err(error) => error
+      }
+    };
+    assert_eq!(error_of("if\nx\n").expected, vec!["indented block"]);
+    let mut expected = error_of("if\n    x\n  x\n").expected;
+    expected.sort();
+    assert_eq!(expected, vec!["indentation matching an enclosing block", "line at the same indentation"]);
+    assert_eq!(error_of("if\n\tx\n").expected, vec!["indentation with spaces"]);
+  }
+
+  #[test]
+  fn test_backtracking_restores_stack() {
+    let stream = IndentStream::new("  x".stream());
+    let indented = recognize_indent(stream.clone());
+    assert!(indented.is_successful());
+    assert_eq!(indented.stream().indent_stack().top(), 2);
+    assert_eq!(stream.indent_stack().top(), 0);
+    assert!(!recognize_samedent(stream).is_successful());
+  }
+}
@@ -19,18 +19,45 @@
 #![feature(str_char)]
 
 pub use str_stream::*;
+pub use read_stream::*;
+pub use chunk_stream::*;
 pub use stream::*;
 pub use parse_success::*;
 pub use parse_error::*;
 pub use parse_state::*;
 pub use combinators::*;
-
+pub use memo::*;
+pub use indentation::*;
+pub use cst::*;
+pub use recovery::*;
+pub use incremental::*;
+pub use position::*;
+pub use diagnostic::*;
+pub use report::*;
+pub use trace::*;
+pub use profile::*;
+pub use char_class::*;
+
+#[macro_use]
+pub mod stream;
 pub mod str_stream;
+pub mod read_stream;
+pub mod chunk_stream;
 pub mod parse_success;
 pub mod parse_error;
 pub mod parse_state;
 pub mod combinators;
-pub mod stream;
+pub mod memo;
+pub mod indentation;
+pub mod cst;
+pub mod recovery;
+pub mod incremental;
+pub mod position;
+pub mod diagnostic;
+pub mod report;
+pub mod trace;
+pub mod profile;
+pub mod char_class;
 
 /// Represents a final result from a parsing state. It is obtained with `ParseState::into_result`. `ParseError<S>` represents the expected items to continue the parsing, it is available even in case of success.
 pub type ParseResult<S, T> = Result<(ParseSuccess<S, T>, ParseError<S>), ParseError<S>>;
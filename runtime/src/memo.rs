@@ -0,0 +1,157 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Memoisation of the parsing states produced by rules annotated with `#[memo]` (packrat parsing).
+//!
+//! The generated code owns one table per memoised function, they are gathered in the structure `Memo` of the grammar module. States are stored with byte offsets instead of streams so that a table does not borrow the input.
+//!
+//! Tables can be kept from one parsing to the next one when the input is modified, see `MemoTable::edit`.
+
+use parse_state::ParseState;
+use stream::StreamOffset;
+use incremental::TextEdit;
+use std::collections::HashMap;
+
+/// Parsing states of a single rule indexed by the offset at which the rule has been called.
+#[derive(Clone)]
+pub struct MemoTable<T>
+{
+  states: HashMap<usize, MemoEntry<T>>
+}
+
+#[derive(Clone)]
+struct MemoEntry<T>
+{
+  state: ParseState<usize, T>,
+  /// Offset following the last byte examined to produce `state`, if it is known.
+  extent: Option<usize>
+}
+
+impl<T> MemoTable<T>
+{
+  pub fn new() -> MemoTable<T> {
+    MemoTable {
+      states: HashMap::new()
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.states.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.states.is_empty()
+  }
+
+  pub fn clear(&mut self) {
+    self.states.clear();
+  }
+}
+
+impl<T> MemoTable<T> where
+ T: Clone
+{
+  /// Retrieves the state produced by the rule when it was called at the position of `stream`. The data is cloned on hit.
+  pub fn get<S>(&self, stream: &S) -> Option<ParseState<S, T>> where
+   S: StreamOffset
+  {
+    self.states.get(&stream.offset())
+      .map(|entry| {
+        if let Some(extent) = entry.extent {
+          stream.examine(extent);
+        }
+        entry.state.clone().map_stream(|offset| stream.at_offset(offset))
+      })
+  }
+
+  /// Records the state produced by the rule called at the position of `stream`. `extent` is the offset following the last byte examined by the rule, as given by `StreamOffset::end_examination`.
+  pub fn insert<S>(&mut self, stream: &S, state: &ParseState<S, T>, extent: Option<usize>) where
+   S: StreamOffset + Clone
+  {
+    let state = state.clone().map_stream(|s| s.offset());
+    self.states.insert(stream.offset(), MemoEntry { state: state, extent: extent });
+  }
+
+  /// Updates the table after the input has been modified by `edit`. The states produced before the edited range, without examining it, are kept, those produced after are moved by the difference of length and the others are removed. States without a known extent are kept only if they are after the edited range.
+  pub fn edit(&mut self, edit: &TextEdit) {
+    let states = self.states.drain().collect::<Vec<_>>();
+    for (start, entry) in states {
+      if start >= edit.old_end {
+        let entry = MemoEntry {
+          state: entry.state.map_stream(|offset| edit.shift(offset)),
+          extent: entry.extent.map(|extent| edit.shift(extent))
+        };
+        self.states.insert(edit.shift(start), entry);
+      }
+      else if entry.extent.map_or(false, |extent| extent <= edit.start) {
+        self.states.insert(start, entry);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use parse_state::ParseState;
+  use incremental::TextEdit;
+  use stream::*;
+
+  #[test]
+  fn test_memo_table() {
+    let input = "abc";
+    let start = input.stream();
+    let mut end = start.clone();
+    end.next();
+    let mut table = MemoTable::new();
+    assert!(table.get(&start).is_none());
+    table.insert(&start, &ParseState::success(end.clone(), 'a'), None);
+    assert_eq!(table.len(), 1);
+    let state = table.get(&start.clone()).unwrap();
+    assert!(state.stream() == end);
+    assert_eq!(state.unwrap_data(), 'a');
+    assert!(table.get(&end).is_none());
+  }
+
+  #[test]
+  fn test_memo_table_error() {
+    let start = "abc".stream();
+    let mut table: MemoTable<()> = MemoTable::new();
+    table.insert(&start, &ParseState::error(start.clone(), "d"), None);
+    let state = table.get(&start).unwrap();
+    assert!(!state.is_successful());
+    assert!(state.error.farthest_read == start);
+    assert_eq!(state.error.expected, vec!["d"]);
+  }
+
+  #[test]
+  fn test_memo_table_edit() {
+    let input = "abcdef";
+    let stream = input.stream();
+    let mut table = MemoTable::new();
+    for (start, end, extent) in vec![(0, 1, Some(2)), (1, 3, Some(4)), (4, 5, Some(6)), (5, 6, None)] {
+      table.insert(&stream.at_offset(start), &ParseState::success(stream.at_offset(end), start), extent);
+    }
+    // "abcdef" becomes "abXYZef".
+    table.edit(&TextEdit::new(2..4, "XYZ"));
+    let edited = "abXYZef".stream();
+    assert_eq!(table.len(), 3);
+    assert_eq!(table.get(&edited.at_offset(0)).unwrap().unwrap_data(), 0);
+    assert!(table.get(&edited.at_offset(1)).is_none());
+    let moved = table.get(&edited.at_offset(5)).unwrap();
+    assert_eq!(moved.stream().offset(), 6);
+    assert_eq!(moved.unwrap_data(), 4);
+    assert_eq!(table.get(&edited.at_offset(6)).unwrap().unwrap_data(), 5);
+  }
+}
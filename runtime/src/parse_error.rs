@@ -15,7 +15,8 @@
 //! Data carried by a parsing state even if the parsing state is currently successful.
 
 use {Location, CodeSnippet};
-use std::collections::hash_set::HashSet;
+use parse_state::ParseState;
+use std::collections::BTreeSet;
 use std::cmp::Ord;
 use std::fmt::{Formatter, Display, Error};
 
@@ -26,7 +27,19 @@ pub struct ParseError<S>
   /// The farthest read into the stream at which we encountered an error.
   pub farthest_read: S,
   /// Expected items at position `farthest_read`. Duplicate entries are possible.
-  pub expected: Vec<&'static str>
+  pub expected: Vec<&'static str>,
+  /// Rules being parsed when the error at `farthest_read` occurred, from the innermost to the outermost.
+  pub context: Vec<RuleContext<S>>
+}
+
+/// Rule `rule` started at the position `start`.
+#[derive(Clone, Debug)]
+pub struct RuleContext<S>
+{
+  pub rule: &'static str,
+  pub start: S,
+  /// `true` if the rule read some input before the error, that is if `start` is before `farthest_read`.
+  pub read_input: bool
 }
 
 impl<S> ParseError<S>
@@ -34,22 +47,51 @@ impl<S> ParseError<S>
   pub fn unique(farthest_read: S, expect: &'static str) -> ParseError<S> {
     ParseError {
       farthest_read: farthest_read,
-      expected: vec![expect]
+      expected: vec![expect],
+      context: vec![]
     }
   }
 
   pub fn empty(farthest_read: S) -> ParseError<S> {
     ParseError {
       farthest_read: farthest_read,
-      expected: vec![]
+      expected: vec![],
+      context: vec![]
+    }
+  }
+
+  /// Maps `op` to the streams of the error while keeping the expected items unchanged.
+  pub fn map_stream<S2, F>(self, op: F) -> ParseError<S2> where
+   F: Fn(S) -> S2
+  {
+    ParseError {
+      farthest_read: op(self.farthest_read),
+      expected: self.expected,
+      context: self.context.into_iter()
+        .map(|context| RuleContext {
+          rule: context.rule,
+          start: op(context.start),
+          read_input: context.read_input
+        })
+        .collect()
     }
   }
 
+  /// The innermost rule that read some input before failing. The rules started at `farthest_read` are skipped because they are the items expected, for example a keyword.
+  pub fn enclosing_rule(&self) -> Option<&RuleContext<S>> {
+    self.context.iter().find(|context| context.read_input)
+  }
+
+  /// Expected items without duplicates and sorted.
+  pub fn unique_expected(&self) -> Vec<&'static str> {
+    let expected: BTreeSet<&'static str> = self.expected.iter().cloned().collect();
+    expected.into_iter().collect()
+  }
+
   pub fn expected_items(&self) -> String {
     let mut desc = String::new();
     if self.expected.len() > 0 {
-      let expected: HashSet<&'static str> = self.expected.clone().into_iter().collect();
-      for expect in expected {
+      for expect in self.unique_expected() {
         desc.push('`');
         desc.push_str(expect);
         desc.push_str("` or ");
@@ -64,7 +106,7 @@ impl<S> ParseError<S>
 impl<S> ParseError<S> where
  S: Ord
 {
-  /// Merge two parsing errors. We only keep information of the error that occurred the farthest in the stream. In case of equality, both expected item lists are merged into a new one. It does not remove identical expected items.
+  /// Merge two parsing errors. We only keep information of the error that occurred the farthest in the stream. In case of equality, both expected item lists are merged into a new one and the context of `self` is kept unless it is empty. It does not remove identical expected items.
   pub fn merge(mut self, other: ParseError<S>) -> ParseError<S> {
     self.merge_in_place(other);
     self
@@ -76,14 +118,40 @@ impl<S> ParseError<S> where
     else if self.farthest_read < other.farthest_read {
       self.farthest_read = other.farthest_read;
       self.expected = other.expected;
+      self.context = other.context;
     }
     else {
       self.expected.extend(other.expected.into_iter());
+      if self.context.is_empty() {
+        self.context = other.context;
+      }
+    }
+  }
+
+  /// Records that the error occurred while parsing the rule `rule` started at `start`. Nothing is recorded if no item is expected since there is no error.
+  pub fn within_rule(&mut self, rule: &'static str, start: S) {
+    if !self.expected.is_empty() {
+      let read_input = start < self.farthest_read;
+      self.context.push(RuleContext {
+        rule: rule,
+        start: start,
+        read_input: read_input
+      });
     }
   }
 }
 
-/// Prints an error message of the form: ```1:1: unexpected `a+1`, expecting `(` or `["0-9"]`.``` where `1:1` is the line and the column where the error occurred.
+/// Calls `rule` and records the rule `name` in the context of the error produced. It is called by the rules of the grammars annotated with `#![error_context]`.
+pub fn rule_context<S, T, F>(stream: S, name: &'static str, rule: F) -> ParseState<S, T> where
+ S: Clone + Ord,
+ F: FnOnce(S) -> ParseState<S, T>
+{
+  let mut state = rule(stream.clone());
+  state.error.within_rule(name, stream);
+  state
+}
+
+/// Prints an error message of the form: ```1:1: unexpected `a+1`, expecting `(` or `["0-9"]`.``` where `1:1` is the line and the column where the error occurred. The enclosing rule is described if any, as in ```3:9: unexpected `1`, expecting `=`, while parsing let binding (started at 3:5).```
 impl<S> Display for ParseError<S> where
  S: Location + CodeSnippet
 {
@@ -91,8 +159,13 @@ impl<S> Display for ParseError<S> where
     let location = self.farthest_read.location();
     let expected = self.expected_items();
     let snippet = self.farthest_read.code_snippet(10usize);
-    formatter.write_fmt(
-      format_args!("{}: unexpected `{}`, expecting {}.", location, snippet, expected))
+    try!(formatter.write_fmt(
+      format_args!("{}: unexpected `{}`, expecting {}", location, snippet, expected)));
+    if let Some(context) = self.enclosing_rule() {
+      try!(formatter.write_fmt(format_args!(", while parsing {} (started at {})",
+        context.rule.replace("_", " "), context.start.location())));
+    }
+    formatter.write_str(".")
   }
 }
 
@@ -101,6 +174,8 @@ impl<S> Display for ParseError<S> where
 mod test {
   use super::*;
   use stream::*;
+  use str_stream::*;
+  use parse_state::*;
 
   #[test]
   fn test_error_merge() {
@@ -117,9 +192,60 @@ mod test {
     let err2_merge = err2.clone().merge(err2.clone());
     assert!(err2_merge.farthest_read == err2.farthest_read);
     assert!(err2_merge.expected == vec!["err2", "err2"]);
+    let err0 = ParseError::unique(err2.farthest_read.clone(), "err0");
+    assert_eq!(err2.clone().merge(err0).expected_items(), "`err0` or `err2`");
 
     let err2_1_merge = err2.clone().merge(err1.clone());
     assert!(err2_1_merge.farthest_read == err1.farthest_read);
     assert!(err2_1_merge.expected == vec!["err1"]);
   }
+
+  // let_binding = let_kw "x" eq_kw "1"
+  fn let_binding<'a>(stream: StrStream<'a>) -> ParseState<StrStream<'a>, ()> {
+    rule_context(stream, "let_binding", |stream| {
+      let mut s = stream.clone();
+      if !s.consume_prefix("let ") {
+        return ParseState::error(stream, "let");
+      }
+      s.consume_prefix("x");
+      rule_context(s, "eq_kw", |stream| {
+        let mut s = stream.clone();
+        if s.consume_prefix("=") { ParseState::success(s, ()) }
+        else { ParseState::error(stream, "=") }
+      })
+    })
+  }
+
+  #[test]
+  fn test_rule_context() {
+    let state = rule_context("\nlet x1".stream(), "program", |mut stream| {
+      stream.next();
+      let_binding(stream)
+    });
+    let error = state.error;
+    assert_eq!(error.context.iter().map(|c| c.rule).collect::<Vec<_>>(),
+      vec!["eq_kw", "let_binding", "program"]);
+    assert_eq!(error.context.iter().map(|c| c.read_input).collect::<Vec<_>>(),
+      vec![false, true, true]);
+    assert_eq!(error.enclosing_rule().unwrap().rule, "let_binding");
+    assert_eq!(format!("{}", error),
+      "2:6: unexpected `1`, expecting `=`, while parsing let binding (started at 2:1).");
+
+    let success = rule_context("x".stream(), "program", |stream| ParseState::success(stream, ()));
+    assert!(success.error.context.is_empty());
+  }
+
+  #[test]
+  fn test_context_merge() {
+    let mut s1 = "ab".stream();
+    let s2 = s1.clone();
+    s1.next();
+    let mut err1 = ParseError::unique(s1.clone(), "err1");
+    err1.within_rule("r1", s2.clone());
+    let mut err2 = ParseError::unique(s1.clone(), "err2");
+    err2.within_rule("r2", s2.clone());
+    assert_eq!(err1.clone().merge(err2.clone()).context[0].rule, "r1");
+    assert_eq!(ParseError::unique(s1, "err3").merge(err2.clone()).context[0].rule, "r2");
+    assert!(ParseError::unique(s2, "err4").merge(err1).context[0].rule == "r1");
+  }
 }
@@ -22,6 +22,7 @@ use ParseResult;
 use std::cmp::Ord;
 use std::fmt::{Formatter, Debug, Error};
 
+#[derive(Clone)]
 pub struct ParseState<S, T>
 {
   /// Even in case of success, we keep error information in case we fail later. Think about parsing "abaa" with `"ab"* "c"`, it will directly fails on `"c"`, so it is better to report an error such as:
@@ -118,6 +119,16 @@ impl<S, T> ParseState<S, T>
     }
   }
 
+  /// Maps `op` to every stream contained in the state, the successful one and the one of the errors. It is used to store a state independently of the stream (see `MemoTable`).
+  pub fn map_stream<S2, F>(self, op: F) -> ParseState<S2, T> where
+   F: Fn(S) -> S2
+  {
+    ParseState {
+      error: self.error.map_stream(&op),
+      success: self.success.map(|success| success.map_stream(&op))
+    }
+  }
+
   /// Calls `op` if the state is not successful, otherwise returns the `self` unchanged.
   #[inline]
   pub fn or_else<F>(self, op: F) -> ParseState<S, T> where
@@ -226,6 +237,12 @@ impl<S, T> ParseState<S, T> where
     self.or_else(|err| op().merge_error(err))
   }
 
+  /// Returns `true` if the error of `self` occurred after `stream`, merging an error occurring at `stream` would leave `self` unchanged.
+  #[inline]
+  pub fn error_beyond(&self, stream: &S) -> bool {
+    &self.error.farthest_read > stream
+  }
+
   /// Merge error lists of `self` and `error`. It does not remove duplicate entries.
   #[inline]
   pub fn merge_error(mut self, error: ParseError<S>) -> ParseState<S, T> {
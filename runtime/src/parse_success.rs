@@ -17,7 +17,7 @@
 use HasNext;
 
 /// Type `ParseSuccess` contains information of a successful parsing state.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ParseSuccess<S, T>
 {
   /// The current stream that can be partially or fully consumed.
@@ -46,6 +46,17 @@ impl<S, T> ParseSuccess<S, T>
       data: op(self.data)
     }
   }
+
+  /// Maps `op` to the current `stream` while keeping `data` unchanged.
+  #[inline]
+  pub fn map_stream<S2, F>(self, op: F) -> ParseSuccess<S2, T> where
+   F: FnOnce(S) -> S2
+  {
+    ParseSuccess {
+      stream: op(self.stream),
+      data: self.data
+    }
+  }
 }
 
 impl<S, T> ParseSuccess<S, T> where
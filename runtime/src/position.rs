@@ -0,0 +1,205 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Line and column of a position in the input.
+//!
+//! The streams compute the position of an error when it is requested, which only happens on the error path. `LineIndex` stores the offset of the beginning of each line, it is built once per input and a position is then found with a binary search, it should be used when many positions of the same input are needed. Lines are ended by `\n`, so `\r\n` is a single line ending. Columns can be counted in bytes, characters or UTF-16 code units (as required by the Language Server Protocol) and tabulations can be expanded to the next tab stop.
+
+use std::fmt::{Formatter, Display, Error};
+
+/// Position in the input. `line` and `column` start at `1`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Position
+{
+  /// Offset in bytes from the beginning of the input.
+  pub offset: usize,
+  pub line: usize,
+  pub column: usize
+}
+
+impl Position
+{
+  pub fn new(offset: usize, line: usize, column: usize) -> Position {
+    Position {
+      offset: offset,
+      line: line,
+      column: column
+    }
+  }
+}
+
+/// Prints `line:column`.
+impl Display for Position
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("{}:{}", self.line, self.column))
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColumnUnit
+{
+  Bytes,
+  Chars,
+  Utf16
+}
+
+/// How columns are counted. By default, a column is a byte and a tabulation counts for one column.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ColumnConfig
+{
+  pub unit: ColumnUnit,
+  /// A tabulation moves the column to the next multiple of `tab_width` (plus one).
+  pub tab_width: usize
+}
+
+impl Default for ColumnConfig
+{
+  fn default() -> ColumnConfig {
+    ColumnConfig {
+      unit: ColumnUnit::Bytes,
+      tab_width: 1
+    }
+  }
+}
+
+impl ColumnConfig
+{
+  /// Columns as expected by the Language Server Protocol, they must still be decremented by one since they start at `0` in this protocol.
+  pub fn utf16() -> ColumnConfig {
+    ColumnConfig {
+      unit: ColumnUnit::Utf16,
+      tab_width: 1
+    }
+  }
+
+  /// Column following the character `c` placed at the column `column`.
+  pub fn next_column(&self, column: usize, c: char) -> usize {
+    if c == '\t' && self.tab_width > 1 {
+      ((column - 1) / self.tab_width + 1) * self.tab_width + 1
+    }
+    else {
+      column + self.width(c)
+    }
+  }
+
+  fn width(&self, c: char) -> usize {
+    match self.unit {
+      ColumnUnit::Bytes => c.len_utf8(),
+      ColumnUnit::Chars => 1,
+      ColumnUnit::Utf16 => c.len_utf16()
+    }
+  }
+}
+
+/// Offsets of the beginning of the lines of an input.
+#[derive(Clone, Debug)]
+pub struct LineIndex
+{
+  line_starts: Vec<usize>
+}
+
+impl LineIndex
+{
+  pub fn new(text: &str) -> LineIndex {
+    LineIndex::from_pieces(Some(text).into_iter())
+  }
+
+  /// Index of the text formed by the concatenation of `pieces`.
+  pub fn from_pieces<'a, I>(pieces: I) -> LineIndex where
+   I: Iterator<Item=&'a str>
+  {
+    let mut line_starts = vec![0];
+    let mut piece_start = 0;
+    for piece in pieces {
+      line_starts.extend(piece.bytes()
+        .enumerate()
+        .filter(|&(_, byte)| byte == b'\n')
+        .map(|(offset, _)| piece_start + offset + 1));
+      piece_start += piece.len();
+    }
+    LineIndex {
+      line_starts: line_starts
+    }
+  }
+
+  pub fn lines_count(&self) -> usize {
+    self.line_starts.len()
+  }
+
+  /// Offset of the beginning of the line `line` (starting at `1`).
+  pub fn line_start(&self, line: usize) -> usize {
+    self.line_starts[line - 1]
+  }
+
+  /// Line containing `offset`, starting at `1`.
+  pub fn line(&self, offset: usize) -> usize {
+    match self.line_starts.binary_search(&offset) {
+      Ok(index) => index + 1,
+      Err(index) => index
+    }
+  }
+
+  /// `text` must be the input from which the index was built and `offset` must be at a character boundary.
+  pub fn position(&self, text: &str, offset: usize, config: &ColumnConfig) -> Position {
+    let line = self.line(offset);
+    let column = text[self.line_start(line)..offset].chars()
+      .fold(1, |column, c| config.next_column(column, c));
+    Position::new(offset, line, column)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_line_index() {
+    let text = "ab\r\ncd\n\nef";
+    let index = LineIndex::new(text);
+    let config = ColumnConfig::default();
+    assert_eq!(index.lines_count(), 4);
+    assert_eq!(index.position(text, 0, &config), Position::new(0, 1, 1));
+    assert_eq!(index.position(text, 2, &config), Position::new(2, 1, 3));
+    assert_eq!(index.position(text, 4, &config), Position::new(4, 2, 1));
+    assert_eq!(index.position(text, 7, &config), Position::new(7, 3, 1));
+    assert_eq!(index.position(text, 10, &config), Position::new(10, 4, 3));
+    assert_eq!(format!("{}", index.position(text, 5, &config)), "2:2");
+  }
+
+  #[test]
+  fn test_line_index_from_pieces() {
+    let pieces = ["ab\n", "", "c", "d\ne\n"];
+    let index = LineIndex::from_pieces(pieces.iter().cloned());
+    assert_eq!(index.lines_count(), 4);
+    assert_eq!(index.line_start(2), 3);
+    assert_eq!(index.line_start(3), 6);
+    assert_eq!(index.line(5), 2);
+  }
+
+  #[test]
+  fn test_column_units() {
+    let text = "é𝄞\tx";
+    let index = LineIndex::new(text);
+    let x = text.len() - 1;
+    let column = |unit, tab_width| {
+      index.position(text, x, &ColumnConfig { unit: unit, tab_width: tab_width }).column
+    };
+    assert_eq!(column(ColumnUnit::Bytes, 1), 8);
+    assert_eq!(column(ColumnUnit::Chars, 1), 4);
+    assert_eq!(column(ColumnUnit::Utf16, 1), 5);
+    assert_eq!(column(ColumnUnit::Chars, 4), 5);
+    assert_eq!(column(ColumnUnit::Utf16, 8), 9);
+  }
+}
@@ -0,0 +1,150 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Profiling of the generated functions.
+//!
+//! With the grammar attribute `#![profile]`, every generated function takes an extra argument `profiler: &mut Profiler` recording, for each function, the number of invocations, the number of failures and the number of bytes re-read. A function re-reads the bytes it examines that were already examined before it started, which happens when the parser backtracks. The bytes examined by a function end at its final position or at the position of its error if it is farther.
+
+use stream::*;
+use parse_state::*;
+use std::collections::HashMap;
+use std::cmp::{min, max};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ProfileEntry
+{
+  /// Name of the generated function, the functions of the expressions are named `*_in_rule_*`.
+  pub function: &'static str,
+  pub invocations: usize,
+  pub failures: usize,
+  pub bytes_reread: usize
+}
+
+impl ProfileEntry
+{
+  fn new(function: &'static str) -> ProfileEntry {
+    ProfileEntry {
+      function: function,
+      invocations: 0,
+      failures: 0,
+      bytes_reread: 0
+    }
+  }
+}
+
+/// Obtained when a function starts and given back when it ends.
+#[derive(Clone, Copy, Debug)]
+pub struct ProfileStart
+{
+  offset: usize,
+  high_water_mark: usize
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct Profiler
+{
+  entries: HashMap<&'static str, ProfileEntry>,
+  /// Offset following the farthest byte examined so far.
+  high_water_mark: usize
+}
+
+impl Profiler
+{
+  pub fn new() -> Profiler {
+    Profiler::default()
+  }
+
+  pub fn enter<S>(&mut self, stream: &S) -> ProfileStart where
+   S: StreamOffset
+  {
+    ProfileStart {
+      offset: stream.offset(),
+      high_water_mark: self.high_water_mark
+    }
+  }
+
+  /// The function `function` started at `start` produced `state`.
+  pub fn exit<S, T>(&mut self, function: &'static str, start: ProfileStart, state: &ParseState<S, T>) where
+   S: StreamOffset
+  {
+    let mut end = state.error.farthest_read.offset();
+    if let Some(ref success) = state.success {
+      end = max(end, success.stream.offset());
+    }
+    let reread = min(end, start.high_water_mark).saturating_sub(start.offset);
+    self.high_water_mark = max(self.high_water_mark, end);
+    let entry = self.entries.entry(function).or_insert_with(|| ProfileEntry::new(function));
+    entry.invocations += 1;
+    entry.bytes_reread += reread;
+    if !state.is_successful() {
+      entry.failures += 1;
+    }
+  }
+
+  /// Entries sorted by decreasing number of invocations, then by decreasing number of bytes re-read and by name.
+  pub fn entries(&self) -> Vec<ProfileEntry> {
+    let mut entries: Vec<ProfileEntry> = self.entries.values().cloned().collect();
+    entries.sort_by(|a, b|
+      (b.invocations, b.bytes_reread, a.function).cmp(&(a.invocations, a.bytes_reread, b.function)));
+    entries
+  }
+
+  /// Table of the entries, one function per line.
+  pub fn report(&self) -> String {
+    let entries = self.entries();
+    let width = entries.iter()
+      .map(|entry| entry.function.len())
+      .fold("function".len(), max);
+    let mut report = format!("{:<width$}  {:>11}  {:>8}  {:>13}\n",
+      "function", "invocations", "failures", "bytes re-read", width = width);
+    for entry in entries {
+      report.push_str(&format!("{:<width$}  {:>11}  {:>8}  {:>13}\n",
+        entry.function, entry.invocations, entry.failures, entry.bytes_reread, width = width));
+    }
+    report
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use stream::*;
+  use parse_state::*;
+
+  #[test]
+  fn test_profiler() {
+    let mut profiler = Profiler::new();
+    let stream = "abcdef".stream();
+    // `ab` fails at `c`, then the alternative `abcd` succeeds.
+    let first = profiler.enter(&stream);
+    let failure = ParseState::<_, ()>::error(stream.at_offset(2), "x");
+    profiler.exit("recognize_ab", first, &failure);
+    let second = profiler.enter(&stream);
+    let inner = profiler.enter(&stream.at_offset(1));
+    profiler.exit("recognize_b", inner, &ParseState::success(stream.at_offset(2), ()));
+    profiler.exit("recognize_abcd", second, &ParseState::success(stream.at_offset(4), ()));
+    let third = profiler.enter(&stream.at_offset(4));
+    profiler.exit("recognize_b", third, &ParseState::<_, ()>::error(stream.at_offset(4), "b"));
+
+    let entries = profiler.entries();
+    assert_eq!(entries[0], ProfileEntry { function: "recognize_b", invocations: 2, failures: 1, bytes_reread: 1 });
+    assert_eq!(entries[1], ProfileEntry { function: "recognize_abcd", invocations: 1, failures: 0, bytes_reread: 2 });
+    assert_eq!(entries[2], ProfileEntry { function: "recognize_ab", invocations: 1, failures: 1, bytes_reread: 0 });
+    assert_eq!(profiler.report(),
+      "function        invocations  failures  bytes re-read\n\
+       recognize_b               2         1              1\n\
+       recognize_abcd            1         0              2\n\
+       recognize_ab              1         1              0\n");
+  }
+}
@@ -0,0 +1,467 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Implementation of a stream reading its input from a `std::io::Read`. It implements all traits required by `CharStream`.
+//!
+//! The input is read by chunks into a buffer shared by all the clones of a stream. The buffer counts the streams alive at the beginning of each line and the data preceding the first of them is discarded, so the memory used depends on how far the parser backtracks and on the length of the lines, and not on the size of the input. The line of a stream is kept to compute the columns of its position.
+
+use stream::*;
+use position::*;
+use std::io::{self, Read};
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::cmp::Ordering;
+use std::str;
+
+/// Number of bytes read from the reader at once.
+pub const DEFAULT_CHUNK_SIZE: usize = 8192;
+
+struct ReadBuffer<R>
+{
+  reader: R,
+  /// Bytes of the input from the offset `start`.
+  data: Vec<u8>,
+  start: usize,
+  end_of_input: bool,
+  error: Option<io::Error>,
+  chunk_size: usize,
+  /// Number of streams alive on the line starting at each offset.
+  alive: BTreeMap<usize, usize>
+}
+
+impl<R> ReadBuffer<R> where
+ R: Read
+{
+  fn end(&self) -> usize {
+    self.start + self.data.len()
+  }
+
+  /// Reads the input until `offset` (excluded) is buffered. Returns `false` if the input ends before.
+  fn fill_until(&mut self, offset: usize) -> bool {
+    while self.end() < offset && !self.end_of_input {
+      self.read_chunk();
+    }
+    self.end() >= offset
+  }
+
+  /// An error of the reader is kept and ends the input.
+  fn read_chunk(&mut self) {
+    let len = self.data.len();
+    self.data.resize(len + self.chunk_size, 0);
+    let mut read = 0;
+    loop {
+      match self.reader.read(&mut self.data[len..]) {
+        Ok(0) => {
+          self.end_of_input = true;
+        }
+        Ok(n) => {
+          read = n;
+        }
+        Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+        Err(err) => {
+          self.error = Some(err);
+          self.end_of_input = true;
+        }
+      }
+      break;
+    }
+    self.data.truncate(len + read);
+  }
+
+  fn bytes(&mut self, offset: usize, len: usize) -> Option<&[u8]> {
+    if self.fill_until(offset + len) {
+      let from = offset - self.start;
+      Some(&self.data[from..from + len])
+    }
+    else {
+      None
+    }
+  }
+
+  /// Decodes the character at `offset` and gives its length in bytes. Invalid UTF-8 sequences are decoded as `U+FFFD` one byte at a time.
+  fn char_at(&mut self, offset: usize) -> Option<(char, usize)> {
+    let width = match self.bytes(offset, 1) {
+      Some(first) => utf8_width(first[0]),
+      None => return None
+    };
+    let decoded = self.bytes(offset, width)
+      .and_then(|bytes| str::from_utf8(bytes).ok())
+      .and_then(|text| text.chars().next());
+    match decoded {
+      Some(c) => Some((c, width)),
+      None => Some(('\u{FFFD}', 1))
+    }
+  }
+
+  fn acquire(&mut self, offset: usize) {
+    *self.alive.entry(offset).or_insert(0) += 1;
+  }
+
+  fn release(&mut self, offset: usize) {
+    let remaining = {
+      let count = self.alive.get_mut(&offset).expect("Released a stream position never acquired.");
+      *count -= 1;
+      *count
+    };
+    if remaining == 0 {
+      self.alive.remove(&offset);
+      self.discard();
+    }
+  }
+
+  /// Data is discarded by chunks to avoid moving the buffer at each character read.
+  fn discard(&mut self) {
+    let first_alive = self.alive.keys().next().cloned().unwrap_or(self.end());
+    let unreachable = first_alive - self.start;
+    if unreachable >= self.chunk_size {
+      self.data.drain(..unreachable);
+      self.start = first_alive;
+    }
+  }
+}
+
+/// Length of the UTF-8 sequence starting with `first`, `1` for an invalid first byte.
+fn utf8_width(first: u8) -> usize {
+  match first {
+    0xC2...0xDF => 2,
+    0xE0...0xEF => 3,
+    0xF0...0xF4 => 4,
+    _ => 1
+  }
+}
+
+/// Represents a stream of characters read from `R`. The line of the position is maintained while reading since the beginning of the input may have been discarded.
+pub struct ReadStream<R> where
+ R: Read
+{
+  buffer: Rc<RefCell<ReadBuffer<R>>>,
+  offset: usize,
+  line: usize,
+  /// Offset of the beginning of the current line, it is kept in the buffer.
+  line_start: usize
+}
+
+impl<R> ReadStream<R> where
+ R: Read
+{
+  pub fn new(reader: R) -> ReadStream<R> {
+    ReadStream::with_chunk_size(reader, DEFAULT_CHUNK_SIZE)
+  }
+
+  pub fn with_chunk_size(reader: R, chunk_size: usize) -> ReadStream<R> {
+    assert!(chunk_size > 0, "The chunk size of a `ReadStream` must be positive.");
+    let mut buffer = ReadBuffer {
+      reader: reader,
+      data: vec![],
+      start: 0,
+      end_of_input: false,
+      error: None,
+      chunk_size: chunk_size,
+      alive: BTreeMap::new()
+    };
+    buffer.acquire(0);
+    ReadStream {
+      buffer: Rc::new(RefCell::new(buffer)),
+      offset: 0,
+      line: 1,
+      line_start: 0
+    }
+  }
+
+  pub fn line_column(&self) -> (usize, usize) {
+    let position = self.position();
+    (position.line, position.column)
+  }
+
+  /// Number of bytes currently held in memory.
+  pub fn buffered_len(&self) -> usize {
+    self.buffer.borrow().data.len()
+  }
+
+  /// The input ends at the first error of the reader. This error is returned only once.
+  pub fn take_io_error(&self) -> Option<io::Error> {
+    self.buffer.borrow_mut().error.take()
+  }
+
+  #[inline(always)]
+  fn assert_same_buffer(&self, other: &ReadStream<R>) {
+    debug_assert!(&*self.buffer as *const _ == &*other.buffer as *const _,
+      "Operations between two streams are only defined when they share the same reader.");
+  }
+
+  fn advance(&mut self, c: char, width: usize) {
+    self.offset += width;
+    if c == '\n' {
+      self.line += 1;
+      let mut buffer = self.buffer.borrow_mut();
+      buffer.acquire(self.offset);
+      buffer.release(self.line_start);
+      self.line_start = self.offset;
+    }
+  }
+}
+
+impl<R> Clone for ReadStream<R> where
+ R: Read
+{
+  fn clone(&self) -> ReadStream<R> {
+    self.buffer.borrow_mut().acquire(self.line_start);
+    ReadStream {
+      buffer: self.buffer.clone(),
+      offset: self.offset,
+      line: self.line,
+      line_start: self.line_start
+    }
+  }
+}
+
+impl<R> Drop for ReadStream<R> where
+ R: Read
+{
+  fn drop(&mut self) {
+    self.buffer.borrow_mut().release(self.line_start);
+  }
+}
+
+impl<R> Iterator for ReadStream<R> where
+ R: Read
+{
+  type Item = char;
+  fn next(&mut self) -> Option<Self::Item> {
+    let current = self.buffer.borrow_mut().char_at(self.offset);
+    current.map(|(c, width)| {
+      self.advance(c, width);
+      c
+    })
+  }
+}
+
+impl<R> PartialEq for ReadStream<R> where
+ R: Read
+{
+  fn eq(&self, other: &Self) -> bool {
+    self.assert_same_buffer(other);
+    self.offset == other.offset
+  }
+}
+
+impl<R> Eq for ReadStream<R> where R: Read {}
+
+impl<R> PartialOrd for ReadStream<R> where
+ R: Read
+{
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    self.assert_same_buffer(other);
+    self.offset.partial_cmp(&other.offset)
+  }
+}
+
+impl<R> Ord for ReadStream<R> where
+ R: Read
+{
+  fn cmp(&self, other: &Self) -> Ordering {
+    self.assert_same_buffer(other);
+    self.offset.cmp(&other.offset)
+  }
+}
+
+impl<R> Location for ReadStream<R> where
+ R: Read
+{
+  fn location(&self) -> String {
+    format!("{}", self.position())
+  }
+}
+
+impl<R> StreamPosition for ReadStream<R> where
+ R: Read
+{
+  fn position_with(&self, config: &ColumnConfig) -> Position {
+    let mut buffer = self.buffer.borrow_mut();
+    let mut column = 1;
+    let mut offset = self.line_start;
+    while offset < self.offset {
+      let (c, width) = buffer.char_at(offset).expect("The current line is kept in the buffer.");
+      column = config.next_column(column, c);
+      offset += width;
+    }
+    Position::new(self.offset, self.line, column)
+  }
+}
+
+impl<R> CodeSnippet for ReadStream<R> where
+ R: Read
+{
+  fn code_snippet(&self, len_hint: usize) -> String {
+    let mut buffer = self.buffer.borrow_mut();
+    let mut snippet = String::new();
+    let mut offset = self.offset;
+    while snippet.len() < len_hint {
+      match buffer.char_at(offset) {
+        Some((c, width)) => {
+          snippet.push(c);
+          offset += width;
+        }
+        None => break
+      }
+    }
+    if snippet.is_empty() && len_hint > 0 {
+      String::from("<end-of-file>")
+    }
+    else {
+      snippet
+    }
+  }
+}
+
+impl<R> ConsumePrefix<&'static str> for ReadStream<R> where
+ R: Read
+{
+  fn consume_prefix(&mut self, prefix: &'static str) -> bool {
+    let matched = self.buffer.borrow_mut()
+      .bytes(self.offset, prefix.len())
+      .map_or(false, |bytes| bytes == prefix.as_bytes());
+    if matched {
+      for c in prefix.chars() {
+        self.advance(c, c.len_utf8());
+      }
+    }
+    matched
+  }
+}
+
+impl<R> HasNext for ReadStream<R> where
+ R: Read
+{
+  fn has_next(&self) -> bool {
+    self.buffer.borrow_mut().bytes(self.offset, 1).is_some()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use stream::*;
+  use std::io::{self, Read};
+
+  /// Gives at most `max_read` bytes at each call to `read`.
+  struct SlowReader
+  {
+    data: Vec<u8>,
+    position: usize,
+    max_read: usize
+  }
+
+  impl SlowReader
+  {
+    fn new(data: &str, max_read: usize) -> SlowReader {
+      SlowReader { data: data.as_bytes().to_vec(), position: 0, max_read: max_read }
+    }
+  }
+
+  impl Read for SlowReader
+  {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+      let remaining = self.data.len() - self.position;
+      let len = *[buf.len(), self.max_read, remaining].iter().min().unwrap();
+      buf[..len].copy_from_slice(&self.data[self.position..self.position + len]);
+      self.position += len;
+      Ok(len)
+    }
+  }
+
+  fn stream(data: &str) -> ReadStream<SlowReader> {
+    ReadStream::with_chunk_size(SlowReader::new(data, 2), 3)
+  }
+
+  #[test]
+  fn test_read_across_chunks() {
+    let input = "aé€\n𝄞z";
+    let s = stream(input);
+    assert_eq!(s.clone().collect::<String>(), input);
+    let mut s2 = s.clone();
+    s2.next();
+    s2.next();
+    assert!(s < s2);
+    assert_eq!(s2.location(), "1:4");
+    assert_eq!(s2.code_snippet(3), "€");
+    s2.next();
+    s2.next();
+    assert_eq!(s2.location(), "2:1");
+    assert!(s2.has_next());
+    s2.next();
+    s2.next();
+    assert!(!s2.has_next());
+    assert_eq!(s2.code_snippet(10), "<end-of-file>");
+  }
+
+  #[test]
+  fn test_consume_prefix() {
+    let s = stream("let x = 1");
+    let mut s2 = s.clone();
+    assert!(!s2.consume_prefix("lex"));
+    assert!(s2 == s);
+    assert!(s2.consume_prefix("let x"));
+    assert_eq!(s2.next(), Some(' '));
+    assert!(!s2.consume_prefix("= 12"));
+    assert!(s2.consume_prefix("= 1"));
+    assert!(!s2.has_next());
+  }
+
+  #[test]
+  fn test_discard_unreachable_data() {
+    let line = "abcdefghi\n";
+    let input: String = (0..100).map(|_| line).collect();
+    let mut s = stream(&input);
+    let mut read = 0;
+    while let Some(_) = s.next() {
+      read += 1;
+      assert!(s.buffered_len() <= line.len() + 2 * 3 + 4);
+    }
+    assert_eq!(read, input.len());
+  }
+
+  #[test]
+  fn test_position_with() {
+    let mut s = stream("a\n\té𝄞x");
+    for _ in 0..5 {
+      s.next();
+    }
+    assert_eq!(s.position(), Position::new(9, 2, 8));
+    let config = ColumnConfig { unit: ColumnUnit::Utf16, tab_width: 8 };
+    assert_eq!(s.position_with(&config), Position::new(9, 2, 12));
+    assert_eq!(s.line_column(), (2, 8));
+  }
+
+  #[test]
+  fn test_clone_keeps_data() {
+    let s = stream("abc\ndef\nghij");
+    let mut s2 = s.clone();
+    for _ in 0..8 {
+      s2.next();
+    }
+    assert_eq!(s.clone().collect::<String>(), "abc\ndef\nghij");
+    drop(s);
+    s2.next();
+    assert!(s2.buffered_len() < 10);
+  }
+
+  #[test]
+  fn test_invalid_utf8() {
+    let reader: &[u8] = &[b'a', 0xFF, b'b'];
+    let s = ReadStream::new(reader);
+    assert_eq!(s.collect::<String>(), "a\u{FFFD}b");
+  }
+}
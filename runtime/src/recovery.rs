@@ -0,0 +1,282 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Error recovery.
+//!
+//! The expression `e ?! "sync"` does not fail when `e` fails: the error of `e` is recorded, the input is skipped up to the next occurrence of `"sync"` (included) and the parsing continues from there. `RecoveryStream` stores the recorded errors in a persistent list, which is restored along with the stream when the parser backtracks, so an error recovered in an alternative that fails afterwards is not reported.
+
+use stream::*;
+use parse_state::*;
+use parse_error::*;
+use indentation::*;
+use cst::RuleEvents;
+use std::rc::Rc;
+use std::cmp::Ordering;
+
+/// Records the errors recovered by the expressions `e ?! "sync"`. It is required by these expressions.
+pub trait RecoverErrors: Sized
+{
+  fn recover_error(&mut self, error: ParseError<Self>);
+}
+
+#[derive(Debug)]
+struct RecoveredError<S>
+{
+  error: ParseError<S>,
+  previous: Option<Rc<RecoveredError<S>>>
+}
+
+/// Adds to a stream the list of the errors recovered so far. Streams are compared by their positions only.
+#[derive(Clone, Debug)]
+pub struct RecoveryStream<S>
+{
+  stream: S,
+  errors: Option<Rc<RecoveredError<S>>>
+}
+
+impl<S> RecoveryStream<S>
+{
+  pub fn new(stream: S) -> RecoveryStream<S> {
+    RecoveryStream {
+      stream: stream,
+      errors: None
+    }
+  }
+
+  pub fn into_inner(self) -> S {
+    self.stream
+  }
+
+  pub fn has_errors(&self) -> bool {
+    self.errors.is_some()
+  }
+}
+
+impl<S> RecoveryStream<S> where
+ S: Clone
+{
+  /// Errors recovered before the current position, in the order of the input.
+  pub fn errors(&self) -> Vec<ParseError<S>> {
+    let mut errors = vec![];
+    let mut current = self.errors.as_ref();
+    while let Some(recovered) = current {
+      errors.push(recovered.error.clone());
+      current = recovered.previous.as_ref();
+    }
+    errors.reverse();
+    errors
+  }
+}
+
+impl<S> RecoverErrors for RecoveryStream<S>
+{
+  fn recover_error(&mut self, error: ParseError<Self>) {
+    self.errors = Some(Rc::new(RecoveredError {
+      error: error.map_stream(RecoveryStream::into_inner),
+      previous: self.errors.take()
+    }));
+  }
+}
+
+impl<S> IndentationStream for RecoveryStream<S> where
+ S: IndentationStream
+{
+  fn indent_stack(&self) -> &IndentStack {
+    self.stream.indent_stack()
+  }
+
+  fn set_indent_stack(&mut self, stack: IndentStack) {
+    self.stream.set_indent_stack(stack);
+  }
+}
+
+impl<S> RuleEvents for RecoveryStream<S> where
+ S: RuleEvents
+{
+  fn enter_rule(&mut self, name: &'static str, trivia: bool) {
+    self.stream.enter_rule(name, trivia);
+  }
+
+  fn exit_rule(&mut self) {
+    self.stream.exit_rule();
+  }
+}
+
+impl<S> Iterator for RecoveryStream<S> where
+ S: Iterator
+{
+  type Item = S::Item;
+  fn next(&mut self) -> Option<Self::Item> {
+    self.stream.next()
+  }
+}
+
+impl<S> PartialEq for RecoveryStream<S> where
+ S: PartialEq
+{
+  fn eq(&self, other: &Self) -> bool {
+    self.stream.eq(&other.stream)
+  }
+}
+
+impl<S> Eq for RecoveryStream<S> where S: Eq {}
+
+impl<S> PartialOrd for RecoveryStream<S> where
+ S: PartialOrd
+{
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    self.stream.partial_cmp(&other.stream)
+  }
+}
+
+impl<S> Ord for RecoveryStream<S> where
+ S: Ord
+{
+  fn cmp(&self, other: &Self) -> Ordering {
+    self.stream.cmp(&other.stream)
+  }
+}
+
+forward_position_traits!(RecoveryStream);
+
+impl<S, P> ConsumePrefix<P> for RecoveryStream<S> where
+ S: ConsumePrefix<P>
+{
+  fn consume_prefix(&mut self, prefix: P) -> bool {
+    self.stream.consume_prefix(prefix)
+  }
+}
+
+impl<S> HasNext for RecoveryStream<S> where
+ S: HasNext
+{
+  fn has_next(&self) -> bool {
+    self.stream.has_next()
+  }
+}
+
+/// Skips the input from `farthest_read` to the end of the next occurrence of `sync`. Returns `None` if `sync` does not occur in the rest of the input.
+fn synchronise<S>(mut stream: S, farthest_read: &S, sync: &'static str) -> Option<S> where
+ S: CharStream
+{
+  while stream < *farthest_read && stream.next().is_some() {}
+  loop {
+    let mut sync_stream = stream.clone();
+    if sync_stream.consume_prefix(sync) {
+      return Some(sync_stream);
+    }
+    if stream.next().is_none() {
+      return None;
+    }
+  }
+}
+
+/// `state` is the result of `e` started at `stream` in `e ?! sync`. If `e` failed, its error is recorded and the parsing continues after the next `sync` with the value `None`.
+pub fn recover_parser<S, T>(state: ParseState<S, T>, stream: S, sync: &'static str)
+  -> ParseState<S, Option<T>> where
+ S: CharStream + RecoverErrors
+{
+  if state.is_successful() {
+    return state.map_data(Some);
+  }
+  let error = state.error;
+  match synchronise(stream, &error.farthest_read, sync) {
+    Some(mut sync_stream) => {
+      sync_stream.recover_error(error);
+      ParseState::success(sync_stream, None)
+    }
+    None => ParseState::from_error(error)
+  }
+}
+
+pub fn recover_recognizer<S>(state: ParseState<S, ()>, stream: S, sync: &'static str)
+  -> ParseState<S, ()> where
+ S: CharStream + RecoverErrors
+{
+  recover_parser(state, stream, sync).map_data(|_| ())
+}
+
+#[cfg(test)]
+mod test
+{
+  use super::*;
+  use str_stream::*;
+  use stream::*;
+  use parse_state::*;
+
+  type RecoveryState<'a, T> = ParseState<RecoveryStream<StrStream<'a>>, T>;
+
+  // statement = "x" ";"
+  fn statement<'a>(stream: RecoveryStream<StrStream<'a>>) -> RecoveryState<'a, char> {
+    let mut s = stream.clone();
+    if !s.consume_prefix("x") {
+      return ParseState::error(stream, "x");
+    }
+    let after_x = s.clone();
+    if s.consume_prefix(";") {
+      ParseState::success(s, 'x')
+    }
+    else {
+      ParseState::error(after_x, ";")
+    }
+  }
+
+  // program = (statement ?! ";")*
+  fn program<'a>(stream: RecoveryStream<StrStream<'a>>) -> RecoveryState<'a, Vec<Option<char>>> {
+    let mut statements = vec![];
+    let mut current = stream;
+    loop {
+      let state = recover_parser(statement(current.clone()), current.clone(), ";");
+      match state.success {
+        Some(success) => {
+          statements.push(success.data);
+          current = success.stream;
+        }
+        None => return ParseState::success(current, statements)
+      }
+    }
+  }
+
+  #[test]
+  fn test_recovered_errors() {
+    let state = program(RecoveryStream::new("x;y;xx;x;".stream()));
+    let success = state.success.unwrap();
+    assert_eq!(success.data, vec![Some('x'), None, None, Some('x')]);
+    assert!(!success.stream.has_next());
+    let errors = success.stream.errors();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].expected, vec!["x"]);
+    assert_eq!(errors[0].farthest_read.location(), "1:3");
+    assert_eq!(errors[1].expected, vec![";"]);
+    assert_eq!(errors[1].farthest_read.location(), "1:6");
+  }
+
+  #[test]
+  fn test_no_synchronisation() {
+    let stream = RecoveryStream::new("x;y".stream());
+    let state = program(stream);
+    let success = state.success.unwrap();
+    assert_eq!(success.data, vec![Some('x')]);
+    assert!(!success.stream.has_errors());
+    assert_eq!(success.stream.location(), "1:3");
+  }
+
+  #[test]
+  fn test_backtracking_discards_errors() {
+    let stream = RecoveryStream::new("y;".stream());
+    let recovered = recover_recognizer(statement(stream.clone()).map_data(|_| ()), stream.clone(), ";");
+    assert!(recovered.stream().has_errors());
+    assert!(!stream.has_errors());
+  }
+}
@@ -0,0 +1,317 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Machine-readable reports of parse errors.
+//!
+//! An `ErrorReport` is an error independent of the stream, with structured positions, that can be printed in JSON with `to_json`. It can be converted into the `Diagnostic` structure of the Language Server Protocol with `lsp_diagnostic`.
+
+use stream::*;
+use parse_error::*;
+use parse_state::*;
+use position::*;
+
+/// Rule being parsed when the error occurred.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ContextReport
+{
+  pub rule: &'static str,
+  pub start: Position
+}
+
+/// Message attached to a position of the input.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Label
+{
+  pub position: Position,
+  pub message: String
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ErrorReport
+{
+  /// Description of the error without its position.
+  pub message: String,
+  pub position: Position,
+  /// Character at `position`, `None` at the end of the input.
+  pub unexpected: Option<char>,
+  /// Expected items without duplicates and sorted.
+  pub expected: Vec<&'static str>,
+  /// Rules being parsed when the error occurred, from the innermost to the outermost.
+  pub context: Vec<ContextReport>,
+  /// The first label describes the error, it is followed by the start of the rules enclosing the error.
+  pub labels: Vec<Label>
+}
+
+impl ErrorReport
+{
+  pub fn new<S>(error: &ParseError<S>) -> ErrorReport where
+   S: CharStream + StreamPosition
+  {
+    let position = error.farthest_read.position();
+    let unexpected = error.farthest_read.clone().next();
+    let found = match unexpected {
+      Some(c) => format!("`{}`", c),
+      None => String::from("end of input")
+    };
+    let mut message = format!("unexpected {}", found);
+    let mut labels = vec![];
+    if error.expected.is_empty() {
+      labels.push(Label::new(position, message.clone()));
+    }
+    else {
+      let expected = format!("expecting {}", error.expected_items());
+      message = format!("{}, {}", message, expected);
+      labels.push(Label::new(position, expected));
+    }
+    let mut context = vec![];
+    for rule in &error.context {
+      let start = rule.start.position();
+      context.push(ContextReport {
+        rule: rule.rule,
+        start: start
+      });
+      if rule.start < error.farthest_read {
+        labels.push(Label::new(start, format!("while parsing {}", rule.rule.replace("_", " "))));
+      }
+    }
+    ErrorReport {
+      message: message,
+      position: position,
+      unexpected: unexpected,
+      expected: error.unique_expected(),
+      context: context,
+      labels: labels
+    }
+  }
+
+  pub fn to_json(&self) -> String {
+    let unexpected = match self.unexpected {
+      Some(c) => json_string(&c.to_string()),
+      None => String::from("null")
+    };
+    let expected: Vec<String> = self.expected.iter().map(|e| json_string(e)).collect();
+    let context: Vec<String> = self.context.iter()
+      .map(|c| format!("{{\"rule\":{},\"start\":{}}}", json_string(c.rule), json_position(&c.start)))
+      .collect();
+    let labels: Vec<String> = self.labels.iter()
+      .map(|l| format!("{{\"position\":{},\"message\":{}}}", json_position(&l.position), json_string(&l.message)))
+      .collect();
+    format!("{{\"message\":{},\"position\":{},\"unexpected\":{},\"expected\":[{}],\"context\":[{}],\"labels\":[{}]}}",
+      json_string(&self.message), json_position(&self.position), unexpected,
+      expected.join(","), context.join(","), labels.join(","))
+  }
+
+  /// `source` must be the input in which the error occurred and `index` its lines, they are needed to count the columns in UTF-16 code units. The index is built once with `LineIndex::new(source)` and shared by the diagnostics of this input. The range covers the unexpected character.
+  pub fn lsp_diagnostic(&self, source: &str, index: &LineIndex) -> LspDiagnostic {
+    let config = ColumnConfig::utf16();
+    let start = index.position(source, self.position.offset, &config);
+    let end_offset = self.position.offset + self.unexpected.map_or(0, |c| c.len_utf8());
+    let end = index.position(source, end_offset, &config);
+    let mut message = self.message.clone();
+    for label in self.labels.iter().skip(1) {
+      message.push_str(&format!("\n{} (started at {})", label.message, label.position));
+    }
+    LspDiagnostic {
+      range: LspRange {
+        start: LspPosition::new(start),
+        end: LspPosition::new(end)
+      },
+      severity: LspSeverity::Error,
+      message: message
+    }
+  }
+}
+
+impl Label
+{
+  pub fn new(position: Position, message: String) -> Label {
+    Label {
+      position: position,
+      message: message
+    }
+  }
+}
+
+impl<S> ParseError<S> where
+ S: CharStream + StreamPosition
+{
+  pub fn report(&self) -> ErrorReport {
+    ErrorReport::new(self)
+  }
+}
+
+impl<S, T> ParseState<S, T> where
+ S: CharStream + StreamPosition
+{
+  /// Report of the error if the state is erroneous or if the input was partially read, `None` otherwise.
+  pub fn error_report(&self) -> Option<ErrorReport> {
+    match self.success {
+      Some(ref success) if success.full_read() => None,
+      _ => Some(self.error.report())
+    }
+  }
+}
+
+/// Position in the Language Server Protocol, the line and the column (`character`) start at `0` and columns are counted in UTF-16 code units.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LspPosition
+{
+  pub line: usize,
+  pub character: usize
+}
+
+impl LspPosition
+{
+  /// `position` must have its columns counted in UTF-16 code units.
+  pub fn new(position: Position) -> LspPosition {
+    LspPosition {
+      line: position.line - 1,
+      character: position.column - 1
+    }
+  }
+
+  fn to_json(&self) -> String {
+    format!("{{\"line\":{},\"character\":{}}}", self.line, self.character)
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LspRange
+{
+  pub start: LspPosition,
+  pub end: LspPosition
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LspSeverity
+{
+  Error = 1,
+  Warning = 2,
+  Information = 3,
+  Hint = 4
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LspDiagnostic
+{
+  pub range: LspRange,
+  pub severity: LspSeverity,
+  pub message: String
+}
+
+impl LspDiagnostic
+{
+  pub fn to_json(&self) -> String {
+    format!("{{\"range\":{{\"start\":{},\"end\":{}}},\"severity\":{},\"message\":{}}}",
+      self.range.start.to_json(), self.range.end.to_json(),
+      self.severity as u8, json_string(&self.message))
+  }
+}
+
+fn json_position(position: &Position) -> String {
+  format!("{{\"offset\":{},\"line\":{},\"column\":{}}}", position.offset, position.line, position.column)
+}
+
+fn json_string(text: &str) -> String {
+  let mut json = String::from("\"");
+  for c in text.chars() {
+    match c {
+      '"' => json.push_str("\\\""),
+      '\\' => json.push_str("\\\\"),
+      '\n' => json.push_str("\\n"),
+      '\r' => json.push_str("\\r"),
+      '\t' => json.push_str("\\t"),
+      c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+      c => json.push(c)
+    }
+  }
+  json.push('"');
+  json
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use stream::*;
+  use parse_error::*;
+  use parse_state::*;
+  use position::*;
+  use str_stream::*;
+
+  fn let_error(source: &str) -> ParseError<StrStream> {
+    let stream = source.stream();
+    let mut error = ParseError::unique(stream.at_offset(10), "=");
+    error.expected.push("=");
+    error.within_rule("bind_op", stream.at_offset(10));
+    error.within_rule("let_binding", stream.at_offset(4));
+    error
+  }
+
+  #[test]
+  fn test_error_report() {
+    let source = "é\n let x 1";
+    let report = let_error(source).report();
+    assert_eq!(report.message, "unexpected `1`, expecting `=`");
+    assert_eq!(report.position, Position::new(10, 2, 8));
+    assert_eq!(report.unexpected, Some('1'));
+    assert_eq!(report.expected, vec!["="]);
+    assert_eq!(report.context.len(), 2);
+    assert_eq!(report.labels, vec![
+      Label::new(Position::new(10, 2, 8), String::from("expecting `=`")),
+      Label::new(Position::new(4, 2, 2), String::from("while parsing let binding"))]);
+    assert_eq!(report.to_json(),
+      "{\"message\":\"unexpected `1`, expecting `=`\",\
+        \"position\":{\"offset\":10,\"line\":2,\"column\":8},\
+        \"unexpected\":\"1\",\"expected\":[\"=\"],\
+        \"context\":[{\"rule\":\"bind_op\",\"start\":{\"offset\":10,\"line\":2,\"column\":8}},\
+          {\"rule\":\"let_binding\",\"start\":{\"offset\":4,\"line\":2,\"column\":2}}],\
+        \"labels\":[{\"position\":{\"offset\":10,\"line\":2,\"column\":8},\"message\":\"expecting `=`\"},\
+          {\"position\":{\"offset\":4,\"line\":2,\"column\":2},\"message\":\"while parsing let binding\"}]}");
+  }
+
+  #[test]
+  fn test_lsp_diagnostic() {
+    let source = "𝄞\n let x 1";
+    let error = ParseError::unique(source.stream().at_offset(12), "=");
+    let diagnostic = error.report().lsp_diagnostic(source, &LineIndex::new(source));
+    assert_eq!(diagnostic.range.start, LspPosition { line: 1, character: 7 });
+    assert_eq!(diagnostic.range.end, LspPosition { line: 1, character: 8 });
+    assert_eq!(diagnostic.to_json(),
+      "{\"range\":{\"start\":{\"line\":1,\"character\":7},\"end\":{\"line\":1,\"character\":8}},\
+        \"severity\":1,\"message\":\"unexpected `1`, expecting `=`\"}");
+
+    let source = "ab𝄞";
+    let error = ParseError::unique(source.stream().at_offset(2), "c");
+    let diagnostic = error.report().lsp_diagnostic(source, &LineIndex::new(source));
+    assert_eq!(diagnostic.range.end, LspPosition { line: 0, character: 4 });
+    let source = "é\n let x 1";
+    let index = LineIndex::new(source);
+    let with_context = let_error(source).report().lsp_diagnostic(source, &index);
+    assert_eq!(with_context.message, "unexpected `1`, expecting `=`\nwhile parsing let binding (started at 2:2)");
+  }
+
+  #[test]
+  fn test_state_report() {
+    let source = "a\"";
+    let full = ParseState::success(source.stream().at_offset(2), ());
+    assert!(full.error_report().is_none());
+    let partial = ParseState::success(source.stream().at_offset(1), ()).merge_error(
+      ParseError::unique(source.stream().at_offset(1), "\"\\\n"));
+    let report = partial.error_report().unwrap();
+    assert_eq!(report.unexpected, Some('"'));
+    assert!(report.to_json().contains("\"expected\":[\"\\\"\\\\\\n\"]"));
+    let end = ParseState::<_, ()>::error(source.stream().at_offset(2), "b").error_report().unwrap();
+    assert_eq!(end.message, "unexpected end of input, expecting `b`");
+  }
+}
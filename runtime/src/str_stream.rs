@@ -15,7 +15,10 @@
 //! Implementation of `Stream` for `&'a str` type. It implements all traits required by `CharStream`.
 
 use stream::*;
+use position::*;
 use std::cmp::{Ordering, min};
+use std::rc::Rc;
+use std::cell::RefCell;
 
 impl<'a> Stream for &'a str
 {
@@ -38,7 +41,9 @@ impl<'a> Stream for &'a String
 pub struct StrStream<'a>
 {
   raw_data: &'a str,
-  bytes_offset: usize
+  bytes_offset: usize,
+  /// Built the first time a position is requested and shared by all the streams of `raw_data`.
+  lines: Rc<RefCell<Option<LineIndex>>>
 }
 
 impl<'a> StrStream<'a>
@@ -46,7 +51,8 @@ impl<'a> StrStream<'a>
   fn new(raw_data: &'a str) -> StrStream<'a> {
     StrStream {
       raw_data: raw_data,
-      bytes_offset: 0
+      bytes_offset: 0,
+      lines: Rc::new(RefCell::new(None))
     }
   }
 
@@ -56,19 +62,9 @@ impl<'a> StrStream<'a>
       "Operations between two streams are only defined when they share the same raw data.");
   }
 
-  // Partially taken from https://github.com/kevinmehall/rust-peg/blob/master/src/translate.rs
   pub fn line_column(&self) -> (usize, usize) {
-    let mut remaining = self.bytes_offset;
-    let mut line_no = 1usize;
-    for line in self.raw_data.lines() {
-      let line_len = line.len() + 1;
-      if remaining < line_len {
-        break;
-      }
-      remaining -= line_len;
-      line_no += 1;
-    }
-    (line_no, remaining + 1)
+    let position = self.position();
+    (position.line, position.column)
   }
 }
 
@@ -115,8 +111,18 @@ impl<'a> Ord for StrStream<'a>
 impl<'a> Location for StrStream<'a>
 {
   fn location(&self) -> String {
-    let (line, column) = self.line_column();
-    format!("{}:{}", line, column)
+    format!("{}", self.position())
+  }
+}
+
+impl<'a> StreamPosition for StrStream<'a>
+{
+  fn position_with(&self, config: &ColumnConfig) -> Position {
+    let mut lines = self.lines.borrow_mut();
+    if lines.is_none() {
+      *lines = Some(LineIndex::new(self.raw_data));
+    }
+    lines.as_ref().unwrap().position(self.raw_data, self.bytes_offset, config)
   }
 }
 
@@ -129,7 +135,10 @@ impl<'a> CodeSnippet for StrStream<'a>
       String::from("<end-of-file>")
     }
     else {
-      let len = min(total_len - current_offset, len_hint);
+      let mut len = min(total_len - current_offset, len_hint);
+      while !self.raw_data.is_char_boundary(current_offset + len) {
+        len -= 1;
+      }
       String::from(&self.raw_data[current_offset..][..len])
     }
   }
@@ -158,6 +167,22 @@ impl<'a> HasNext for StrStream<'a>
   }
 }
 
+impl<'a> StreamOffset for StrStream<'a>
+{
+  fn offset(&self) -> usize {
+    self.bytes_offset
+  }
+
+  fn at_offset(&self, offset: usize) -> StrStream<'a> {
+    debug_assert!(self.raw_data.is_char_boundary(offset),
+      "`at_offset`: The offset must be at a character boundary of the raw data.");
+    StrStream {
+      raw_data: self.raw_data,
+      bytes_offset: offset,
+      lines: self.lines.clone()
+    }
+  }
+}
 
 #[cfg(test)]
 mod test {
@@ -212,6 +237,52 @@ mod test {
     test_str_stream(abc.stream(), abc.chars());
   }
 
+  #[test]
+  fn test_stream_offset() {
+    let mut s1 = "aé b".stream();
+    assert_eq!(s1.offset(), 0);
+    s1.next();
+    s1.next();
+    assert_eq!(s1.offset(), 3);
+    let mut s2 = s1.at_offset(1);
+    assert_eq!(s2.next(), Some('é'));
+    assert!(s1 == s2);
+  }
+
+  #[test]
+  fn test_position() {
+    let text = "a\r\n\tbé\nc";
+    let mut s1 = text.stream();
+    let s0 = s1.clone();
+    assert_eq!(s1.location(), "1:1");
+    // The index is built once and shared with the clones of the stream.
+    assert_eq!(s0.lines.borrow().as_ref().map(|lines| lines.lines_count()), Some(3));
+    s1.next();
+    s1.next();
+    assert_eq!(s1.location(), "1:3");
+    s1.next();
+    assert_eq!(s1.position(), Position::new(3, 2, 1));
+    s1.next();
+    s1.next();
+    assert_eq!(s1.position(), Position::new(5, 2, 3));
+    s1.next();
+    let config = ColumnConfig { unit: ColumnUnit::Chars, tab_width: 4 };
+    assert_eq!(s1.position_with(&config), Position::new(7, 2, 7));
+    assert_eq!(s1.position(), Position::new(7, 2, 5));
+    let s2 = s1.at_offset(text.len());
+    assert_eq!(s2.line_column(), (3, 2));
+    let config = ColumnConfig::utf16();
+    assert_eq!(s2.position_with(&config), LineIndex::new(text).position(text, text.len(), &config));
+  }
+
+  #[test]
+  fn test_code_snippet() {
+    let s1 = "aéb".stream();
+    assert_eq!(s1.code_snippet(2), "a");
+    assert_eq!(s1.code_snippet(3), "aé");
+    assert_eq!(s1.at_offset(4).code_snippet(10), "<end-of-file>");
+  }
+
   #[test]
   fn test_empty_stream() {
     let mut empty = "".stream();
@@ -16,6 +16,8 @@
 //!
 //! A stream produces a sequence of items (characters, bytes, etc.) while retaining information on the underlying data traversed. For example, a couple `(File, Iterator<char>)` could represent a stream of characters from a file.
 
+use position::{Position, ColumnConfig};
+
 /// Transforms a value into a stream of type `Output`.
 pub trait Stream
 {
@@ -42,6 +44,17 @@ pub trait Location
   fn location(&self) -> String;
 }
 
+/// Gives the line and column of the current position in the stream, see the module `position`.
+pub trait StreamPosition
+{
+  /// Position with the columns counted as described by `config`.
+  fn position_with(&self, config: &ColumnConfig) -> Position;
+
+  fn position(&self) -> Position {
+    self.position_with(&ColumnConfig::default())
+  }
+}
+
 /// Produces a code snippet of size `len_hint` or less starting from the current position in the stream.
 pub trait CodeSnippet
 {
@@ -59,3 +72,49 @@ pub trait HasNext
 {
   fn has_next(&self) -> bool;
 }
+
+/// Gives the position of the stream as a byte offset from the beginning of the input and builds a stream of the same input at another offset. It is required by memoised rules to store states independently of the stream.
+pub trait StreamOffset
+{
+  fn offset(&self) -> usize;
+  /// `offset` must be a valid position in the underlying data, for example an offset previously returned by `offset`.
+  fn at_offset(&self, offset: usize) -> Self;
+
+  /// Starts recording the input examined from the current position, it returns the recording in progress that must be given back to `end_examination`. Only streams used for incremental reparsing record it, see `IncrementalStream`.
+  fn start_examination(&self) -> usize { 0 }
+
+  /// Offset following the last byte examined since `start_examination`, `None` if the stream does not record it.
+  fn end_examination(&self, _previous: usize) -> Option<usize> { None }
+
+  /// Signals that the input up to `extent` has been examined, it happens when a memoised state is reused.
+  fn examine(&self, _extent: usize) {}
+}
+
+/// Implements `Location`, `StreamPosition` and `CodeSnippet` for a stream wrapping another stream in its field `stream`, the position is the one of the wrapped stream.
+macro_rules! forward_position_traits {
+  ($wrapper:ident) => {
+    impl<S> ::stream::Location for $wrapper<S> where
+     S: ::stream::Location
+    {
+      fn location(&self) -> String {
+        self.stream.location()
+      }
+    }
+
+    impl<S> ::stream::StreamPosition for $wrapper<S> where
+     S: ::stream::StreamPosition
+    {
+      fn position_with(&self, config: &::position::ColumnConfig) -> ::position::Position {
+        self.stream.position_with(config)
+      }
+    }
+
+    impl<S> ::stream::CodeSnippet for $wrapper<S> where
+     S: ::stream::CodeSnippet
+    {
+      fn code_snippet(&self, len_hint: usize) -> String {
+        self.stream.code_snippet(len_hint)
+      }
+    }
+  }
+}
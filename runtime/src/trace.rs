@@ -0,0 +1,128 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tracing of the rules tried by a parser.
+//!
+//! With the grammar attribute `#![trace]`, every generated function takes an extra argument `tracer: &mut Tracer` which is notified when a rule starts and when it succeeds or fails.
+
+use stream::*;
+use parse_state::*;
+use std::io::{self, Write};
+
+pub trait Tracer
+{
+  /// The rule `rule` starts at the position of `stream`.
+  fn enter_rule(&mut self, rule: &'static str, stream: &Location);
+  /// The rule `rule` succeeded and the parsing continues at the position of `stream`.
+  fn rule_success(&mut self, rule: &'static str, stream: &Location);
+  /// The rule `rule` failed, `farthest_read` is the position of its error.
+  fn rule_failure(&mut self, rule: &'static str, farthest_read: &Location);
+}
+
+/// Notifies `tracer` of the success or the failure of the rule `rule` that produced `state`.
+pub fn trace_exit<S, T>(tracer: &mut Tracer, rule: &'static str, state: &ParseState<S, T>) where
+ S: Location
+{
+  match state.success {
+    Some(ref success) => tracer.rule_success(rule, &success.stream),
+    None => tracer.rule_failure(rule, &state.error.farthest_read)
+  }
+}
+
+/// Prints a line when a rule starts, succeeds or fails, indented by the number of enclosing rules. For example:
+///
+/// ```text
+/// expression at 1:1
+///   number at 1:1
+///   number succeeded at 1:3
+/// expression failed at 1:4
+/// ```
+///
+/// Writing errors are ignored.
+pub struct PrintTracer<W>
+{
+  writer: W,
+  depth: usize
+}
+
+impl PrintTracer<io::Stderr>
+{
+  pub fn stderr() -> PrintTracer<io::Stderr> {
+    PrintTracer::new(io::stderr())
+  }
+}
+
+impl<W> PrintTracer<W> where
+ W: Write
+{
+  pub fn new(writer: W) -> PrintTracer<W> {
+    PrintTracer {
+      writer: writer,
+      depth: 0
+    }
+  }
+
+  pub fn into_inner(self) -> W {
+    self.writer
+  }
+
+  fn print(&mut self, rule: &'static str, event: &str, stream: &Location) {
+    let indent: String = (0..self.depth).map(|_| "  ").collect();
+    let _ = writeln!(self.writer, "{}{}{} at {}", indent, rule, event, stream.location());
+  }
+}
+
+impl<W> Tracer for PrintTracer<W> where
+ W: Write
+{
+  fn enter_rule(&mut self, rule: &'static str, stream: &Location) {
+    self.print(rule, "", stream);
+    self.depth += 1;
+  }
+
+  fn rule_success(&mut self, rule: &'static str, stream: &Location) {
+    self.depth -= 1;
+    self.print(rule, " succeeded", stream);
+  }
+
+  fn rule_failure(&mut self, rule: &'static str, farthest_read: &Location) {
+    self.depth -= 1;
+    self.print(rule, " failed", farthest_read);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use stream::*;
+  use parse_state::*;
+
+  #[test]
+  fn test_print_tracer() {
+    let mut tracer = PrintTracer::new(vec![]);
+    let stream = "12+".stream();
+    tracer.enter_rule("expression", &stream);
+    tracer.enter_rule("number", &stream);
+    let number = ParseState::success(stream.at_offset(2), ());
+    trace_exit(&mut tracer, "number", &number);
+    let expression = ParseState::<_, ()>::error(stream.at_offset(3), "number");
+    trace_exit(&mut tracer, "expression", &expression);
+    let trace = String::from_utf8(tracer.into_inner()).unwrap();
+    assert_eq!(trace,
+      "expression at 1:1\n  \
+         number at 1:1\n  \
+         number succeeded at 1:3\n\
+       expression failed at 1:4\n");
+  }
+}
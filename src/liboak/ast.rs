@@ -17,10 +17,12 @@
 #![macro_use]
 
 pub use identifier::*;
+pub use ordered_map::OrderedMap;
 pub use rust::Span;
 
 use rust;
 use std::fmt::{Formatter, Write, Display, Error};
+use std::char;
 
 pub type RTy = rust::P<rust::Ty>;
 pub type RExpr = rust::P<rust::Expr>;
@@ -39,7 +41,105 @@ pub enum Expression_<SubExpr: ?Sized>{
   Optional(Box<SubExpr>), // space?
   NotPredicate(Box<SubExpr>), // !space
   AndPredicate(Box<SubExpr>), // &space
-  SemanticAction(Box<SubExpr>, Ident) // rule > function
+  SemanticAction(Box<SubExpr>, Ident), // rule > function
+  Recovery(Box<SubExpr>, String), // statement ?! ";"
+  OperatorTable(OperatorTableExpr<SubExpr>), // %precedence(atom) { left: add_op > add; }
+  Indentation(IndentKind) // INDENT
+}
+
+/// Terminals comparing the indentation of the next line with the one of the enclosing block.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum IndentKind
+{
+  Indent,
+  Dedent,
+  Samedent
+}
+
+impl IndentKind
+{
+  pub fn from_name(name: &str) -> Option<IndentKind> {
+    match name {
+      "INDENT" => Some(IndentKind::Indent),
+      "DEDENT" => Some(IndentKind::Dedent),
+      "SAMEDENT" => Some(IndentKind::Samedent),
+      _ => None
+    }
+  }
+}
+
+/// An operator-precedence table `%precedence(operand) { ... }` parses `operand` separated or surrounded by operators. Each operator is associated to a semantic action building the value of the compound expression, which has the type of `operand`.
+#[derive(Clone, Debug)]
+pub struct OperatorTableExpr<SubExpr: ?Sized>
+{
+  pub operand: Box<SubExpr>,
+  /// Levels are ordered from the lowest to the highest precedence.
+  pub levels: Vec<PrecedenceLevel<SubExpr>>
+}
+
+impl<SubExpr> OperatorTableExpr<SubExpr>
+{
+  /// Builds a new table with the same structure where every sub-expression (operand and operators) is transformed by `f`.
+  pub fn map_exprs<Expr, F>(&self, mut f: F) -> OperatorTableExpr<Expr> where
+   F: FnMut(&Box<SubExpr>) -> Box<Expr>
+  {
+    let operand = f(&self.operand);
+    let levels = self.levels.iter().map(|level|
+      PrecedenceLevel {
+        associativity: level.associativity,
+        operators: level.operators.iter().map(|op|
+          Operator {
+            expr: f(&op.expr),
+            action: op.action
+          }).collect()
+      }).collect();
+    OperatorTableExpr {
+      operand: operand,
+      levels: levels
+    }
+  }
+
+  /// Iterates over all the operators of the table with their precedence. The precedence of the first level is `1`.
+  pub fn operators<'a>(&'a self) -> Vec<(usize, Associativity, &'a Operator<SubExpr>)> {
+    self.levels.iter().zip(1..)
+      .flat_map(|(level, precedence)|
+        level.operators.iter().map(move |op| (precedence, level.associativity, op)))
+      .collect()
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct PrecedenceLevel<SubExpr: ?Sized>
+{
+  pub associativity: Associativity,
+  pub operators: Vec<Operator<SubExpr>>
+}
+
+#[derive(Clone, Debug)]
+pub struct Operator<SubExpr: ?Sized>
+{
+  /// Only recognized, the value of this expression is never built.
+  pub expr: Box<SubExpr>,
+  pub action: Ident
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Associativity
+{
+  Left,
+  Right,
+  Prefix,
+  Postfix
+}
+
+impl Associativity
+{
+  pub fn is_unary(self) -> bool {
+    match self {
+      Associativity::Prefix | Associativity::Postfix => true,
+      _ => false
+    }
+  }
 }
 
 #[derive(Clone, Debug)]
@@ -63,6 +163,33 @@ pub struct CharacterInterval {
   pub hi: char
 }
 
+/// Sorts the intervals and merges the ones overlapping or adjacent.
+pub fn normalize_intervals(mut intervals: Vec<CharacterInterval>) -> Vec<CharacterInterval> {
+  intervals.sort_by_key(|interval| (interval.lo, interval.hi));
+  let mut normalized: Vec<CharacterInterval> = vec![];
+  for interval in intervals {
+    if let Some(last) = normalized.last_mut() {
+      if char_after(last.hi).map_or(true, |next| interval.lo <= next) {
+        if interval.hi > last.hi {
+          last.hi = interval.hi;
+        }
+        continue;
+      }
+    }
+    normalized.push(interval);
+  }
+  normalized
+}
+
+/// The character following `c`, the surrogate code points are skipped.
+pub fn char_after(c: char) -> Option<char> {
+  match c {
+    char::MAX => None,
+    '\u{D7FF}' => Some('\u{E000}'),
+    c => char::from_u32(c as u32 + 1)
+  }
+}
+
 impl Display for CharacterInterval {
   fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
     if self.lo == self.hi {
@@ -96,6 +223,7 @@ pub trait Visitor<Node: ExprNode, R>
   }
 
   fn visit_str_literal(&mut self, _parent: &Box<Node>, _lit: &String) -> R;
+  fn visit_indentation(&mut self, _parent: &Box<Node>, _kind: IndentKind) -> R;
   fn visit_non_terminal_symbol(&mut self, _parent: &Box<Node>, _id: Ident) -> R;
   fn visit_character(&mut self, _parent: &Box<Node>) -> R;
 
@@ -109,6 +237,7 @@ pub trait Visitor<Node: ExprNode, R>
 
   fn visit_sequence(&mut self, _parent: &Box<Node>, exprs: &Vec<Box<Node>>) -> R;
   fn visit_choice(&mut self, _parent: &Box<Node>, exprs: &Vec<Box<Node>>) -> R;
+  fn visit_operator_table(&mut self, _parent: &Box<Node>, table: &OperatorTableExpr<Node>) -> R;
 
   fn visit_repeat(&mut self, _parent: &Box<Node>, expr: &Box<Node>) -> R {
     walk_expr(self, expr)
@@ -141,11 +270,16 @@ pub trait Visitor<Node: ExprNode, R>
   fn visit_semantic_action(&mut self, _parent: &Box<Node>, expr: &Box<Node>, _id: Ident) -> R {
     walk_expr(self, expr)
   }
+
+  fn visit_recovery(&mut self, _parent: &Box<Node>, expr: &Box<Node>, _sync: &String) -> R {
+    walk_expr(self, expr)
+  }
 }
 
 /// We need this macro for factorizing the code since we can not specialize a trait on specific type parameter (we would need to specialize on `()` here).
 macro_rules! unit_visitor_impl {
   ($Node:ty, str_literal) => (fn visit_str_literal(&mut self, _parent: &Box<$Node>, _lit: &String) -> () {});
+  ($Node:ty, indentation) => (fn visit_indentation(&mut self, _parent: &Box<$Node>, _kind: IndentKind) -> () {});
   ($Node:ty, non_terminal) => (fn visit_non_terminal_symbol(&mut self, _parent: &Box<$Node>, _id: Ident) -> () {});
   ($Node:ty, character) => (fn visit_character(&mut self, _parent: &Box<$Node>) -> () {});
   ($Node:ty, sequence) => (
@@ -158,6 +292,11 @@ macro_rules! unit_visitor_impl {
       walk_exprs(self, exprs);
     }
   );
+  ($Node:ty, operator_table) => (
+    fn visit_operator_table(&mut self, _parent: &Box<$Node>, table: &OperatorTableExpr<$Node>) -> () {
+      walk_operator_table(self, table);
+    }
+  );
 }
 
 pub fn walk_expr<Node, R, V: ?Sized>(visitor: &mut V, parent: &Box<Node>) -> R where
@@ -202,6 +341,15 @@ pub fn walk_expr<Node, R, V: ?Sized>(visitor: &mut V, parent: &Box<Node>) -> R w
     &SemanticAction(ref expr, id) => {
       visitor.visit_semantic_action(parent, expr, id)
     }
+    &Recovery(ref expr, ref sync) => {
+      visitor.visit_recovery(parent, expr, sync)
+    }
+    &OperatorTable(ref table) => {
+      visitor.visit_operator_table(parent, table)
+    }
+    &Indentation(kind) => {
+      visitor.visit_indentation(parent, kind)
+    }
   }
 }
 
@@ -212,3 +360,15 @@ pub fn walk_exprs<Node, R, V: ?Sized>(visitor: &mut V, exprs: &Vec<Box<Node>>) -
   exprs.iter().map(|expr| visitor.visit_expr(expr)).collect()
 }
 
+
+/// Visits the operand first and then the operators in the order of their declaration.
+pub fn walk_operator_table<Node, R, V: ?Sized>(visitor: &mut V, table: &OperatorTableExpr<Node>) -> Vec<R> where
+  Node: ExprNode,
+  V: Visitor<Node, R>
+{
+  let mut res = vec![visitor.visit_expr(&table.operand)];
+  for (_, _, op) in table.operators() {
+    res.push(visitor.visit_expr(&op.expr));
+  }
+  res
+}
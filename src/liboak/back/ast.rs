@@ -13,9 +13,10 @@
 // limitations under the License.
 
 pub use ast::*;
-pub use middle::typing::ast::{Grammar_, Rule_, ExprTy};
+pub use middle::typing::ast::{Grammar_, Rule_, RuleAttributes, AstType, AstConstructor, ExprTy};
 pub use std::collections::HashMap;
 pub use rust::{ExtCtxt, Spanned, SpannedIdent};
+pub use back::first_set::ChoiceDispatch;
 
 use back::ast::FunctionKind::*;
 
@@ -29,7 +30,13 @@ pub struct Expression
   pub span: Span,
   pub node: ExpressionNode,
   pub ty: ExprTy,
-  pub kind: FunctionKind
+  pub kind: FunctionKind,
+  /// The dispatch of a choice on its next character, `None` for the other expressions.
+  pub dispatch: Option<ChoiceDispatch>,
+  /// The structurally identical expressions have the same identifier and share their generated functions, `None` if the expressions are not shared.
+  pub shared_id: Option<usize>,
+  /// The literals merged into a `StrLiteral`, they are matched one after the other so a failure is reported on the literal that failed. `None` for the other expressions.
+  pub merged_literals: Option<Vec<String>>
 }
 
 impl ExprNode for Expression
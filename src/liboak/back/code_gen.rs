@@ -14,7 +14,13 @@
 
 //! This part generates the Rust code from the AST built during the previous phases. For a single expression, two functions can be generated: recognizer and parser. The difference is that recognizer does not build any value while parser does.
 //!
-//! Semantics actions `expr > f` are compiled into `f(expr)` with `expr` expanded if `expr` is a tuple. Semantics actions are not called in recognizers.
+//! Rules marked with `#[ast]` have been wrapped into semantic actions during the analysis, their types and actions are generated here from the types of the wrapped expressions.
+//!
+//! A choice of literals is compiled into a trie (see `literal_trie`). Any other choice is compiled into a chain of alternatives tried in order. If the choice has a dispatch (see `first_set`), the next character is first matched against the dispatch classes and the alternatives known to fail on it are skipped.
+//!
+//! A character class is compiled into comparisons with its intervals. A class with more than `MAX_COMPARED_INTERVALS` intervals is compiled into a static `oak_runtime::CharClass` table, shared by all the occurrences of this class in the grammar.
+//!
+//! Semantics actions `expr > f` are compiled into `f(expr)` with `expr` expanded if `expr` is a tuple. Semantics actions are not called in recognizers. If the grammar declares a context `#![context(Ctx)]` and the first parameter of `f` has the type `&mut Ctx`, the action is compiled into `f(context, expr)`.
 
 use rust;
 use rust::AstBuilder;
@@ -22,9 +28,14 @@ use back::ast::*;
 use back::naming::*;
 use back::function::*;
 use back::code_printer::*;
+use back::literal_trie::*;
 use monad::partial::Partial;
 
 use std::iter::*;
+use std::collections::HashSet;
+use std::cmp::min;
+
+const MAX_COMPARED_INTERVALS: usize = 3;
 
 pub fn generate_rust_code<'cx>(cx: &'cx ExtCtxt, grammar: Grammar)
   -> Partial<Box<rust::MacResult + 'cx>>
@@ -32,6 +43,150 @@ pub fn generate_rust_code<'cx>(cx: &'cx ExtCtxt, grammar: Grammar)
   Partial::Value(CodeGenerator::compile(cx, grammar))
 }
 
+fn is_memoised(grammar: &Grammar) -> bool {
+  grammar.rules.values().any(|rule| rule.attributes.memo)
+}
+
+/// Actions whose first parameter has the type `&mut Ctx` where `Ctx` is the context of the grammar.
+fn context_actions(grammar: &Grammar) -> HashSet<Ident> {
+  match grammar.attributes.context {
+    Some(context_ty) => {
+      grammar.rust_functions.iter()
+        .filter(|&(_, item)| takes_context(item, context_ty))
+        .map(|(&id, _)| id)
+        .collect()
+    }
+    None => HashSet::new()
+  }
+}
+
+fn takes_context(item: &RItem, context_ty: Ident) -> bool {
+  if let rust::ItemKind::Fn(ref decl, _,_,_,_,_) = item.node {
+    if let Some(arg) = decl.inputs.first() {
+      if let rust::TyKind::Rptr(_, ref mut_ty) = arg.ty.node {
+        if let rust::TyKind::Path(None, ref path) = mut_ty.ty.node {
+          return mut_ty.mutbl == rust::Mutability::Mutable
+            && path.segments.last().map_or(false, |seg| seg.identifier.name == context_ty.name);
+        }
+      }
+    }
+  }
+  false
+}
+
+/// Actions building the values of the types generated for the rules marked with `#[ast]`.
+fn ast_actions(grammar: &Grammar) -> HashSet<Ident> {
+  grammar.ast_types.iter()
+    .flat_map(|ast_type| ast_type.constructors.iter().map(|constructor| constructor.action))
+    .collect()
+}
+
+/// Fields containing a generated type are boxed since these types can be recursive. The generated types are searched inside the tuples and the options which store their values inline, a vector already stores its elements on the heap. Returns the type of the field with the boxes and the expression building it from `value`, or `None` if nothing is boxed.
+fn box_ast_types(cx: &ExtCtxt, ty: &RTy, value: RExpr, ast_types: &Vec<AstType>,
+  fresh_vars: &mut usize) -> Option<(RTy, RExpr)>
+{
+  match ty.node {
+    rust::TyKind::Path(None, ref path) if is_ast_type(path, ast_types) => {
+      Some((quote_ty!(cx, Box<$ty>), quote_expr!(cx, Box::new($value))))
+    }
+    rust::TyKind::Path(None, ref path) => {
+      option_type(path).and_then(|inner_ty| {
+        let var = fresh_var(cx, fresh_vars);
+        box_ast_types(cx, &inner_ty, quote_expr!(cx, $var), ast_types, fresh_vars)
+          .map(|(boxed_ty, boxed_value)|
+            (quote_ty!(cx, Option<$boxed_ty>), quote_expr!(cx, $value.map(|$var| $boxed_value))))
+      })
+    }
+    rust::TyKind::Tup(ref tys) => {
+      let mut has_box = false;
+      let mut vars = vec![];
+      let mut boxed_tys = vec![];
+      let mut boxed_values = vec![];
+      for ty in tys {
+        let var = fresh_var(cx, fresh_vars);
+        vars.push(cx.pat_ident(ty.span, var));
+        match box_ast_types(cx, ty, quote_expr!(cx, $var), ast_types, fresh_vars) {
+          Some((boxed_ty, boxed_value)) => {
+            has_box = true;
+            boxed_tys.push(boxed_ty);
+            boxed_values.push(boxed_value);
+          }
+          None => {
+            boxed_tys.push(ty.clone());
+            boxed_values.push(quote_expr!(cx, $var));
+          }
+        }
+      }
+      if has_box {
+        let pattern = cx.pat_tuple(ty.span, vars);
+        let boxed_value = cx.expr_tuple(ty.span, boxed_values);
+        Some((cx.ty(ty.span, rust::TyKind::Tup(boxed_tys)),
+          quote_expr!(cx, { let $pattern = $value; $boxed_value })))
+      }
+      else {
+        None
+      }
+    }
+    _ => None
+  }
+}
+
+fn is_ast_type(path: &rust::Path, ast_types: &Vec<AstType>) -> bool {
+  path.segments.len() == 1
+    && ast_types.iter().any(|ast_type| ast_type.name.name == path.segments[0].identifier.name)
+}
+
+/// The type `T` of `Option<T>`.
+fn option_type(path: &rust::Path) -> Option<RTy> {
+  if path.segments.len() == 1 && &*path.segments[0].identifier.name.as_str() == "Option" {
+    if let rust::PathParameters::AngleBracketed(ref data) = path.segments[0].parameters {
+      if data.types.len() == 1 {
+        return Some(data.types[0].clone());
+      }
+    }
+  }
+  None
+}
+
+fn fresh_var(cx: &ExtCtxt, fresh_vars: &mut usize) -> Ident {
+  *fresh_vars += 1;
+  cx.ident_of(format!("value{}", fresh_vars).as_str())
+}
+
+/// Indentation terminals require the stream to carry an indentation stack and the recovery expressions require it to record the recovered errors.
+fn stream_requirements(grammar: &Grammar) -> StreamRequirements {
+  let mut requirements = StreamRequirements { indentation: false, recovery: false };
+  for rule in grammar.rules.values() {
+    requirements.visit_expr(&rule.def);
+  }
+  requirements
+}
+
+struct StreamRequirements
+{
+  indentation: bool,
+  recovery: bool
+}
+
+impl Visitor<Expression, ()> for StreamRequirements
+{
+  unit_visitor_impl!(Expression, str_literal);
+  unit_visitor_impl!(Expression, non_terminal);
+  unit_visitor_impl!(Expression, character);
+  unit_visitor_impl!(Expression, sequence);
+  unit_visitor_impl!(Expression, choice);
+  unit_visitor_impl!(Expression, operator_table);
+
+  fn visit_indentation(&mut self, _parent: &Box<Expression>, _kind: IndentKind) {
+    self.indentation = true;
+  }
+
+  fn visit_recovery(&mut self, _parent: &Box<Expression>, expr: &Box<Expression>, _sync: &String) {
+    self.recovery = true;
+    self.visit_expr(expr);
+  }
+}
+
 fn map_foldr<T, U, V, F, G>(data: Vec<T>, accu: V, f: F, g: G) -> V where
  F: Fn(T) -> U,
  G: Fn(V, U) -> V
@@ -59,7 +214,15 @@ struct CodeGenerator<'cx>
 {
   cx: &'cx ExtCtxt<'cx>,
   function_gen: FunctionGenerator<'cx>,
-  current_rule_name: Ident
+  current_rule_name: Ident,
+  context_actions: HashSet<Ident>,
+  ast_actions: HashSet<Ident>,
+  ast_fields: HashMap<Ident, Vec<RTy>>,
+  /// Static tables of the large character classes, indexed by their normalized intervals.
+  char_class_tables: HashMap<Vec<(char, char)>, Ident>,
+  char_class_items: Vec<RItem>,
+  /// Functions generated for the structurally identical expressions, indexed by their shared identifier.
+  shared_exprs: HashMap<usize, GenFunNames>
 }
 
 impl<'cx> CodeGenerator<'cx>
@@ -68,8 +231,40 @@ impl<'cx> CodeGenerator<'cx>
     let mut compiler = CodeGenerator {
       cx: cx,
       function_gen: FunctionGenerator::new(cx),
-      current_rule_name: *grammar.rules.keys().next().unwrap()
+      current_rule_name: *grammar.rules.keys().next().unwrap(),
+      context_actions: context_actions(&grammar),
+      ast_actions: ast_actions(&grammar),
+      ast_fields: HashMap::new(),
+      char_class_tables: HashMap::new(),
+      char_class_items: vec![],
+      shared_exprs: HashMap::new()
     };
+    if let Some(context_ty) = grammar.attributes.context {
+      compiler.function_gen.thread_arg(cx.ident_of("context"), quote_ty!(cx, $context_ty));
+    }
+    if is_memoised(&grammar) {
+      compiler.function_gen.thread_arg(cx.ident_of("memo"), quote_ty!(cx, Memo));
+      compiler.function_gen.add_stream_bound(quote_tokens!(cx, oak_runtime::StreamOffset));
+    }
+    let requirements = stream_requirements(&grammar);
+    if requirements.indentation {
+      compiler.function_gen.add_stream_bound(quote_tokens!(cx, oak_runtime::IndentationStream));
+    }
+    if requirements.recovery {
+      compiler.function_gen.add_stream_bound(quote_tokens!(cx, oak_runtime::RecoverErrors));
+    }
+    if grammar.attributes.cst {
+      compiler.function_gen.build_syntax_tree();
+    }
+    if grammar.attributes.trace {
+      compiler.function_gen.trace_rules();
+    }
+    if grammar.attributes.profile {
+      compiler.function_gen.profile_functions();
+    }
+    if grammar.attributes.error_context {
+      compiler.function_gen.record_rule_context();
+    }
     compiler.compile_peg(&grammar)
   }
 
@@ -125,8 +320,15 @@ impl<'cx> CodeGenerator<'cx>
   fn compile_parser(&mut self, grammar: &Grammar) -> Vec<RItem> {
     self.compile_rules(grammar);
     let mut rust_code = grammar.rust_items.clone();
-    rust_code.extend(grammar.rust_functions.values().cloned());
+    rust_code.extend(grammar.rust_functions.iter()
+      .filter(|&(id, _)| !self.ast_actions.contains(id))
+      .map(|(_, function)| function.clone()));
+    rust_code.extend(self.compile_ast_types(grammar).into_iter());
     rust_code.extend(self.function_gen.code().into_iter());
+    rust_code.extend(self.char_class_items.drain(..));
+    if is_memoised(grammar) {
+      rust_code.extend(self.compile_memo(grammar).into_iter());
+    }
     rust_code
   }
 
@@ -134,7 +336,127 @@ impl<'cx> CodeGenerator<'cx>
     for rule in grammar.rules.values() {
       self.current_rule_name = rule.name.node;
       let expr_fn = self.visit_expr(&rule.def);
-      self.function_gen.generate_rule(rule.def.kind(), self.current_rule_name, expr_fn);
+      self.function_gen.generate_rule(rule.def.kind(), self.current_rule_name, expr_fn, rule.attributes);
+    }
+  }
+
+  /// The structure `Memo` has one memoisation table per generated function of a memoised rule.
+  #[allow(unused_imports)] // `quote_tokens` generates a warning.
+  fn compile_memo(&mut self, grammar: &Grammar) -> Vec<RItem> {
+    let cx = self.cx;
+    let mut fields = vec![];
+    let mut fields_init = vec![];
+    let mut fields_edit = vec![];
+    for rule in grammar.rules.values().filter(|rule| rule.attributes.memo) {
+      let GenFunNames{recognizer, parser} = self.function_gen.names_of_rule(rule.name.node);
+      let mut tables = vec![(recognizer, quote_ty!(cx, ()))];
+      if let FunctionKind::Both(ty) = rule.def.kind() {
+        tables.push((parser, ty));
+      }
+      for (name, ty) in tables {
+        fields.extend(quote_tokens!(cx, pub $name: oak_runtime::MemoTable<$ty>,).into_iter());
+        fields_init.extend(quote_tokens!(cx, $name: oak_runtime::MemoTable::new(),).into_iter());
+        fields_edit.extend(quote_tokens!(cx, self.$name.edit(edit);).into_iter());
+      }
+    }
+    vec![
+      quote_item!(cx,
+        pub struct Memo {
+          $fields
+        }
+      ).expect("Quote the memoisation structure."),
+      quote_item!(cx,
+        impl Memo {
+          pub fn new() -> Memo {
+            Memo {
+              $fields_init
+            }
+          }
+
+          pub fn edit(&mut self, edit: &oak_runtime::TextEdit) {
+            $fields_edit
+          }
+        }
+      ).expect("Quote the memoisation structure constructor.")
+    ]
+  }
+
+  #[allow(unused_imports)] // `quote_tokens` generates a warning.
+  fn compile_ast_types(&self, grammar: &Grammar) -> Vec<RItem> {
+    let cx = self.cx;
+    let mut items = vec![];
+    for ast_type in &grammar.ast_types {
+      let type_name = ast_type.name;
+      let visibility = if ast_type.is_enum { None } else { Some(quote_tokens!(cx, pub)) };
+      let mut variants = vec![];
+      for constructor in &ast_type.constructors {
+        let action = constructor.action;
+        let mut fields = vec![];
+        let mut params = vec![];
+        let mut args = vec![];
+        let mut fresh_vars = 0;
+        for (idx, ty) in self.ast_fields[&action].iter().cloned().enumerate() {
+          let param = cx.ident_of(format!("field{}", idx).as_str());
+          params.extend(quote_tokens!(cx, $param: $ty,).into_iter());
+          match box_ast_types(cx, &ty, quote_expr!(cx, $param), &grammar.ast_types, &mut fresh_vars) {
+            Some((boxed_ty, boxed_value)) => {
+              fields.extend(quote_tokens!(cx, $visibility $boxed_ty,).into_iter());
+              args.extend(quote_tokens!(cx, $boxed_value,).into_iter());
+            }
+            None => {
+              fields.extend(quote_tokens!(cx, $visibility $ty,).into_iter());
+              args.extend(quote_tokens!(cx, $param,).into_iter());
+            }
+          }
+        }
+        let value = match (constructor.variant, args.is_empty()) {
+          (Some(variant), true) => quote_expr!(cx, $type_name::$variant),
+          (Some(variant), false) => quote_expr!(cx, $type_name::$variant($args)),
+          (None, true) => quote_expr!(cx, $type_name),
+          (None, false) => quote_expr!(cx, $type_name($args))
+        };
+        items.push(quote_item!(cx,
+          fn $action($params) -> $type_name {
+            $value
+          }
+        ).expect("Quote the constructor of a generated type."));
+        match (constructor.variant, fields.is_empty()) {
+          (Some(variant), true) => variants.extend(quote_tokens!(cx, $variant,).into_iter()),
+          (Some(variant), false) => variants.extend(quote_tokens!(cx, $variant($fields),).into_iter()),
+          (None, true) => items.push(quote_item!(cx,
+            #[derive(Clone, Debug, PartialEq)]
+            pub struct $type_name;
+          ).expect("Quote a generated unit struct.")),
+          (None, false) => items.push(quote_item!(cx,
+            #[derive(Clone, Debug, PartialEq)]
+            pub struct $type_name($fields);
+          ).expect("Quote a generated struct."))
+        }
+      }
+      if ast_type.is_enum {
+        items.push(quote_item!(cx,
+          #[derive(Clone, Debug, PartialEq)]
+          pub enum $type_name {
+            $variants
+          }
+        ).expect("Quote a generated enum."));
+      }
+    }
+    items
+  }
+
+  /// The values of the expression are the fields of the generated type, tuples are expanded as for any semantic action.
+  fn ast_constructor_fields(&self, expr: &Box<Expression>) -> Vec<RTy> {
+    let ty = expr.return_type(self.cx);
+    match expr.ty {
+      ExprTy::Tuple(ref indexes) if indexes.len() == 0 => vec![],
+      ExprTy::Tuple(ref indexes) if indexes.len() > 1 => {
+        match ty.node {
+          rust::TyKind::Tup(ref tys) => tys.clone(),
+          _ => vec![ty.clone()]
+        }
+      }
+      _ => vec![ty]
     }
   }
 
@@ -153,11 +475,12 @@ impl<'cx> CodeGenerator<'cx>
   }
 
   fn compile_star_body(&self, expr: Ident, result_init: RExpr, result: RExpr) -> RExpr {
+    let next_call = self.function_gen.call(expr, quote_expr!(self.cx, state.stream()));
     quote_expr!(self.cx, {
       let mut state = $result_init;
-      let mut next = $expr(state.stream());
+      let mut next = $next_call;
       while state.soft_merge(next) {
-        next = $expr(state.stream());
+        next = $next_call;
       }
       $result
     })
@@ -165,10 +488,11 @@ impl<'cx> CodeGenerator<'cx>
 
   fn compile_sequence_recognizer_body(&self, exprs: Vec<GenFunNames>) -> RExpr {
     map_foldr_init(exprs,
-      |name: Ident| quote_expr!(self.cx, $name(stream)),
+      |name: Ident| self.call(name),
       |name| name.recognizer,
       |accu: RExpr, name: Ident| {
-        quote_expr!(self.cx, $name(stream).and_then(|success| {
+        let call = self.call(name);
+        quote_expr!(self.cx, $call.and_then(|success| {
           let stream = success.stream;
           $accu
         }))
@@ -192,8 +516,9 @@ impl<'cx> CodeGenerator<'cx>
       |(accu, state_idx): (RExpr, usize), name: Ident| {
         let state_idx = state_idx - 1;
         let state_name = state_names[state_idx];
+        let call = self.call(name);
         (
-          quote_expr!(self.cx, $name(stream).and_then(move |$state_name| {
+          quote_expr!(self.cx, $call.and_then(move |$state_name| {
             let stream = $state_name.stream.clone();
             $accu
           })),
@@ -203,6 +528,173 @@ impl<'cx> CodeGenerator<'cx>
     ).0
   }
 
+  fn compile_choice_chain(&self, alternatives: Vec<Ident>) -> RExpr {
+    let cx = self.cx;
+    map_foldr_init(alternatives,
+      |name: Ident| self.call(name),
+      |name| name,
+      |accu: RExpr, name: Ident| {
+        let call = self.call_with_clone(name);
+        quote_expr!(cx, $call.or_else_merge(|| $accu))
+      }
+    )
+  }
+
+  /// The alternatives are split in segments starting at the dispatched alternatives. The function `choice_tail` of a segment tries its alternatives and then calls the one of the next segment, so the alternative `i` is tried directly by calling the tail starting at `i` if the next character is in its dispatch class. The alternatives before `i` fail on this character without consuming input, they are only called for their errors, by the function `choice_replay` merging the errors of the previous segments, when the error of the tail is at the position the choice started; otherwise their errors would be discarded by the merge. Every alternative is called once in the tails and once in the replays.
+  fn compile_dispatched_choice(&mut self, parent: &Box<Expression>, dispatch: &ChoiceDispatch,
+    alternatives: Vec<GenFunNames>) -> (RExpr, RExpr)
+  {
+    let starts: Vec<usize> = dispatch.classes.iter().enumerate()
+      .filter(|&(_, class)| !class.is_empty())
+      .map(|(idx, _)| idx)
+      .collect();
+    let recognizers: Vec<Ident> = alternatives.iter().map(|name| name.recognizer).collect();
+    let parsers: Vec<Ident> = alternatives.iter().map(|name| name.parser).collect();
+    let segment_end = |seg: usize| if seg + 1 < starts.len() { starts[seg + 1] } else { alternatives.len() };
+
+    let mut tails: Vec<GenFunNames> = vec![];
+    for seg in (0..starts.len()).rev() {
+      let mut segment_recognizers = recognizers[starts[seg]..segment_end(seg)].to_vec();
+      let mut segment_parsers = parsers[starts[seg]..segment_end(seg)].to_vec();
+      if let Some(next_tail) = tails.last() {
+        segment_recognizers.push(next_tail.recognizer);
+        segment_parsers.push(next_tail.parser);
+      }
+      let recognizer_body = self.compile_choice_chain(segment_recognizers);
+      let parser_body = self.compile_choice_chain(segment_parsers);
+      let tail = self.function_gen.generate_expr("choice_tail", self.current_rule_name, parent.kind(),
+        recognizer_body, parser_body);
+      tails.push(tail);
+    }
+    tails.reverse();
+
+    let mut replays: Vec<Ident> = vec![];
+    for seg in 0..starts.len() {
+      let previous_start = if seg == 0 { 0 } else { starts[seg - 1] };
+      let mut calls = vec![];
+      if let Some(&previous_replay) = replays.last() {
+        calls.push(previous_replay);
+      }
+      calls.extend(recognizers[previous_start..starts[seg]].iter().cloned());
+      let body = self.compile_choice_replay(calls);
+      let replay = self.function_gen.generate_unit_expr("choice_replay", self.current_rule_name,
+        FunctionKind::Recognizer, body);
+      replays.push(replay.recognizer);
+    }
+
+    let mut recognizer_chain = recognizers[..starts[0]].to_vec();
+    recognizer_chain.push(tails[0].recognizer);
+    let mut parser_chain = parsers[..starts[0]].to_vec();
+    parser_chain.push(tails[0].parser);
+    let mut recognizer_arms = vec![];
+    let mut parser_arms = vec![];
+    for seg in 0..starts.len() {
+      let class = &dispatch.classes[starts[seg]];
+      recognizer_arms.push((self.compile_class_condition(class),
+        self.compile_dispatched_arm(tails[seg].recognizer, replays[seg])));
+      parser_arms.push((self.compile_class_condition(class),
+        self.compile_dispatched_arm(tails[seg].parser, replays[seg])));
+    }
+    let recognizer_chain = self.compile_choice_chain(recognizer_chain);
+    let parser_chain = self.compile_choice_chain(parser_chain);
+    (self.compile_dispatch_match(parent.span, recognizer_arms, recognizer_chain),
+     self.compile_dispatch_match(parent.span, parser_arms, parser_chain))
+  }
+
+  /// The state of the first call with the errors of the next ones.
+  fn compile_choice_replay(&self, replayed: Vec<Ident>) -> RExpr {
+    let cx = self.cx;
+    let mut calls = replayed.into_iter().map(|name| self.call_with_clone(name));
+    let first = calls.next().expect("A replay calls at least one alternative.");
+    calls.fold(first, |accu, call| quote_expr!(cx, $accu.merge_error($call.error)))
+  }
+
+  fn compile_dispatched_arm(&self, tail: Ident, replay: Ident) -> RExpr {
+    let tail_call = self.call_with_clone(tail);
+    let replay_call = self.call_with_clone(replay);
+    quote_expr!(self.cx, {
+      let state = $tail_call;
+      if state.error_beyond(&stream) {
+        state
+      }
+      else {
+        state.merge_error($replay_call.error)
+      }
+    })
+  }
+
+  /// Matches the next character against the conditions of the arms, `default` is called if none is satisfied.
+  fn compile_dispatch_match(&self, sp: Span, arms: Vec<(RExpr, RExpr)>, default: RExpr) -> RExpr {
+    let cx = self.cx;
+    let mut arms: Vec<rust::Arm> = arms.into_iter()
+      .map(|(condition, body)| rust::Arm {
+        attrs: vec![],
+        pats: vec![cx.pat_some(sp, cx.pat_ident(sp, cx.ident_of("current")))],
+        guard: Some(condition),
+        body: body
+      })
+      .collect();
+    arms.push(cx.arm(sp, vec![cx.pat_wild(sp)], default));
+    cx.expr_match(sp, quote_expr!(cx, stream.clone().next()), arms)
+  }
+
+  /// Condition testing if the character `current` belongs to one of the intervals.
+  fn compile_class_condition(&mut self, intervals: &Vec<CharacterInterval>) -> RExpr {
+    let cx = self.cx;
+    let intervals = normalize_intervals(intervals.clone());
+    if intervals.len() > MAX_COMPARED_INTERVALS {
+      let table = self.char_class_table(intervals);
+      return quote_expr!(cx, $table.contains(current));
+    }
+    let mut seq_it = intervals.iter();
+    let CharacterInterval{lo, hi} = *seq_it.next()
+      .expect("Empty character intervals should be forbidden at the parsing stage.");
+    seq_it.fold(quote_expr!(cx, (current >= $lo && current <= $hi)),
+      |accu, &CharacterInterval{lo, hi}| {
+        quote_expr!(cx, $accu || (current >= $lo && current <= $hi))
+      }
+    )
+  }
+
+  /// Name of the static table of the class, it is declared the first time the class is encountered.
+  fn char_class_table(&mut self, intervals: Vec<CharacterInterval>) -> Ident {
+    let cx = self.cx;
+    let key: Vec<(char, char)> = intervals.iter().map(|interval| (interval.lo, interval.hi)).collect();
+    if let Some(&name) = self.char_class_tables.get(&key) {
+      return name;
+    }
+    let name = cx.ident_of(format!("CHAR_CLASS_{}", self.char_class_tables.len()).as_str());
+    let mut ascii = [0u64; 2];
+    let mut ranges = vec![];
+    for (lo, hi) in key.iter().cloned() {
+      for code in (lo as u32)..min(hi as u32 + 1, 128) {
+        ascii[(code / 64) as usize] |= 1 << (code % 64);
+      }
+      if hi as u32 >= 128 {
+        let lo = if (lo as u32) < 128 { '\u{80}' } else { lo };
+        ranges.push(quote_expr!(cx, ($lo, $hi)));
+      }
+    }
+    let (ascii_lo, ascii_hi) = (ascii[0], ascii[1]);
+    let ranges = cx.expr_vec_slice(rust::DUMMY_SP, ranges);
+    self.char_class_items.push(quote_item!(cx,
+      static $name: oak_runtime::CharClass = oak_runtime::CharClass {
+        ascii: [$ascii_lo, $ascii_hi],
+        ranges: $ranges
+      };
+    ).expect("Quote a character class table."));
+    self.char_class_tables.insert(key, name);
+    name
+  }
+
+  fn call(&self, fun: Ident) -> RExpr {
+    self.function_gen.call(fun, quote_expr!(self.cx, stream))
+  }
+
+  fn call_with_clone(&self, fun: Ident) -> RExpr {
+    self.function_gen.call(fun, quote_expr!(self.cx, stream.clone()))
+  }
+
   fn compile_sequence_result(&self, parent: &Box<Expression>, state_names: &Vec<Ident>) -> RExpr {
     let tuple_indexes = parent.tuple_indexes();
 
@@ -238,13 +730,84 @@ impl<'cx> CodeGenerator<'cx>
         vec![access_data]
       }
     };
+    let action_params =
+      if self.context_actions.contains(&action_name) {
+        let mut params = vec![quote_expr!(self.cx, context)];
+        params.extend(action_params.into_iter());
+        params
+      } else {
+        action_params
+      };
     self.cx.expr_call_ident(parent.span, action_name, action_params)
   }
+
+  fn compile_operator(&self, precedence: usize, associativity: Associativity, action: RExpr) -> RExpr {
+    let cx = self.cx;
+    let kind = match associativity {
+      Associativity::Left => quote_expr!(cx, oak_runtime::OperatorKind::InfixLeft($action)),
+      Associativity::Right => quote_expr!(cx, oak_runtime::OperatorKind::InfixRight($action)),
+      Associativity::Prefix => quote_expr!(cx, oak_runtime::OperatorKind::Prefix($action)),
+      Associativity::Postfix => quote_expr!(cx, oak_runtime::OperatorKind::Postfix($action))
+    };
+    quote_expr!(cx, oak_runtime::PrecedenceOperator {
+      precedence: $precedence,
+      kind: $kind
+    })
+  }
+
+  /// Closure `|stream, context| fun(stream, ...)` called back by the precedence climbing combinator.
+  fn compile_operand_closure(&self, fun: Ident) -> RExpr {
+    let call = self.function_gen.call_from_context(fun,
+      quote_expr!(self.cx, stream), quote_expr!(self.cx, context));
+    quote_expr!(self.cx, |stream, context| $call)
+  }
+
+  /// Closure `|op, stream, context| ...` recognizing the operator number `op` of the table.
+  fn compile_operators_closure(&self, sp: Span, recognizers: Vec<Ident>) -> RExpr {
+    let cx = self.cx;
+    let mut arms: Vec<rust::Arm> = recognizers.into_iter().enumerate()
+      .map(|(idx, recognizer)| {
+        let call = self.function_gen.call_from_context(recognizer,
+          quote_expr!(cx, stream), quote_expr!(cx, context));
+        cx.arm(sp, vec![cx.pat_lit(sp, cx.expr_usize(sp, idx))], call)
+      })
+      .collect();
+    arms.push(cx.arm_unreachable(sp));
+    let dispatch = cx.expr_match(sp, quote_expr!(cx, op), arms);
+    quote_expr!(cx, |op, stream, context| $dispatch)
+  }
 }
 
 impl<'cx> Visitor<Expression, GenFunNames> for CodeGenerator<'cx>
 {
+  /// The functions are generated once for all the structurally identical expressions.
+  fn visit_expr(&mut self, expr: &Box<Expression>) -> GenFunNames {
+    match expr.shared_id {
+      Some(id) => {
+        if let Some(names) = self.shared_exprs.get(&id).cloned() {
+          return names;
+        }
+        let names = walk_expr(self, expr);
+        self.shared_exprs.insert(id, names);
+        names
+      }
+      None => walk_expr(self, expr)
+    }
+  }
+
   fn visit_str_literal(&mut self, parent: &Box<Expression>, lit_str: &String) -> GenFunNames {
+    if let Some(ref merged_literals) = parent.merged_literals {
+      let cx = self.cx;
+      let lits = cx.expr_vec_slice(parent.span, merged_literals.iter()
+        .map(|lit| {
+          let lit = lit.as_str();
+          quote_expr!(cx, $lit)
+        })
+        .collect());
+      let body = quote_expr!(cx, oak_runtime::match_literal_sequence(stream, $lits));
+      return self.function_gen.generate_expr("str_literal", self.current_rule_name, parent.kind(),
+        body.clone(), body);
+    }
     let lit_str = lit_str.as_str();
     self.function_gen.generate_expr("str_literal", self.current_rule_name, parent.kind(),
       quote_expr!(self.cx, oak_runtime::recognize_match_literal(stream, $lit_str)),
@@ -252,6 +815,16 @@ impl<'cx> Visitor<Expression, GenFunNames> for CodeGenerator<'cx>
     )
   }
 
+  fn visit_indentation(&mut self, parent: &Box<Expression>, kind: IndentKind) -> GenFunNames {
+    let cx = self.cx;
+    let (desc, body) = match kind {
+      IndentKind::Indent => ("indent", quote_expr!(cx, oak_runtime::recognize_indent(stream))),
+      IndentKind::Dedent => ("dedent", quote_expr!(cx, oak_runtime::recognize_dedent(stream))),
+      IndentKind::Samedent => ("samedent", quote_expr!(cx, oak_runtime::recognize_samedent(stream)))
+    };
+    self.function_gen.generate_unit_expr(desc, self.current_rule_name, parent.kind(), body)
+  }
+
   fn visit_non_terminal_symbol(&mut self, _parent: &Box<Expression>, rule_id: Ident) -> GenFunNames {
     self.function_gen.names_of_rule(rule_id)
   }
@@ -269,15 +842,7 @@ impl<'cx> Visitor<Expression, GenFunNames> for CodeGenerator<'cx>
 
   fn visit_character_class(&mut self, parent: &Box<Expression>, classes: &CharacterClassExpr) -> GenFunNames {
     let cx = self.cx;
-    let mut seq_it = classes.intervals.iter();
-
-    let CharacterInterval{lo, hi} = *seq_it.next()
-      .expect("Empty character intervals should be forbidden at the parsing stage.");
-    let cond = seq_it.fold(quote_expr!(cx, (current >= $lo && current <= $hi)),
-      |accu, &CharacterInterval{lo, hi}| {
-        quote_expr!(cx, $accu || (current >= $lo && current <= $hi))
-      }
-    );
+    let cond = self.compile_class_condition(&classes.intervals);
 
     let classes_desc = format!("{}", classes).escape_default();
     let classes_desc_str = classes_desc.as_str();
@@ -312,23 +877,19 @@ impl<'cx> Visitor<Expression, GenFunNames> for CodeGenerator<'cx>
   }
 
   fn visit_choice(&mut self, parent: &Box<Expression>, choices: &Vec<Box<Expression>>) -> GenFunNames {
+    if let Some(trie) = LiteralTrie::from_choice(choices) {
+      let body = trie.compile(self.cx, parent.span);
+      return self.function_gen.generate_unit_expr("literal_choice", self.current_rule_name, parent.kind(), body);
+    }
     let exprs = walk_exprs(self, choices);
-
-    let cx = self.cx;
-    let init = |name: Ident| quote_expr!(cx, $name(stream));
-    let make_body = |accu:RExpr, name:Ident| {
-      quote_expr!(cx, $name(stream.clone()).or_else_merge(|| $accu))
+    let (recognizer_body, parser_body) = match parent.dispatch {
+      Some(ref dispatch) => self.compile_dispatched_choice(parent, dispatch, exprs),
+      None => {
+        let recognizers = exprs.iter().map(|name| name.recognizer).collect();
+        let parsers = exprs.iter().map(|name| name.parser).collect();
+        (self.compile_choice_chain(recognizers), self.compile_choice_chain(parsers))
+      }
     };
-    let recognizer_body = map_foldr_init(exprs.clone(),
-      &init,
-      |name| name.recognizer,
-      &make_body
-    );
-    let parser_body = map_foldr_init(exprs,
-      init,
-      |name| name.parser,
-      make_body
-    );
     self.function_gen.generate_expr("choice", self.current_rule_name, parent.kind(),
       recognizer_body,
       parser_body)
@@ -354,21 +915,40 @@ impl<'cx> Visitor<Expression, GenFunNames> for CodeGenerator<'cx>
 
   fn visit_optional(&mut self, parent: &Box<Expression>, expr: &Box<Expression>) -> GenFunNames {
     let GenFunNames{recognizer, parser} = self.visit_expr(expr);
+    let recognizer_call = self.call_with_clone(recognizer);
+    let parser_call = self.call_with_clone(parser);
     let recognizer_body = quote_expr!(self.cx,
-      oak_runtime::optional_recognizer($recognizer(stream.clone()), stream)
+      oak_runtime::optional_recognizer($recognizer_call, stream)
     );
     let parser_body = quote_expr!(self.cx,
-      oak_runtime::optional_parser($parser(stream.clone()), stream)
+      oak_runtime::optional_parser($parser_call, stream)
     );
     self.function_gen.generate_expr("optional", self.current_rule_name, parent.kind(),
       recognizer_body,
       parser_body)
   }
 
+  fn visit_recovery(&mut self, parent: &Box<Expression>, expr: &Box<Expression>, sync: &String) -> GenFunNames {
+    let GenFunNames{recognizer, parser} = self.visit_expr(expr);
+    let recognizer_call = self.call_with_clone(recognizer);
+    let parser_call = self.call_with_clone(parser);
+    let sync = sync.as_str();
+    let recognizer_body = quote_expr!(self.cx,
+      oak_runtime::recover_recognizer($recognizer_call, stream, $sync)
+    );
+    let parser_body = quote_expr!(self.cx,
+      oak_runtime::recover_parser($parser_call, stream, $sync)
+    );
+    self.function_gen.generate_expr("recovery", self.current_rule_name, parent.kind(),
+      recognizer_body,
+      parser_body)
+  }
+
   fn visit_not_predicate(&mut self, parent: &Box<Expression>, expr: &Box<Expression>) -> GenFunNames {
     let recognizer_name = self.visit_expr(expr).recognizer;
+    let recognizer_call = self.call_with_clone(recognizer_name);
     let body = quote_expr!(self.cx,
-      oak_runtime::not_predicate($recognizer_name(stream.clone()), stream)
+      oak_runtime::not_predicate($recognizer_call, stream)
     );
     self.function_gen.generate_unit_expr(
       "not_predicate", self.current_rule_name, parent.kind(), body)
@@ -376,23 +956,61 @@ impl<'cx> Visitor<Expression, GenFunNames> for CodeGenerator<'cx>
 
   fn visit_and_predicate(&mut self, parent: &Box<Expression>, expr: &Box<Expression>) -> GenFunNames {
     let recognizer_name = self.visit_expr(expr).recognizer;
+    let recognizer_call = self.call_with_clone(recognizer_name);
     let body = quote_expr!(self.cx,
-      oak_runtime::and_predicate($recognizer_name(stream.clone()), stream)
+      oak_runtime::and_predicate($recognizer_call, stream)
     );
     self.function_gen.generate_unit_expr(
       "and_predicate", self.current_rule_name, parent.kind(), body)
   }
 
+  /// The recognizer uses the same table as the parser but with actions that build unit values.
+  /// The threaded arguments are packed into the context given to the precedence climbing combinator.
+  fn visit_operator_table(&mut self, parent: &Box<Expression>, table: &OperatorTableExpr<Expression>) -> GenFunNames {
+    let cx = self.cx;
+    let operand = self.visit_expr(&table.operand);
+    let mut op_recognizers = vec![];
+    let mut recognizer_ops = vec![];
+    let mut parser_ops = vec![];
+    for (precedence, associativity, op) in table.operators() {
+      op_recognizers.push(self.visit_expr(&op.expr).recognizer);
+      if self.context_actions.contains(&op.action) {
+        cx.span_err(op.expr.span, "Actions of an operator table cannot take the parsing context.");
+      }
+      let unit_action =
+        if associativity.is_unary() { quote_expr!(cx, oak_runtime::unit_unary_operator) }
+        else { quote_expr!(cx, oak_runtime::unit_binary_operator) };
+      let action = op.action;
+      recognizer_ops.push(self.compile_operator(precedence, associativity, unit_action));
+      parser_ops.push(self.compile_operator(precedence, associativity, quote_expr!(cx, $action)));
+    }
+    let recognizer_table = cx.expr_vec_slice(parent.span, recognizer_ops);
+    let parser_table = cx.expr_vec_slice(parent.span, parser_ops);
+    let GenFunNames{recognizer, parser} = operand;
+    let context = self.function_gen.packed_context();
+    let recognizer_operand = self.compile_operand_closure(recognizer);
+    let parser_operand = self.compile_operand_closure(parser);
+    let operators = self.compile_operators_closure(parent.span, op_recognizers);
+    self.function_gen.generate_expr("operator_table", self.current_rule_name, parent.kind(),
+      quote_expr!(cx, oak_runtime::precedence_climbing(stream, $context,
+        $recognizer_operand, $operators, $recognizer_table)),
+      quote_expr!(cx, oak_runtime::precedence_climbing(stream, $context,
+        $parser_operand, $operators, $parser_table)))
+  }
+
   fn visit_semantic_action(&mut self, parent: &Box<Expression>,
     expr: &Box<Expression>, action_name: Ident) -> GenFunNames
   {
     let GenFunNames{recognizer, parser} = self.visit_expr(expr);
-    let recognizer_body = quote_expr!(self.cx,
-      $recognizer(stream)
-    );
+    if self.ast_actions.contains(&action_name) {
+      let fields = self.ast_constructor_fields(expr);
+      self.ast_fields.insert(action_name, fields);
+    }
+    let recognizer_body = self.call(recognizer);
     let action_call = self.compile_semantic_action_call(parent, expr, action_name);
+    let parser_call = self.call(parser);
     let parser_body = quote_expr!(self.cx,
-      $parser(stream).map_data(|data| $action_call)
+      $parser_call.map_data(|data| $action_call)
     );
     self.function_gen.generate_expr("semantic_action", self.current_rule_name, parent.kind(),
       recognizer_body,
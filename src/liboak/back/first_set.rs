@@ -0,0 +1,229 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The FIRST set of an expression is the set of characters it can consume first. An expression that is not nullable fails when the next character is not in its FIRST set.
+//!
+//! A choice `e1 / e2 / e3` is dispatched on its first character: if the next character `c` is in the FIRST set of `e3` but not in the ones of `e1` and `e2`, and these are not nullable, then `e1` and `e2` fail and `e3` can be tried directly. The alternative `e1` is never dispatched since it is tried first anyway.
+//!
+//! The dispatches are computed on the typed grammar once optimised, when the Rust types are generated, and are stored on the choices (see `back::type_gen`).
+
+use middle::typing::ast::*;
+use std::collections::HashMap;
+use std::char;
+
+/// The alternative `i` of a choice is tried directly if the next character is in `classes[i]`, all the alternatives before it failing on this character.
+#[derive(Clone)]
+pub struct ChoiceDispatch
+{
+  pub classes: Vec<Vec<CharacterInterval>>
+}
+
+impl ChoiceDispatch
+{
+  pub fn is_useful(&self) -> bool {
+    self.classes.iter().any(|class| !class.is_empty())
+  }
+}
+
+#[derive(Clone)]
+pub struct First
+{
+  /// Characters that can be consumed first.
+  pub class: Vec<CharacterInterval>,
+  /// The expression can succeed without consuming input.
+  pub nullable: bool
+}
+
+impl First
+{
+  fn new(class: Vec<CharacterInterval>, nullable: bool) -> First {
+    First {
+      class: class,
+      nullable: nullable
+    }
+  }
+
+  fn union(self, other: First) -> First {
+    let mut class = self.class;
+    class.extend(other.class.into_iter());
+    First::new(class, self.nullable || other.nullable)
+  }
+}
+
+/// Computes the FIRST sets of expressions, `None` if it is unknown. The FIRST sets of the indentation terminals and of the recovery expressions are unknown since they can consume characters not appearing in the grammar.
+pub struct FirstSet<'a>
+{
+  rules: &'a OrderedMap<Ident, Rule>,
+  rule_firsts: HashMap<Ident, Option<First>>,
+  rule_stack: Vec<Ident>
+}
+
+impl<'a> FirstSet<'a>
+{
+  pub fn new(rules: &'a OrderedMap<Ident, Rule>) -> FirstSet<'a> {
+    FirstSet {
+      rules: rules,
+      rule_firsts: HashMap::new(),
+      rule_stack: vec![]
+    }
+  }
+
+  /// The dispatch of the choice with the alternatives `alternatives`, `None` if no alternative can be tried directly.
+  pub fn dispatch(&mut self, alternatives: &Vec<Box<Expression>>) -> Option<ChoiceDispatch> {
+    let mut classes = vec![];
+    let mut previous_firsts = vec![];
+    let mut dispatchable = true;
+    for (idx, alternative) in alternatives.iter().enumerate() {
+      if !dispatchable {
+        classes.push(vec![]);
+        continue;
+      }
+      match self.first(alternative) {
+        Some(first) => {
+          let first_class = normalize_intervals(first.class);
+          classes.push(
+            if idx == 0 { vec![] }
+            else { subtract_class(&first_class, &previous_firsts) });
+          previous_firsts = normalize_intervals(previous_firsts.into_iter().chain(first_class.into_iter()).collect());
+          dispatchable = !first.nullable;
+        }
+        None => {
+          classes.push(vec![]);
+          dispatchable = false;
+        }
+      }
+    }
+    let dispatch = ChoiceDispatch {
+      classes: classes
+    };
+    if dispatch.is_useful() { Some(dispatch) } else { None }
+  }
+
+  pub fn first(&mut self, expr: &Box<Expression>) -> Option<First> {
+    match &expr.node {
+      &StrLiteral(ref lit) => Some(match lit.chars().next() {
+        Some(c) => First::new(vec![CharacterInterval { lo: c, hi: c }], false),
+        None => First::new(vec![], true)
+      }),
+      &AnySingleChar => Some(First::new(vec![CharacterInterval { lo: '\u{0}', hi: char::MAX }], false)),
+      &CharacterClass(ref class) => Some(First::new(class.intervals.clone(), false)),
+      &NonTerminalSymbol(id) => self.first_of_rule(id),
+      &Sequence(ref exprs) => {
+        let mut first = First::new(vec![], true);
+        for expr in exprs {
+          match self.first(expr) {
+            Some(expr_first) => {
+              first = First::new(first.class, false).union(expr_first);
+              if !first.nullable {
+                break;
+              }
+            }
+            None => return None
+          }
+        }
+        Some(first)
+      }
+      &Choice(ref exprs) => {
+        let mut first = First::new(vec![], false);
+        for expr in exprs {
+          match self.first(expr) {
+            Some(expr_first) => first = first.union(expr_first),
+            None => return None
+          }
+        }
+        Some(first)
+      }
+      &ZeroOrMore(ref expr) | &Optional(ref expr) =>
+        self.first(expr).map(|first| First::new(first.class, true)),
+      &OneOrMore(ref expr) | &SemanticAction(ref expr, _) => self.first(expr),
+      &NotPredicate(_) | &AndPredicate(_) => Some(First::new(vec![], true)),
+      &OperatorTable(ref table) => self.first_of_table(table),
+      &Recovery(_, _) | &Indentation(_) => None
+    }
+  }
+
+  fn first_of_rule(&mut self, id: Ident) -> Option<First> {
+    if let Some(first) = self.rule_firsts.get(&id) {
+      return first.clone();
+    }
+    if self.rule_stack.contains(&id) {
+      return None;
+    }
+    let rules = self.rules;
+    let first = match rules.get(&id) {
+      Some(rule) => {
+        self.rule_stack.push(id);
+        let first = self.first(&rule.def);
+        self.rule_stack.pop();
+        first
+      }
+      None => None
+    };
+    self.rule_firsts.insert(id, first.clone());
+    first
+  }
+
+  /// The table starts with the operand or a prefix operator, or with any operator if the operand is nullable.
+  fn first_of_table(&mut self, table: &OperatorTableExpr<Expression>) -> Option<First> {
+    let mut first = match self.first(&table.operand) {
+      Some(first) => first,
+      None => return None
+    };
+    let nullable = first.nullable;
+    for (_, associativity, op) in table.operators() {
+      if nullable || associativity == Associativity::Prefix {
+        match self.first(&op.expr) {
+          Some(op_first) => first.class.extend(op_first.class.into_iter()),
+          None => return None
+        }
+      }
+    }
+    Some(first)
+  }
+}
+
+fn char_before(c: char) -> Option<char> {
+  match c {
+    '\u{0}' => None,
+    '\u{E000}' => Some('\u{D7FF}'),
+    c => char::from_u32(c as u32 - 1)
+  }
+}
+
+/// Characters of `class` not in `removed`, both must be normalized.
+fn subtract_class(class: &Vec<CharacterInterval>, removed: &Vec<CharacterInterval>) -> Vec<CharacterInterval> {
+  let mut result = vec![];
+  for interval in class {
+    let mut lo = Some(interval.lo);
+    for r in removed {
+      let current = match lo {
+        Some(current) => current,
+        None => break
+      };
+      if r.hi < current || r.lo > interval.hi {
+        continue;
+      }
+      if r.lo > current {
+        result.push(CharacterInterval { lo: current, hi: char_before(r.lo).unwrap() });
+      }
+      lo = char_after(r.hi);
+    }
+    if let Some(lo) = lo {
+      if lo <= interval.hi {
+        result.push(CharacterInterval { lo: lo, hi: interval.hi });
+      }
+    }
+  }
+  result
+}
@@ -15,12 +15,28 @@
 use back::ast::*;
 use back::ast::FunctionKind::*;
 use back::naming::*;
+use rust;
+use rust::AstBuilder;
+
+/// An argument of type `&mut ty` passed down, along with the stream, to every generated function.
+#[derive(Clone)]
+pub struct ThreadedArg
+{
+  pub name: Ident,
+  pub ty: RTy
+}
 
 pub struct FunctionGenerator<'cx>
 {
   cx: &'cx ExtCtxt<'cx>,
   name_factory: NameFactory<'cx>,
-  functions: HashMap<Ident, RItem>
+  functions: OrderedMap<Ident, RItem>,
+  threaded_args: Vec<ThreadedArg>,
+  stream_bounds: Vec<Vec<rust::TokenTree>>,
+  syntax_tree: bool,
+  trace: bool,
+  profile: bool,
+  rule_context: bool
 }
 
 impl<'cx> FunctionGenerator<'cx>
@@ -29,10 +45,87 @@ impl<'cx> FunctionGenerator<'cx>
     FunctionGenerator {
       cx: cx,
       name_factory: NameFactory::new(cx),
-      functions: HashMap::new()
+      functions: OrderedMap::new(),
+      threaded_args: vec![],
+      stream_bounds: vec![],
+      syntax_tree: false,
+      trace: false,
+      profile: false,
+      rule_context: false
     }
   }
 
+  /// Every generated function will take an extra parameter `name: &mut ty`.
+  pub fn thread_arg(&mut self, name: Ident, ty: RTy) {
+    self.threaded_args.push(ThreadedArg{name: name, ty: ty});
+  }
+
+  /// Every generated function will require the stream type to implement `bound`.
+  pub fn add_stream_bound(&mut self, bound: Vec<rust::TokenTree>) {
+    self.stream_bounds.push(bound);
+  }
+
+  /// Rule recognizers will signal their start and success to the stream, and a function `cst_<rule>` building the concrete syntax tree will be generated for each rule.
+  pub fn build_syntax_tree(&mut self) {
+    self.add_stream_bound(quote_tokens!(self.cx, oak_runtime::RuleEvents));
+    self.syntax_tree = true;
+  }
+
+  /// Every generated function will take an extra parameter `tracer: &mut oak_runtime::Tracer` and rules will notify it of their start, success and failure.
+  pub fn trace_rules(&mut self) {
+    let cx = self.cx;
+    self.thread_arg(cx.ident_of("tracer"), quote_ty!(cx, oak_runtime::Tracer));
+    self.add_stream_bound(quote_tokens!(cx, oak_runtime::Location));
+    self.trace = true;
+  }
+
+  /// Every generated function will take an extra parameter `profiler: &mut oak_runtime::Profiler` recording its invocations.
+  pub fn profile_functions(&mut self) {
+    let cx = self.cx;
+    self.thread_arg(cx.ident_of("profiler"), quote_ty!(cx, oak_runtime::Profiler));
+    self.add_stream_bound(quote_tokens!(cx, oak_runtime::StreamOffset));
+    self.profile = true;
+  }
+
+  /// Rules will record in their errors that these occurred while parsing them.
+  pub fn record_rule_context(&mut self) {
+    self.rule_context = true;
+  }
+
+  /// Call of the generated function `fun` with the stream `stream` and the threaded arguments.
+  pub fn call(&self, fun: Ident, stream: RExpr) -> RExpr {
+    let mut args = vec![stream];
+    args.extend(self.threaded_args.iter()
+      .map(|arg| self.cx.expr_ident(rust::DUMMY_SP, arg.name)));
+    self.cx.expr_call_ident(rust::DUMMY_SP, fun, args)
+  }
+
+  /// Threaded arguments packed in a tuple, it is the context given to the runtime combinators
+  /// calling back generated functions.
+  pub fn packed_context(&self) -> RExpr {
+    let cx = self.cx;
+    let args = self.threaded_args.iter()
+      .map(|arg| {
+        let name = arg.name;
+        quote_expr!(cx, &mut *$name)
+      })
+      .collect();
+    let context = cx.expr_tuple(rust::DUMMY_SP, args);
+    quote_expr!(cx, &mut $context)
+  }
+
+  /// Call of the generated function `fun` from a closure receiving the context built by `packed_context`.
+  pub fn call_from_context(&self, fun: Ident, stream: RExpr, context: RExpr) -> RExpr {
+    let cx = self.cx;
+    let mut args = vec![stream];
+    args.extend((0..self.threaded_args.len())
+      .map(|idx| {
+        let arg = cx.expr_tup_field_access(rust::DUMMY_SP, context.clone(), idx);
+        quote_expr!(cx, &mut *$arg)
+      }));
+    cx.expr_call_ident(rust::DUMMY_SP, fun, args)
+  }
+
   fn generate_recognizer(&mut self, names: GenFunNames, recognizer_body: RExpr, public: bool) {
     self.push_unit_fun(names.recognizer, recognizer_body, public);
   }
@@ -40,7 +133,7 @@ impl<'cx> FunctionGenerator<'cx>
   fn generate_parser_alias(&mut self, kind: FunctionKind, names: GenFunNames, public: bool) -> bool {
     let GenFunNames{recognizer, parser} = names;
     if kind == ParserAlias {
-      let recognizer_call = quote_expr!(self.cx, $recognizer(stream));
+      let recognizer_call = self.call(recognizer, quote_expr!(self.cx, stream));
       self.push_unit_fun(parser, recognizer_call, public);
       true
     } else {
@@ -83,15 +176,95 @@ impl<'cx> FunctionGenerator<'cx>
     names
   }
 
-  pub fn generate_rule(&mut self, kind: FunctionKind, rule_id: Ident, expr_fn_names: GenFunNames) {
-    let cx = self.cx;
+  pub fn generate_rule(&mut self, kind: FunctionKind, rule_id: Ident, expr_fn_names: GenFunNames,
+    attributes: RuleAttributes)
+  {
     let rule_name = self.names_of_rule(rule_id);
     let GenFunNames{recognizer, parser} = expr_fn_names;
-    self.generate(rule_name, kind,
-      quote_expr!(cx, $recognizer(stream)),
-      quote_expr!(cx, $parser(stream)),
-      true
-    )
+    let (mut recognizer_body, mut parser_body) =
+      if attributes.memo {
+        (self.memoise(rule_name.recognizer, recognizer), self.memoise(rule_name.parser, parser))
+      } else {
+        (self.call(recognizer, quote_expr!(self.cx, stream)), self.call(parser, quote_expr!(self.cx, stream)))
+      };
+    if self.syntax_tree {
+      recognizer_body = self.syntax_rule(rule_id, attributes.trivia, recognizer_body);
+      self.generate_cst(rule_id, rule_name.recognizer);
+    }
+    if self.rule_context {
+      recognizer_body = self.record_rule(rule_id, recognizer_body);
+      parser_body = self.record_rule(rule_id, parser_body);
+    }
+    if self.trace {
+      recognizer_body = self.trace_rule(rule_id, recognizer_body);
+      parser_body = self.trace_rule(rule_id, parser_body);
+    }
+    self.generate(rule_name, kind, recognizer_body, parser_body, true)
+  }
+
+  fn trace_rule(&self, rule_id: Ident, body: RExpr) -> RExpr {
+    let rule_name = id_to_string(rule_id);
+    let rule_name = rule_name.as_str();
+    quote_expr!(self.cx, {
+      oak_runtime::Tracer::enter_rule(tracer, $rule_name, &stream);
+      let state = $body;
+      oak_runtime::trace_exit(tracer, $rule_name, &state);
+      state
+    })
+  }
+
+  /// The error produced by the rule records that it occurred while parsing this rule.
+  fn record_rule(&self, rule_id: Ident, body: RExpr) -> RExpr {
+    let rule_name = id_to_string(rule_id);
+    let rule_name = rule_name.as_str();
+    quote_expr!(self.cx,
+      oak_runtime::rule_context(stream, $rule_name, |stream| $body))
+  }
+
+  /// The recognizer of the rule signals its start and its success to the stream.
+  fn syntax_rule(&self, rule_id: Ident, trivia: bool, body: RExpr) -> RExpr {
+    let rule_name = id_to_string(rule_id);
+    let rule_name = rule_name.as_str();
+    quote_expr!(self.cx,
+      oak_runtime::syntax_rule(stream, $rule_name, $trivia, |stream| $body))
+  }
+
+  /// `cst_<rule>` recognizes the rule and builds the concrete syntax tree of the input read.
+  #[allow(unused_imports)] // `quote_tokens` generates a warning.
+  fn generate_cst(&mut self, rule_id: Ident, recognizer: Ident) {
+    let name = self.cx.ident_of(format!("cst_{}", ident_to_lowercase(rule_id)).as_str());
+    let call = self.call(recognizer, quote_expr!(self.cx, stream));
+    let mut params = vec![];
+    for arg in &self.threaded_args {
+      let (arg_name, arg_ty) = (arg.name, arg.ty.clone());
+      params.extend(quote_tokens!(self.cx, , $arg_name: &mut $arg_ty).into_iter());
+    }
+    let function = quote_item!(self.cx,
+      pub fn $name<S>(stream: S $params) -> oak_runtime::ParseState<S, oak_runtime::CstNode> where
+       S: oak_runtime::CharStream + oak_runtime::StreamOffset
+      {
+        oak_runtime::concrete_syntax_tree(stream, |stream| $call)
+      }
+    ).expect("Quotation of a concrete syntax tree function.");
+    self.functions.insert(name, function);
+  }
+
+  /// The state produced by `fun` is looked up in the memoisation table `field` of `memo` before calling it. The extent of the input examined by `fun` is recorded with the state for incremental reparsing.
+  fn memoise(&self, field: Ident, fun: Ident) -> RExpr {
+    let call = self.call(fun, quote_expr!(self.cx, stream.clone()));
+    quote_expr!(self.cx, {
+      let memoised = memo.$field.get(&stream);
+      match memoised {
+        Some(state) => state,
+        None => {
+          let examination = oak_runtime::StreamOffset::start_examination(&stream);
+          let state = $call;
+          let extent = oak_runtime::StreamOffset::end_examination(&stream, examination);
+          memo.$field.insert(&stream, &state, extent);
+          state
+        }
+      }
+    })
   }
 
   pub fn names_of_rule(&mut self, rule_id: Ident) -> GenFunNames {
@@ -105,10 +278,20 @@ impl<'cx> FunctionGenerator<'cx>
     } else {
       None
     };
+    let mut params = vec![];
+    for arg in &self.threaded_args {
+      let (arg_name, arg_ty) = (arg.name, arg.ty.clone());
+      params.extend(quote_tokens!(self.cx, , $arg_name: &mut $arg_ty).into_iter());
+    }
+    let mut bounds = vec![];
+    for bound in &self.stream_bounds {
+      bounds.extend(quote_tokens!(self.cx, + $bound).into_iter());
+    }
+    let body = if self.profile { self.profile_fun(name, body) } else { body };
     let function = quote_item!(self.cx,
       #[inline]
-      $pub_kw fn $name<S>(mut stream: S) -> oak_runtime::ParseState<S, $ty> where
-       S: oak_runtime::CharStream
+      $pub_kw fn $name<S>(mut stream: S $params) -> oak_runtime::ParseState<S, $ty> where
+       S: oak_runtime::CharStream $bounds
       {
         $body
       }
@@ -116,6 +299,17 @@ impl<'cx> FunctionGenerator<'cx>
     self.functions.insert(name, function);
   }
 
+  fn profile_fun(&self, name: Ident, body: RExpr) -> RExpr {
+    let fun_name = id_to_string(name);
+    let fun_name = fun_name.as_str();
+    quote_expr!(self.cx, {
+      let start = profiler.enter(&stream);
+      let state = $body;
+      profiler.exit($fun_name, start, &state);
+      state
+    })
+  }
+
   fn push_unit_fun(&mut self, name: Ident, body: RExpr, public: bool) {
     self.push_fun(name, body, quote_ty!(self.cx, ()), public);
   }
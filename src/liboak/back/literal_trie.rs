@@ -0,0 +1,145 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A choice of literals `l1 / ... / ln` is compiled into a trie of nested `match` on the next character instead of trying every literal in turn.
+//!
+//! The first literal of the choice matching the input wins, even if a longer one also matches. When a node of the trie ends a literal preceding, in the choice, all the literals ending at its ancestors, the stream is recorded in `matched`. The sub-tries without such literals are not generated.
+
+use rust::AstBuilder;
+use back::ast::*;
+use back::ast::Expression_::*;
+use std::str::Chars;
+
+pub struct LiteralTrie
+{
+  /// Literals in the order of the choice.
+  literals: Vec<String>,
+  root: TrieNode
+}
+
+struct TrieNode
+{
+  /// Index in the choice of the literal ending at this node.
+  literal: Option<usize>,
+  /// Children in the order of their first insertion.
+  children: Vec<(char, TrieNode)>
+}
+
+impl TrieNode
+{
+  fn new() -> TrieNode {
+    TrieNode {
+      literal: None,
+      children: vec![]
+    }
+  }
+
+  fn insert(&mut self, mut chars: Chars, idx: usize) {
+    match chars.next() {
+      Some(c) => {
+        let pos = match self.children.iter().position(|&(child_c, _)| child_c == c) {
+          Some(pos) => pos,
+          None => {
+            self.children.push((c, TrieNode::new()));
+            self.children.len() - 1
+          }
+        };
+        self.children[pos].1.insert(chars, idx);
+      }
+      None => {
+        if self.literal.is_none() {
+          self.literal = Some(idx);
+        }
+      }
+    }
+  }
+
+  /// Smallest index of the literals ending in this sub-trie.
+  fn first_literal(&self) -> Option<usize> {
+    self.children.iter()
+      .filter_map(|&(_, ref child)| child.first_literal())
+      .chain(self.literal.into_iter())
+      .min()
+  }
+}
+
+fn improves(candidate: Option<usize>, best: Option<usize>) -> bool {
+  match (candidate, best) {
+    (Some(candidate), Some(best)) => candidate < best,
+    (Some(_), None) => true,
+    _ => false
+  }
+}
+
+impl LiteralTrie
+{
+  /// Returns `None` if the choice is not only made of non-empty literals. The merged literals are excluded since they report the error of the literal that failed.
+  pub fn from_choice(choices: &Vec<Box<Expression>>) -> Option<LiteralTrie> {
+    let mut literals = vec![];
+    let mut root = TrieNode::new();
+    for (idx, choice) in choices.iter().enumerate() {
+      match &choice.node {
+        &StrLiteral(ref lit) if !lit.is_empty() && choice.merged_literals.is_none() => {
+          root.insert(lit.chars(), idx);
+          literals.push(lit.clone());
+        }
+        _ => return None
+      }
+    }
+    Some(LiteralTrie {
+      literals: literals,
+      root: root
+    })
+  }
+
+  /// Body of the recognizer, the parser is an alias since the choice has the unit type.
+  pub fn compile(&self, cx: &ExtCtxt, sp: Span) -> RExpr {
+    let matcher = self.compile_node(cx, sp, &self.root, None);
+    let lits = cx.expr_vec_slice(sp, self.literals.iter()
+      .map(|lit| {
+        let lit = lit.as_str();
+        quote_expr!(cx, $lit)
+      })
+      .collect());
+    quote_expr!(cx, {
+      let past = stream.clone();
+      let mut matched = None;
+      $matcher;
+      oak_runtime::match_literals(past, matched, $lits)
+    })
+  }
+
+  fn compile_node(&self, cx: &ExtCtxt, sp: Span, node: &TrieNode, best: Option<usize>) -> RExpr {
+    let mut arms = vec![];
+    for &(c, ref child) in &node.children {
+      if !improves(child.first_literal(), best) {
+        continue;
+      }
+      let record = improves(child.literal, best);
+      let child_best = if record { child.literal } else { best };
+      let next = self.compile_node(cx, sp, child, child_best);
+      let body =
+        if record { quote_expr!(cx, { matched = Some(stream.clone()); $next }) }
+        else { next };
+      arms.push(cx.arm(sp, vec![cx.pat_some(sp, cx.pat_lit(sp, quote_expr!(cx, $c)))], body));
+    }
+    if arms.is_empty() {
+      quote_expr!(cx, ())
+    }
+    else {
+      arms.push(cx.arm(sp, vec![cx.pat_wild(sp)], quote_expr!(cx, ())));
+      cx.expr_match(sp, quote_expr!(cx, stream.next()), arms)
+    }
+  }
+}
@@ -21,6 +21,8 @@ mod naming;
 mod function;
 mod type_gen;
 mod code_gen;
+mod literal_trie;
+mod first_set;
 mod code_printer;
 mod sum_type;
 
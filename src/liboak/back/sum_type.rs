@@ -33,19 +33,19 @@ pub fn sum_type_analysis(cx: &ExtCtxt, grammar: Grammar)
 pub struct SumType<'a>
 {
   cx: &'a ExtCtxt<'a>,
-  rules: &'a HashMap<Ident, Rule>,
+  rules: &'a OrderedMap<Ident, Rule>,
   bad_type_detected: bool
 }
 
 impl<'a> SumType<'a>
 {
-  fn analyse(cx: &'a ExtCtxt, rules: &'a HashMap<Ident, Rule>) -> bool {
+  fn analyse(cx: &'a ExtCtxt, rules: &'a OrderedMap<Ident, Rule>) -> bool {
     let mut sum_type = SumType::new(cx, rules);
     sum_type.visit_rules();
     !sum_type.bad_type_detected
   }
 
-  fn new(cx: &'a ExtCtxt, rules: &'a HashMap<Ident, Rule>) -> SumType<'a> {
+  fn new(cx: &'a ExtCtxt, rules: &'a OrderedMap<Ident, Rule>) -> SumType<'a> {
     SumType {
       cx: cx,
       rules: rules,
@@ -64,8 +64,8 @@ impl<'a> SumType<'a>
   }
 
   /// Types are compared with their string description, so it is not a semantic comparison but a syntactic one. It should be sufficient for now but see issue #73 for more explanations.
-  fn map_types_to_indices(&self, exprs: &Vec<Box<Expression>>) -> HashMap<String, Vec<usize>> {
-    let mut tys_indices: HashMap<String, Vec<usize>> = HashMap::new();
+  fn map_types_to_indices(&self, exprs: &Vec<Box<Expression>>) -> OrderedMap<String, Vec<usize>> {
+    let mut tys_indices: OrderedMap<String, Vec<usize>> = OrderedMap::new();
     for (idx, expr) in exprs.iter().enumerate() {
       let mut updated = false;
       let rust_ty = expr.return_type(self.cx);
@@ -82,7 +82,7 @@ impl<'a> SumType<'a>
   }
 
   fn sum_type_error(&mut self, parent: &Box<Expression>, exprs: &Vec<Box<Expression>>,
-    tys_indices: HashMap<String, Vec<usize>>)
+    tys_indices: OrderedMap<String, Vec<usize>>)
   {
     self.bad_type_detected = true;
     let mut db = self.cx.struct_span_err(parent.span, "sum combinator arms have incompatible types:");
@@ -98,9 +98,11 @@ impl<'a> SumType<'a>
 impl<'a> Visitor<Expression, ()> for SumType<'a>
 {
   unit_visitor_impl!(Expression, str_literal);
+  unit_visitor_impl!(Expression, indentation);
   unit_visitor_impl!(Expression, character);
   unit_visitor_impl!(Expression, sequence);
   unit_visitor_impl!(Expression, non_terminal);
+  unit_visitor_impl!(Expression, operator_table);
 
   fn visit_choice(&mut self, parent: &Box<Expression>, exprs: &Vec<Box<Expression>>) {
     if !parent.is_unit() {
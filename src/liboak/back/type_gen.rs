@@ -29,17 +29,20 @@ use middle::typing::ast::Expression as TExpression;
 use middle::typing::ast::EvaluationContext;
 use back::ast::*;
 use back::ast::Expression_::*;
+use back::first_set::FirstSet;
 
 pub fn generate_rust_types(cx: &ExtCtxt, tgrammar: TGrammar) -> Grammar {
   let mut grammar = Grammar {
     name: tgrammar.name,
-    rules: HashMap::with_capacity(tgrammar.rules.len()),
+    rules: OrderedMap::with_capacity(tgrammar.rules.len()),
     rust_functions: tgrammar.rust_functions,
     rust_items: tgrammar.rust_items,
+    ast_types: tgrammar.ast_types,
     attributes: tgrammar.attributes
   };
   let rule_types = RuleTyper::type_rules(cx, &tgrammar.rules);
-  grammar.rules = ExpressionTyper::type_exprs(cx, &tgrammar.rules, rule_types);
+  grammar.rules = ExpressionTyper::type_exprs(cx, &tgrammar.rules, rule_types,
+    grammar.attributes.is_optimised());
   grammar
 }
 
@@ -95,25 +98,30 @@ impl TypeGenerator
   }
 }
 
+/// Generates the Rust types of the expressions. The choices are dispatched on their next character (see `back::first_set`) if the grammar is optimised.
 struct ExpressionTyper<'a>
 {
   cx: &'a ExtCtxt<'a>,
-  rules_ty: HashMap<Ident, RTy>
+  rules_ty: HashMap<Ident, RTy>,
+  first_set: FirstSet<'a>,
+  dispatch_choices: bool
 }
 
 impl<'a> ExpressionTyper<'a>
 {
-  fn type_exprs(cx: &'a ExtCtxt<'a>, rules: &'a HashMap<Ident, TRule>,
-    rules_ty: HashMap<Ident, RTy>) -> HashMap<Ident, Rule>
+  fn type_exprs(cx: &'a ExtCtxt<'a>, rules: &'a OrderedMap<Ident, TRule>,
+    rules_ty: HashMap<Ident, RTy>, dispatch_choices: bool) -> OrderedMap<Ident, Rule>
   {
     let mut typer = ExpressionTyper {
       cx: cx,
-      rules_ty: rules_ty
+      rules_ty: rules_ty,
+      first_set: FirstSet::new(rules),
+      dispatch_choices: dispatch_choices
     };
     typer.visit_rules(rules)
   }
 
-  fn visit_rules(&mut self, rules: &HashMap<Ident, TRule>) -> HashMap<Ident, Rule> {
+  fn visit_rules(&mut self, rules: &OrderedMap<Ident, TRule>) -> OrderedMap<Ident, Rule> {
     rules.iter()
     .map(|(&id, rule)| (id, self.visit_rule(rule)))
     .collect()
@@ -122,7 +130,8 @@ impl<'a> ExpressionTyper<'a>
   fn visit_rule(&mut self, rule: &TRule) -> Rule {
     Rule {
       name: rule.name,
-      def: self.visit_expr(&rule.def)
+      def: self.visit_expr(&rule.def),
+      attributes: rule.attributes
     }
   }
 
@@ -147,7 +156,10 @@ impl<'a> ExpressionTyper<'a>
       span: parent.span,
       ty: parent.ty_clone(),
       node: node,
-      kind: self.function_kind(parent, ty)
+      kind: self.function_kind(parent, ty),
+      dispatch: None,
+      shared_id: parent.shared_id,
+      merged_literals: parent.merged_literals.clone()
     }
   }
 
@@ -170,6 +182,11 @@ impl<'a> Visitor<TExpression, Box<Expression>> for ExpressionTyper<'a>
     self.build_expr(parent, ty, StrLiteral(lit.clone()))
   }
 
+  fn visit_indentation(&mut self, parent: &Box<TExpression>, kind: IndentKind) -> Box<Expression> {
+    let ty = TypeGenerator::unit_ty(self.cx);
+    self.build_expr(parent, ty, Indentation(kind))
+  }
+
   fn visit_not_predicate(&mut self, parent: &Box<TExpression>, expr: &Box<TExpression>) -> Box<Expression> {
     self.compose_expr(parent, expr, NotPredicate, |cx,_| TypeGenerator::unit_ty(cx))
   }
@@ -209,16 +226,30 @@ impl<'a> Visitor<TExpression, Box<Expression>> for ExpressionTyper<'a>
     self.compose_expr(parent, expr, Optional, TypeGenerator::option_ty)
   }
 
+  fn visit_recovery(&mut self, parent: &Box<TExpression>, expr: &Box<TExpression>, sync: &String) -> Box<Expression> {
+    self.compose_expr(parent, expr, |expr| Recovery(expr, sync.clone()), TypeGenerator::option_ty)
+  }
+
   fn visit_sequence(&mut self, parent: &Box<TExpression>, exprs: &Vec<Box<TExpression>>) -> Box<Expression> {
     let exprs = walk_exprs(self, exprs);
     let ty = TypeGenerator::tuple_ty(self.cx, parent, |idx| exprs[idx].return_type(self.cx));
     self.build_expr(parent, ty, Sequence(exprs))
   }
 
-  fn visit_choice(&mut self, parent: &Box<TExpression>, exprs: &Vec<Box<TExpression>>) -> Box<Expression> {
-    let exprs = walk_exprs(self, exprs);
+  fn visit_choice(&mut self, parent: &Box<TExpression>, alternatives: &Vec<Box<TExpression>>) -> Box<Expression> {
+    let exprs = walk_exprs(self, alternatives);
     let ty = exprs[0].return_type(self.cx);
-    self.build_expr(parent, ty, Choice(exprs))
+    let mut choice = self.build_expr(parent, ty, Choice(exprs));
+    if self.dispatch_choices {
+      choice.dispatch = self.first_set.dispatch(alternatives);
+    }
+    choice
+  }
+
+  fn visit_operator_table(&mut self, parent: &Box<TExpression>, table: &OperatorTableExpr<TExpression>) -> Box<Expression> {
+    let table = table.map_exprs(|expr| self.visit_expr(expr));
+    let ty = table.operand.return_type(self.cx);
+    self.build_expr(parent, ty, OperatorTable(table))
   }
 
   fn visit_semantic_action(&mut self, parent: &Box<TExpression>, expr: &Box<TExpression>, id: Ident) -> Box<Expression> {
@@ -231,14 +262,14 @@ impl<'a> Visitor<TExpression, Box<Expression>> for ExpressionTyper<'a>
 struct RuleTyper<'a>
 {
   cx: &'a ExtCtxt<'a>,
-  rules: &'a HashMap<Ident, TRule>,
+  rules: &'a OrderedMap<Ident, TRule>,
   visited: HashMap<Ident, bool>,
   rules_ty: HashMap<Ident, RTy>
 }
 
 impl<'a> RuleTyper<'a>
 {
-  fn type_rules(cx: &'a ExtCtxt<'a>, rules: &'a HashMap<Ident, TRule>) -> HashMap<Ident, RTy> {
+  fn type_rules(cx: &'a ExtCtxt<'a>, rules: &'a OrderedMap<Ident, TRule>) -> HashMap<Ident, RTy> {
     let mut visited = HashMap::with_capacity(rules.len());
     for id in rules.keys() {
       visited.insert(*id, false);
@@ -253,7 +284,7 @@ impl<'a> RuleTyper<'a>
     typer.rules_ty
   }
 
-  fn visit_rules(&mut self, rules: &HashMap<Ident, TRule>) {
+  fn visit_rules(&mut self, rules: &OrderedMap<Ident, TRule>) {
     for rule in rules.values() {
       self.visit_rule(rule);
     }
@@ -286,6 +317,10 @@ impl<'a> Visitor<TExpression, RTy> for RuleTyper<'a>
     panic!("BUG: String literal expression should have type `Unit` and handled in `visit_expr`.");
   }
 
+  fn visit_indentation(&mut self, _parent: &Box<TExpression>, _kind: IndentKind) -> RTy {
+    panic!("BUG: Indentation expression should have type `Unit` and handled in `visit_expr`.");
+  }
+
   fn visit_syntactic_predicate(&mut self, _parent: &Box<TExpression>, _expr: &Box<TExpression>) -> RTy {
     panic!("BUG: Syntactic predicate (&e, !e) expressions should have type `Unit` and handled in `visit_expr`.");
   }
@@ -310,6 +345,10 @@ impl<'a> Visitor<TExpression, RTy> for RuleTyper<'a>
     TypeGenerator::option_ty(self.cx, walk_expr(self, expr))
   }
 
+  fn visit_recovery(&mut self, _parent: &Box<TExpression>, expr: &Box<TExpression>, _sync: &String) -> RTy {
+    TypeGenerator::option_ty(self.cx, walk_expr(self, expr))
+  }
+
   fn visit_sequence(&mut self, parent: &Box<TExpression>, exprs: &Vec<Box<TExpression>>) -> RTy {
     TypeGenerator::tuple_ty(self.cx, parent, |idx| self.visit_expr(&exprs[idx]))
   }
@@ -318,6 +357,10 @@ impl<'a> Visitor<TExpression, RTy> for RuleTyper<'a>
     self.visit_expr(&exprs[0])
   }
 
+  fn visit_operator_table(&mut self, _parent: &Box<TExpression>, table: &OperatorTableExpr<TExpression>) -> RTy {
+    self.visit_expr(&table.operand)
+  }
+
   fn visit_semantic_action(&mut self, parent: &Box<TExpression>, _expr: &Box<TExpression>, _id: Ident) -> RTy {
     TypeGenerator::action_ty(self.cx, parent.ty_clone())
   }
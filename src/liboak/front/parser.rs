@@ -82,7 +82,13 @@ impl<'a> Parser<'a>
 
   fn parse_rule_decl(&mut self) -> rust::PResult<'a, rust::SpannedIdent> {
     let sp = self.rp.span;
-    Ok(respan(sp, try!(self.rp.parse_ident())))
+    let name = try!(self.rp.parse_ident());
+    if IndentKind::from_name(&*name.name.as_str()).is_some() {
+      self.rp.span_err(sp,
+        format!("`{}` is a reserved name for the indentation terminals, it cannot be the name of a rule.",
+          name).as_str());
+    }
+    Ok(respan(sp, name))
   }
 
   fn parse_rule_rhs(&mut self, rule_name: &str) -> rust::PResult<'a, Box<Expression>> {
@@ -222,13 +228,51 @@ impl<'a> Parser<'a>
       },
       rtok::Question => {
         self.bump();
-        spanned_expr(lo, hi, Optional(expr))
+        if self.is_recovery_operator() {
+          self.bump();
+          let sync = try!(self.parse_sync_literal(rule_name));
+          let hi = self.rp.last_span.hi;
+          spanned_expr(lo, hi, Recovery(expr, sync))
+        }
+        else {
+          spanned_expr(lo, hi, Optional(expr))
+        }
       },
       _ => expr
     };
     Ok(Some(res))
   }
 
+  // `?!` is written without space, otherwise `e? !f` is an optional expression followed by a not predicate.
+  fn is_recovery_operator(&self) -> bool {
+    self.rp.token == rtok::Not && self.rp.last_span.hi == self.rp.span.lo
+  }
+
+  // `e ?! ";"`
+  fn parse_sync_literal(&mut self, rule_name: &str) -> rust::PResult<'a, String> {
+    let token = self.rp.token.clone();
+    match token {
+      rtok::Literal(rust::token::Lit::Str_(name),_) => {
+        self.bump();
+        let cooked_lit = cook_lit(name);
+        if cooked_lit.is_empty() {
+          let span = self.rp.last_span;
+          self.rp.span_err(span,
+            format!("In rule {}: The synchronisation literal of `?!` must not be empty.",
+              rule_name).as_str());
+        }
+        Ok(cooked_lit)
+      },
+      _ => {
+        Err(self.fatal_error(
+          format!("In rule {}: `?!` must be followed by a string literal, the synchronisation point \
+            (such as in `statement ?! \";\"`).",
+            rule_name).as_str()
+        ))
+      }
+    }
+  }
+
   fn last_respan(&self, expr: ExpressionNode) -> Box<Expression> {
     respan_expr(self.rp.last_span, expr)
   }
@@ -260,9 +304,15 @@ impl<'a> Parser<'a>
         if self.is_rule_lhs() { None }
         else {
           self.bump();
-          Some(self.last_respan(NonTerminalSymbol(id)))
+          match IndentKind::from_name(&*id.name.as_str()) {
+            Some(kind) => Some(self.last_respan(Indentation(kind))),
+            None => Some(self.last_respan(NonTerminalSymbol(id)))
+          }
         }
       },
+      rtok::BinOp(rbtok::Percent) => {
+        Some(try!(self.parse_operator_table(rule_name)))
+      },
       rtok::OpenDelim(rust::DelimToken::Bracket) => {
         self.bump();
         let res = try!(self.parse_char_class(rule_name));
@@ -285,6 +335,69 @@ impl<'a> Parser<'a>
     Ok(res)
   }
 
+  // `%precedence(operand) { left: op1 > f1, op2 > f2; prefix: op3 > f3; }`
+  fn parse_operator_table(&mut self, rule_name: &str) -> rust::PResult<'a, Box<Expression>> {
+    let lo = self.rp.span.lo;
+    self.bump();
+    let keyword = try!(self.rp.parse_ident());
+    if &*keyword.name.as_str() != "precedence" {
+      return Err(self.fatal_error(
+        format!("In rule {}: Unknown operator `%{}`. Did you mean `%precedence`?",
+          rule_name, keyword).as_str()
+      ));
+    }
+    try!(self.rp.expect(&rtok::OpenDelim(rust::DelimToken::Paren)));
+    let operand = try!(self.parse_rule_rhs(rule_name));
+    try!(self.rp.expect(&rtok::CloseDelim(rust::DelimToken::Paren)));
+    try!(self.rp.expect(&rtok::OpenDelim(rust::DelimToken::Brace)));
+    let mut levels = vec![];
+    while self.rp.token != rtok::CloseDelim(rust::DelimToken::Brace) {
+      levels.push(try!(self.parse_precedence_level(rule_name)));
+    }
+    self.bump();
+    let hi = self.rp.last_span.hi;
+    if levels.is_empty() {
+      self.rp.span_err(
+        mk_sp(lo, hi),
+        format!("In rule {}: An operator table must define at least one precedence level.",
+          rule_name).as_str());
+    }
+    Ok(spanned_expr(lo, hi, OperatorTable(OperatorTableExpr{operand: operand, levels: levels})))
+  }
+
+  // `left: op1 > f1, op2 > f2;`
+  fn parse_precedence_level(&mut self, rule_name: &str) -> rust::PResult<'a, PrecedenceLevel<Expression>> {
+    let associativity = try!(self.rp.parse_ident());
+    let associativity = match &*associativity.name.as_str() {
+      "left" => Associativity::Left,
+      "right" => Associativity::Right,
+      "prefix" => Associativity::Prefix,
+      "postfix" => Associativity::Postfix,
+      _ => {
+        let span = self.rp.last_span;
+        return Err(self.rp.span_fatal(span,
+          format!("In rule {}: Unknown associativity `{}`, it must be `left`, `right`, `prefix` or `postfix`.",
+            rule_name, associativity).as_str()
+        ));
+      }
+    };
+    try!(self.rp.expect(&rtok::Colon));
+    let mut operators = vec![];
+    loop {
+      let expr = try!(self.parse_rule_seq(rule_name));
+      try!(self.rp.expect(&rtok::Gt));
+      let action = try!(self.rp.parse_ident());
+      operators.push(Operator{expr: expr, action: action});
+      if self.rp.token == rtok::Comma {
+        self.bump();
+      } else {
+        break;
+      }
+    }
+    try!(self.rp.expect(&rtok::Semi));
+    Ok(PrecedenceLevel{associativity: associativity, operators: operators})
+  }
+
   fn parse_char_class(&mut self, rule_name: &str) -> rust::PResult<'a, Box<Expression>> {
     let token = self.rp.token.clone();
     match token {
@@ -31,3 +31,14 @@ pub fn ident_to_lowercase(ident: Ident) -> String {
   let ident = id_to_string(ident);
   string_to_lowercase(&ident)
 }
+
+/// `bin_op` and `binOp` both give `BinOp`.
+pub fn string_to_camel_case(s: &String) -> String {
+  s.split('_')
+    .flat_map(|part| {
+      let mut chars = part.chars();
+      let first = chars.next().into_iter().flat_map(char::to_uppercase);
+      first.chain(chars).collect::<Vec<_>>().into_iter()
+    })
+    .collect()
+}
@@ -32,6 +32,7 @@ mod back;
 mod rust;
 mod identifier;
 mod monad;
+mod ordered_map;
 
 type FGrammar = front::ast::Grammar;
 
@@ -18,15 +18,15 @@ pub use rust::{ExtCtxt,Attribute,SpannedIdent};
 pub use monad::partial::Partial;
 
 use front::ast::Grammar as FGrammar;
-use std::collections::HashMap;
 use std::default::Default;
 
 pub struct Grammar
 {
   pub name: Ident,
-  pub rules: HashMap<Ident, Rule>,
-  pub rust_functions: HashMap<Ident, RItem>,
+  pub rules: OrderedMap<Ident, Rule>,
+  pub rust_functions: OrderedMap<Ident, RItem>,
   pub rust_items: Vec<RItem>,
+  pub ast_types: Vec<AstType>,
   pub attributes: GrammarAttributes
 }
 
@@ -36,9 +36,10 @@ impl Grammar
     let rules_len = fgrammar.rules.len();
     let grammar = Grammar {
       name: fgrammar.name.clone(),
-      rules: HashMap::with_capacity(rules_len),
-      rust_functions: HashMap::new(),
+      rules: OrderedMap::with_capacity(rules_len),
+      rust_functions: OrderedMap::new(),
       rust_items: vec![],
+      ast_types: vec![],
       attributes: GrammarAttributes::default()
     };
     Partial::Value(grammar)
@@ -48,18 +49,64 @@ impl Grammar
 #[derive(Default)]
 pub struct GrammarAttributes
 {
-  pub print_attr: PrintAttribute
+  pub print_attr: PrintAttribute,
+  /// `#![memo]`: Every rule of the grammar is memoised.
+  pub memo: bool,
+  /// `#![context(Ctx)]`: Every generated function takes an extra argument `context: &mut Ctx`.
+  pub context: Option<Ident>,
+  /// `#![cst]`: Functions `cst_<rule>` building the concrete syntax tree of the input are generated.
+  pub cst: bool,
+  /// `#![trace]`: Every generated function takes an extra argument `tracer: &mut oak_runtime::Tracer` notified of the rules tried.
+  pub trace: bool,
+  /// `#![profile]`: Every generated function takes an extra argument `profiler: &mut oak_runtime::Profiler` counting its invocations.
+  pub profile: bool,
+  /// `#![error_context]`: The errors record the rules being parsed when they occurred.
+  pub error_context: bool,
+  /// `#![no_optimisation]`: The grammar is compiled as written, which is easier to debug.
+  pub no_optimisation: bool
 }
 
 impl GrammarAttributes
 {
-  pub fn new(print_attr: PrintAttribute) -> GrammarAttributes {
-    GrammarAttributes {
-      print_attr: print_attr
-    }
+  /// The optimisations are not performed when the generated functions are observed with `#![cst]`, `#![trace]` or `#![profile]`, nor with `#![error_context]` since the inlined rules would be missing from the errors.
+  pub fn is_optimised(&self) -> bool {
+    !self.no_optimisation && !self.cst && !self.trace && !self.profile && !self.error_context
   }
 }
 
+#[derive(Clone, Copy, Default)]
+pub struct RuleAttributes
+{
+  /// `#[memo]`: The states produced by the rule are cached for each position of the stream.
+  pub memo: bool,
+  /// `#[ast]`: The type of the rule is generated from its definition.
+  pub ast: bool,
+  /// `#[start]`: The rule is an entry point of the grammar, the rules that cannot be reached from the start rules are reported as unused.
+  pub start: bool,
+  /// `#[allow(unused_rule)]`: The rule is not reported if it is unused.
+  pub allow_unused: bool,
+  /// Not an attribute: the definition of the rule is annotated with `-> ()` or `-> (^)`, such rules are considered as trivia (spaces, comments) in the concrete syntax tree.
+  pub trivia: bool
+}
+
+/// Type generated for a rule marked with `#[ast]`: a struct or an enum with one variant per alternative of the rule. The types of the fields are only known once the expressions are typed.
+#[derive(Clone)]
+pub struct AstType
+{
+  pub name: Ident,
+  pub is_enum: bool,
+  pub constructors: Vec<AstConstructor>
+}
+
+#[derive(Clone)]
+pub struct AstConstructor
+{
+  /// Only for enum.
+  pub variant: Option<Ident>,
+  /// Semantic action building the value of the type from the values of the expression.
+  pub action: Ident
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum PrintAttribute
 {
@@ -100,6 +147,7 @@ pub struct Rule
 {
   pub name: SpannedIdent,
   pub def: Box<Expression>,
+  pub attributes: RuleAttributes
 }
 
 impl Rule
@@ -107,7 +155,8 @@ impl Rule
   pub fn new(name: SpannedIdent, def: Box<Expression>) -> Rule {
     Rule{
       name: name,
-      def: def
+      def: def,
+      attributes: RuleAttributes::default()
     }
   }
 }
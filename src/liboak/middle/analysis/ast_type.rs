@@ -0,0 +1,147 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rules marked with `#[ast]` have a generated type. The rule `r = e` gives the struct `R` and the rule `r = e1 / e2` gives the enum `R` with one variant per alternative.
+//!
+//! The definition of the rule (or each alternative for an enum) is wrapped into a semantic action building the value of the generated type. These actions are only declared with their return types here, which is all the type inference needs, the types of their parameters are given by the code generator once the expressions are typed.
+
+use middle::analysis::ast::*;
+use front::ast::respan_expr;
+
+pub fn generate_ast_types(cx: &ExtCtxt, mut grammar: Grammar) -> Partial<Grammar> {
+  let ast_rules: Vec<Ident> = grammar.rules.values()
+    .filter(|rule| rule.attributes.ast)
+    .map(|rule| rule.name.node)
+    .collect();
+  let mut has_error = false;
+  for rule_id in ast_rules {
+    let ast_type = wrap_rule_definition(cx, &mut grammar, rule_id);
+    for constructor in &ast_type.constructors {
+      has_error |= declare_constructor(cx, &mut grammar, rule_id, &ast_type, constructor);
+    }
+    grammar.ast_types.push(ast_type);
+  }
+  if has_error {
+    Partial::Nothing
+  } else {
+    Partial::Value(grammar)
+  }
+}
+
+fn wrap_rule_definition(cx: &ExtCtxt, grammar: &mut Grammar, rule_id: Ident) -> AstType {
+  let rule_name = ident_to_lowercase(rule_id);
+  let type_name = string_to_camel_case(&id_to_string(rule_id));
+  let def = grammar.rules[&rule_id].def.clone();
+  match def.node.clone() {
+    Choice(alternatives) => {
+      let mut constructors: Vec<AstConstructor> = vec![];
+      let mut wrapped = vec![];
+      for (idx, alternative) in alternatives.into_iter().enumerate() {
+        let variant = variant_name(grammar, &alternative, &type_name, idx, &constructors);
+        let action = cx.ident_of(format!("ast_{}_{}", rule_name, idx).as_str());
+        wrapped.push(respan_expr(alternative.span, SemanticAction(alternative, action)));
+        constructors.push(AstConstructor {
+          variant: Some(cx.ident_of(variant.as_str())),
+          action: action
+        });
+      }
+      grammar.rules.get_mut(&rule_id).unwrap().def = respan_expr(def.span, Choice(wrapped));
+      AstType {
+        name: cx.ident_of(type_name.as_str()),
+        is_enum: true,
+        constructors: constructors
+      }
+    }
+    _ => {
+      let action = cx.ident_of(format!("ast_{}", rule_name).as_str());
+      grammar.rules.get_mut(&rule_id).unwrap().def = respan_expr(def.span, SemanticAction(def, action));
+      AstType {
+        name: cx.ident_of(type_name.as_str()),
+        is_enum: false,
+        constructors: vec![AstConstructor{variant: None, action: action}]
+      }
+    }
+  }
+}
+
+/// The variant is named after the first rule or keyword of the alternative. Rules only recognizing a literal (such as `lparen = "(" spacing`) are skipped unless the literal is a keyword (such as `let_kw = "let" spacing` naming the variant `Let`). If no name is found or if the name is already taken, the position of the alternative is appended.
+fn variant_name(grammar: &Grammar, alternative: &Box<Expression>, type_name: &String, idx: usize,
+  constructors: &Vec<AstConstructor>) -> String
+{
+  let taken = |name: &String| constructors.iter()
+    .any(|c| c.variant.map_or(false, |v| id_to_string(v) == *name));
+  match variant_base_name(grammar, alternative) {
+    Some(name) => {
+      let name = string_to_camel_case(&name);
+      if taken(&name) { format!("{}{}", name, idx + 1) }
+      else { name }
+    }
+    None => format!("{}{}", type_name, idx + 1)
+  }
+}
+
+fn variant_base_name(grammar: &Grammar, expr: &Box<Expression>) -> Option<String> {
+  match &expr.node {
+    &NonTerminalSymbol(id) => {
+      match grammar.rules.get(&id) {
+        Some(rule) if is_syntactic(&rule.def) => leading_literal(&rule.def).and_then(word),
+        _ => Some(id_to_string(id))
+      }
+    }
+    &StrLiteral(ref lit) => word(lit),
+    &Sequence(ref seq) => seq.iter().filter_map(|e| variant_base_name(grammar, e)).next(),
+    &SemanticAction(ref expr, _) => variant_base_name(grammar, expr),
+    _ => None
+  }
+}
+
+fn is_syntactic(def: &Box<Expression>) -> bool {
+  def.ty.is_some() || leading_literal(def).is_some()
+}
+
+fn leading_literal(expr: &Box<Expression>) -> Option<&String> {
+  match &expr.node {
+    &StrLiteral(ref lit) => Some(lit),
+    &Sequence(ref seq) => leading_literal(&seq[0]),
+    &SemanticAction(ref expr, _) => leading_literal(expr),
+    _ => None
+  }
+}
+
+fn word(lit: &String) -> Option<String> {
+  let is_word = lit.chars().next().map_or(false, char::is_alphabetic)
+    && lit.chars().all(|c| c.is_alphanumeric() || c == '_');
+  if is_word { Some(lit.clone()) } else { None }
+}
+
+/// Returns `true` if the action conflicts with a function of the grammar.
+fn declare_constructor(cx: &ExtCtxt, grammar: &mut Grammar, rule_id: Ident, ast_type: &AstType,
+  constructor: &AstConstructor) -> bool
+{
+  let action = constructor.action;
+  if let Some(function) = grammar.rust_functions.get(&action) {
+    let mut db = cx.struct_span_err(function.span,
+      format!("The function `{}` conflicts with the constructor generated for the rule `{}`.",
+        action, rule_id).as_str());
+    db.span_note(grammar.rules[&rule_id].name.span, "Generated because of the attribute `#[ast]` of this rule.");
+    db.emit();
+    return true;
+  }
+  let type_name = ast_type.name;
+  let declaration = quote_item!(cx,
+    fn $action() -> $type_name { unreachable!() }
+  ).expect("Quote the declaration of an AST constructor.");
+  grammar.rust_functions.insert(action, declaration);
+  false
+}
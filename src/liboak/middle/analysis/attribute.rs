@@ -16,24 +16,59 @@ use middle::analysis::ast::*;
 use front::ast::Grammar as FGrammar;
 use front::ast::Rule as FRule;
 
+use rust;
 use rust::{P, MetaItemKind, MetaItem};
 
 pub fn decorate_with_attributes(cx: &ExtCtxt, fgrammar: &FGrammar,
   mut grammar: Grammar) -> Partial<Grammar>
 {
-  check_rules_attributes(cx, &fgrammar.rules);
-  let print_attr = check_grammar_attributes(cx, &fgrammar.attributes);
-  grammar.attributes = GrammarAttributes::new(print_attr);
+  grammar.attributes = check_grammar_attributes(cx, &fgrammar.attributes);
+  check_rules_attributes(cx, &fgrammar.rules, &mut grammar);
   Partial::Value(grammar)
 }
 
-fn check_grammar_attributes(cx: &ExtCtxt, attrs: &Vec<Attribute>) -> PrintAttribute {
-  let mut print_attr = PrintAttribute::Nothing;
+fn check_grammar_attributes(cx: &ExtCtxt, attrs: &Vec<Attribute>) -> GrammarAttributes {
+  let mut attributes = GrammarAttributes::default();
   for attr in attrs {
     let meta_item = attr.node.value.clone();
-    print_attr = print_attr.merge(check_grammar_attr(cx, meta_item));
+    match &meta_item.node {
+      &MetaItemKind::Word(ref name) if *name == "memo" => {
+        attributes.memo = true;
+      },
+      &MetaItemKind::Word(ref name) if *name == "cst" => {
+        attributes.cst = true;
+      },
+      &MetaItemKind::Word(ref name) if *name == "trace" => {
+        attributes.trace = true;
+      },
+      &MetaItemKind::Word(ref name) if *name == "profile" => {
+        attributes.profile = true;
+      },
+      &MetaItemKind::Word(ref name) if *name == "error_context" => {
+        attributes.error_context = true;
+      },
+      &MetaItemKind::Word(ref name) if *name == "no_optimisation" => {
+        attributes.no_optimisation = true;
+      },
+      &MetaItemKind::List(ref name, ref items) if *name == "context" => {
+        attributes.context = check_context_attr(cx, &meta_item, items);
+      },
+      _ => {
+        attributes.print_attr = attributes.print_attr.merge(check_grammar_attr(cx, meta_item));
+      }
+    }
   }
-  print_attr
+  attributes
+}
+
+fn check_context_attr(cx: &ExtCtxt, meta_item: &P<MetaItem>, items: &Vec<P<MetaItem>>) -> Option<Ident> {
+  if items.len() == 1 {
+    if let MetaItemKind::Word(ref ty_name) = items[0].node {
+      return Some(rust::str_to_ident(&*ty_name));
+    }
+  }
+  cx.span_err(meta_item.span, "The context attribute expects a single type name as in `#![context(Ctx)]`.");
+  None
 }
 
 fn check_grammar_attr(cx: &ExtCtxt, meta_item: P<MetaItem>) -> PrintAttribute {
@@ -54,17 +89,52 @@ fn check_grammar_attr(cx: &ExtCtxt, meta_item: P<MetaItem>) -> PrintAttribute {
   }
 }
 
-fn check_rules_attributes(cx: &ExtCtxt, rules: &Vec<FRule>) {
+fn check_rules_attributes(cx: &ExtCtxt, rules: &Vec<FRule>, grammar: &mut Grammar) {
   for rule in rules {
+    let mut rule_attrs = RuleAttributes::default();
+    rule_attrs.memo = grammar.attributes.memo;
+    rule_attrs.trivia = rule.def.ty.is_some();
     for attr in &rule.attributes {
       let meta_item = attr.node.value.clone();
-      check_rule_attr(cx, rule.name.node, meta_item);
+      check_rule_attr(cx, rule.name.node, meta_item, &mut rule_attrs);
+    }
+    if rule_attrs.memo && grammar.attributes.cst {
+      cx.span_err(rule.name.span, format!(
+        "The rule `{}` cannot be memoised in a grammar building a concrete syntax tree (`#![cst]`).",
+        rule.name.node).as_str());
+    }
+    if let Some(r) = grammar.rules.get_mut(&rule.name.node) {
+      r.attributes = rule_attrs;
     }
   }
 }
 
-fn check_rule_attr(cx: &ExtCtxt, rule_name: Ident, meta_item: P<MetaItem>) {
+fn check_rule_attr(cx: &ExtCtxt, rule_name: Ident, meta_item: P<MetaItem>,
+  rule_attrs: &mut RuleAttributes)
+{
   match &meta_item.node {
+    &MetaItemKind::Word(ref name) if *name == "memo" => {
+      rule_attrs.memo = true;
+    },
+    &MetaItemKind::Word(ref name) if *name == "ast" => {
+      rule_attrs.ast = true;
+    },
+    &MetaItemKind::Word(ref name) if *name == "start" => {
+      rule_attrs.start = true;
+    },
+    &MetaItemKind::List(ref name, ref items) if *name == "allow" => {
+      for item in items {
+        match &item.node {
+          &MetaItemKind::Word(ref lint) if *lint == "unused_rule" => {
+            rule_attrs.allow_unused = true;
+          },
+          _ => {
+            cx.span_warn(item.span, format!(
+              "Unknown lint in the `allow` attribute of the rule `{}`: it will be ignored.", rule_name).as_str());
+          }
+        }
+      }
+    },
       &MetaItemKind::Word(ref name)
     | &MetaItemKind::List(ref name, _)
     | &MetaItemKind::NameValue(ref name, _) => {
@@ -12,7 +12,6 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-pub use std::collections::HashMap;
 use front::ast::Rule as FRule;
 
 use middle::analysis::ast::*;
@@ -90,7 +89,7 @@ impl ItemSpan for Rule {
 struct DuplicateItem<'a, Item>
 {
   cx: &'a ExtCtxt<'a>,
-  items: HashMap<Ident, Item>,
+  items: OrderedMap<Ident, Item>,
   has_duplicate: bool,
   what_is_duplicated: String
 }
@@ -99,13 +98,13 @@ impl<'a, Item> DuplicateItem<'a, Item> where
  Item: ItemIdent + ItemSpan
 {
   pub fn analyse<ItemIter>(cx: &'a ExtCtxt<'a>, iter: ItemIter, item_kind: String)
-    -> Partial<HashMap<Ident, Item>> where
+    -> Partial<OrderedMap<Ident, Item>> where
    ItemIter: Iterator<Item=Item>
   {
     let (min_size, _) = iter.size_hint();
     DuplicateItem {
       cx: cx,
-      items: HashMap::with_capacity(min_size),
+      items: OrderedMap::with_capacity(min_size),
       has_duplicate: false,
       what_is_duplicated: item_kind
     }.populate(iter)
@@ -136,7 +135,7 @@ impl<'a, Item> DuplicateItem<'a, Item> where
       self.what_is_duplicated, pre.ident()).as_str()).emit();
   }
 
-  fn make(self) -> Partial<HashMap<Ident, Item>> {
+  fn make(self) -> Partial<OrderedMap<Ident, Item>> {
     if self.has_duplicate {
       Fake(self.items)
     } else {
@@ -0,0 +1,237 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A rule is left-recursive if it can call itself, directly or not, without consuming input. The generated function would then call itself forever and overflow the stack.
+
+use middle::analysis::ast::*;
+use middle::analysis::nullable::*;
+use monad::partial::Partial::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::cmp::min;
+
+pub struct LeftRecursion<'a>
+{
+  cx: &'a ExtCtxt<'a>,
+  rules: &'a OrderedMap<Ident, Rule>,
+  /// Rules called by each rule before consuming input, with the span of the call.
+  left_calls: HashMap<Ident, Vec<(Ident, Span)>>
+}
+
+impl<'a> LeftRecursion<'a>
+{
+  pub fn analyse(cx: &'a ExtCtxt<'a>, grammar: Grammar) -> Partial<Grammar> {
+    if LeftRecursion::has_left_recursion(cx, &grammar.rules) {
+      Nothing
+    } else {
+      Value(grammar)
+    }
+  }
+
+  /// The left-recursive rules are the rules of the strongly connected components of the left calls graph that contain a cycle. One error is reported for each component so a rule participating in several cycles is not missed.
+  fn has_left_recursion(cx: &'a ExtCtxt<'a>, rules: &'a OrderedMap<Ident, Rule>) -> bool {
+    let nullable = Nullable::new(rules);
+    let left_calls = rules.iter()
+      .map(|(&id, rule)| {
+        let mut calls = LeftCalls { nullable: &nullable, calls: vec![] };
+        calls.visit_expr(&rule.def);
+        (id, calls.calls)
+      })
+      .collect();
+    let analyser = LeftRecursion {
+      cx: cx,
+      rules: rules,
+      left_calls: left_calls
+    };
+    let components = StronglyConnectedComponents::compute(rules, &analyser.left_calls);
+    let mut has_left_recursion = false;
+    // A component is reported on its first rule in the order of declaration so errors are reported in a stable order.
+    let mut reported = HashSet::new();
+    for &id in rules.keys() {
+      let component = components.component_of(id);
+      if !reported.contains(&component) && analyser.is_cyclic(&components.components[component]) {
+        reported.insert(component);
+        has_left_recursion = true;
+        analyser.left_recursion_detected(id, &components.components[component]);
+      }
+    }
+    has_left_recursion
+  }
+
+  fn is_cyclic(&self, component: &Vec<Ident>) -> bool {
+    component.len() > 1 ||
+    self.left_calls.get(&component[0]).unwrap().iter().any(|&(callee, _)| callee == component[0])
+  }
+
+  /// The shortest cycle of calls from `rule` to itself inside `component`, each call is given with its caller, its callee and its span.
+  fn shortest_cycle(&self, rule: Ident, component: &Vec<Ident>) -> Vec<(Ident, Ident, Span)> {
+    let mut previous: HashMap<Ident, (Ident, Span)> = HashMap::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(rule);
+    while let Some(caller) = queue.pop_front() {
+      for &(callee, span) in self.left_calls.get(&caller).unwrap() {
+        if callee == rule {
+          let mut cycle = vec![(caller, callee, span)];
+          let mut current = caller;
+          while current != rule {
+            let (prev, prev_span) = *previous.get(&current).unwrap();
+            cycle.push((prev, current, prev_span));
+            current = prev;
+          }
+          cycle.reverse();
+          return cycle;
+        }
+        if component.contains(&callee) && !previous.contains_key(&callee) {
+          previous.insert(callee, (caller, span));
+          queue.push_back(callee);
+        }
+      }
+    }
+    unreachable!("A rule of a cyclic component always belongs to a cycle.");
+  }
+
+  fn left_recursion_detected(&self, recursive_rule: Ident, component: &Vec<Ident>) {
+    let cycle = self.shortest_cycle(recursive_rule, component);
+    let mut db = self.cx.struct_span_err(self.rules.get(&recursive_rule).unwrap().name.span,
+      format!("Left recursion detected. The rule `{}` can call itself without consuming input, \
+        which never terminates.", recursive_rule).as_str());
+    for &(caller, callee, span) in &cycle {
+      db.span_note(span,
+        format!("`{}` calls `{}` before consuming input.", caller, callee).as_str());
+    }
+    for &id in self.rules.keys() {
+      if component.contains(&id) && !cycle.iter().any(|&(caller, _, _)| caller == id) {
+        db.span_note(self.rules.get(&id).unwrap().name.span,
+          format!("The rule `{}` is also left-recursive through `{}`.", id, recursive_rule).as_str());
+      }
+    }
+    db.note("Left recursion is not supported by parsing expression grammars. \
+      A rule such as `expr = expr \"+\" term / term` can be written `expr = term (\"+\" term)*` \
+      or with an operator table `%precedence(term)`.");
+    db.emit();
+  }
+}
+
+/// Strongly connected components of the left calls graph computed with the algorithm of Tarjan.
+struct StronglyConnectedComponents<'b>
+{
+  left_calls: &'b HashMap<Ident, Vec<(Ident, Span)>>,
+  index: HashMap<Ident, usize>,
+  low_link: HashMap<Ident, usize>,
+  stack: Vec<Ident>,
+  on_stack: HashSet<Ident>,
+  /// Index of the component of each rule in `components`.
+  rule_component: HashMap<Ident, usize>,
+  components: Vec<Vec<Ident>>
+}
+
+impl<'b> StronglyConnectedComponents<'b>
+{
+  fn compute(rules: &OrderedMap<Ident, Rule>, left_calls: &'b HashMap<Ident, Vec<(Ident, Span)>>)
+    -> StronglyConnectedComponents<'b>
+  {
+    let mut sccs = StronglyConnectedComponents {
+      left_calls: left_calls,
+      index: HashMap::new(),
+      low_link: HashMap::new(),
+      stack: vec![],
+      on_stack: HashSet::new(),
+      rule_component: HashMap::new(),
+      components: vec![]
+    };
+    for &id in rules.keys() {
+      if !sccs.index.contains_key(&id) {
+        sccs.connect(id);
+      }
+    }
+    sccs
+  }
+
+  fn component_of(&self, id: Ident) -> usize {
+    *self.rule_component.get(&id).unwrap()
+  }
+
+  fn connect(&mut self, id: Ident) {
+    let index = self.index.len();
+    self.index.insert(id, index);
+    self.low_link.insert(id, index);
+    self.stack.push(id);
+    self.on_stack.insert(id);
+    let left_calls = self.left_calls;
+    for &(callee, _) in left_calls.get(&id).unwrap() {
+      if !self.index.contains_key(&callee) {
+        self.connect(callee);
+        let low_link = min(self.low_link[&id], self.low_link[&callee]);
+        self.low_link.insert(id, low_link);
+      }
+      else if self.on_stack.contains(&callee) {
+        let low_link = min(self.low_link[&id], self.index[&callee]);
+        self.low_link.insert(id, low_link);
+      }
+    }
+    if self.low_link[&id] == self.index[&id] {
+      let component_idx = self.components.len();
+      let mut component = vec![];
+      loop {
+        let rule = self.stack.pop().unwrap();
+        self.on_stack.remove(&rule);
+        self.rule_component.insert(rule, component_idx);
+        component.push(rule);
+        if rule == id {
+          break;
+        }
+      }
+      self.components.push(component);
+    }
+  }
+}
+
+/// Collects the rules called before consuming input.
+struct LeftCalls<'a>
+{
+  nullable: &'a Nullable,
+  calls: Vec<(Ident, Span)>
+}
+
+impl<'a> Visitor<Expression, ()> for LeftCalls<'a>
+{
+  unit_visitor_impl!(Expression, str_literal);
+  unit_visitor_impl!(Expression, indentation);
+  unit_visitor_impl!(Expression, character);
+  unit_visitor_impl!(Expression, choice);
+
+  fn visit_non_terminal_symbol(&mut self, parent: &Box<Expression>, id: Ident) {
+    self.calls.push((id, parent.span));
+  }
+
+  /// Sub-expressions are called before consuming input until one of them is not nullable.
+  fn visit_sequence(&mut self, _parent: &Box<Expression>, exprs: &Vec<Box<Expression>>) {
+    for expr in exprs {
+      self.visit_expr(expr);
+      if !self.nullable.is_nullable(expr) {
+        break;
+      }
+    }
+  }
+
+  /// Prefix operators are parsed before the operand, and the other operators are parsed before consuming input if the operand is nullable.
+  fn visit_operator_table(&mut self, _parent: &Box<Expression>, table: &OperatorTableExpr<Expression>) {
+    self.visit_expr(&table.operand);
+    let nullable_operand = self.nullable.is_nullable(&table.operand);
+    for (_, associativity, op) in table.operators() {
+      if associativity == Associativity::Prefix || nullable_operand {
+        self.visit_expr(&op.expr);
+      }
+    }
+  }
+}
@@ -16,13 +16,24 @@ use middle::analysis::ast::*;
 use middle::analysis::duplicate::*;
 use middle::analysis::undeclared_rule::*;
 use middle::analysis::undeclared_action::*;
+use middle::analysis::left_recursion::*;
+use middle::analysis::nullable_repetition::*;
+use middle::analysis::unreachable_alternative::*;
 use middle::analysis::attribute::*;
+use middle::analysis::unused::*;
+use middle::analysis::ast_type::*;
 use front::ast::Grammar as FGrammar;
 
 mod duplicate;
 mod undeclared_rule;
 mod undeclared_action;
+mod nullable;
+mod left_recursion;
+mod nullable_repetition;
+mod unreachable_alternative;
 mod attribute;
+mod unused;
+mod ast_type;
 pub mod ast;
 
 pub fn analyse(cx: &ExtCtxt, fgrammar: FGrammar) -> Partial<Grammar> {
@@ -31,5 +42,10 @@ pub fn analyse(cx: &ExtCtxt, fgrammar: FGrammar) -> Partial<Grammar> {
     .and_then(|grammar| rust_functions_duplicate(cx, grammar, fgrammar.rust_items.clone()))
     .and_then(|grammar| UndeclaredRule::analyse(cx, grammar))
     .and_then(|grammar| UndeclaredAction::analyse(cx, grammar))
+    .and_then(|grammar| LeftRecursion::analyse(cx, grammar))
+    .and_then(|grammar| NullableRepetition::analyse(cx, grammar))
+    .and_then(|grammar| UnreachableAlternative::analyse(cx, grammar))
     .and_then(|grammar| decorate_with_attributes(cx, &fgrammar, grammar))
+    .and_then(|grammar| Unused::analyse(cx, grammar))
+    .and_then(|grammar| generate_ast_types(cx, grammar))
 }
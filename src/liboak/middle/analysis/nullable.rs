@@ -0,0 +1,102 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An expression is nullable if it can succeed without consuming input. The nullable rules are computed by iterating until no more rule becomes nullable.
+
+use middle::analysis::ast::*;
+use std::collections::HashSet;
+
+pub struct Nullable
+{
+  nullable_rules: HashSet<Ident>
+}
+
+impl Nullable
+{
+  pub fn new(rules: &OrderedMap<Ident, Rule>) -> Nullable {
+    let mut nullable = Nullable {
+      nullable_rules: HashSet::new()
+    };
+    let mut changed = true;
+    while changed {
+      changed = false;
+      for rule in rules.values() {
+        let id = rule.name.node;
+        if !nullable.nullable_rules.contains(&id) && nullable.is_nullable(&rule.def) {
+          nullable.nullable_rules.insert(id);
+          changed = true;
+        }
+      }
+    }
+    nullable
+  }
+
+  pub fn is_nullable(&self, expr: &Box<Expression>) -> bool {
+    NullableExpr { nullable_rules: &self.nullable_rules }.visit_expr(expr)
+  }
+
+  pub fn is_nullable_rule(&self, id: Ident) -> bool {
+    self.nullable_rules.contains(&id)
+  }
+}
+
+struct NullableExpr<'a>
+{
+  nullable_rules: &'a HashSet<Ident>
+}
+
+impl<'a> Visitor<Expression, bool> for NullableExpr<'a>
+{
+  fn visit_str_literal(&mut self, _parent: &Box<Expression>, lit: &String) -> bool {
+    lit.is_empty()
+  }
+
+  /// The indentation terminals do not consume input when the indentation of the line was already read.
+  fn visit_indentation(&mut self, _parent: &Box<Expression>, _kind: IndentKind) -> bool {
+    true
+  }
+
+  fn visit_non_terminal_symbol(&mut self, _parent: &Box<Expression>, id: Ident) -> bool {
+    self.nullable_rules.contains(&id)
+  }
+
+  fn visit_character(&mut self, _parent: &Box<Expression>) -> bool {
+    false
+  }
+
+  fn visit_sequence(&mut self, _parent: &Box<Expression>, exprs: &Vec<Box<Expression>>) -> bool {
+    exprs.iter().all(|expr| self.visit_expr(expr))
+  }
+
+  fn visit_choice(&mut self, _parent: &Box<Expression>, exprs: &Vec<Box<Expression>>) -> bool {
+    exprs.iter().any(|expr| self.visit_expr(expr))
+  }
+
+  /// The operand alone is a valid expression of the table.
+  fn visit_operator_table(&mut self, _parent: &Box<Expression>, table: &OperatorTableExpr<Expression>) -> bool {
+    self.visit_expr(&table.operand)
+  }
+
+  fn visit_zero_or_more(&mut self, _parent: &Box<Expression>, _expr: &Box<Expression>) -> bool {
+    true
+  }
+
+  fn visit_optional(&mut self, _parent: &Box<Expression>, _expr: &Box<Expression>) -> bool {
+    true
+  }
+
+  fn visit_syntactic_predicate(&mut self, _parent: &Box<Expression>, _expr: &Box<Expression>) -> bool {
+    true
+  }
+}
@@ -0,0 +1,109 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The repetitions `e*` and `e+` loop until `e` fails, if `e` can succeed without consuming input, the repetition never terminates. Similarly, the operators of an operator table are parsed in a loop (postfix and infix) or recursively (prefix), so an operator succeeding without consuming input is rejected.
+
+use middle::analysis::ast::*;
+use middle::analysis::nullable::*;
+use monad::partial::Partial::*;
+
+pub struct NullableRepetition<'a>
+{
+  cx: &'a ExtCtxt<'a>,
+  rules: &'a OrderedMap<Ident, Rule>,
+  nullable: Nullable,
+  has_nullable_repetition: bool
+}
+
+impl<'a> NullableRepetition<'a>
+{
+  pub fn analyse(cx: &'a ExtCtxt<'a>, grammar: Grammar) -> Partial<Grammar> {
+    if NullableRepetition::has_nullable_repetition(cx, &grammar.rules) {
+      Nothing
+    } else {
+      Value(grammar)
+    }
+  }
+
+  fn has_nullable_repetition(cx: &'a ExtCtxt<'a>, rules: &'a OrderedMap<Ident, Rule>) -> bool {
+    let mut analyser = NullableRepetition {
+      cx: cx,
+      rules: rules,
+      nullable: Nullable::new(rules),
+      has_nullable_repetition: false
+    };
+    // Rules are visited in the order of their declaration, kept by `OrderedMap`, so errors are reported in a stable order.
+    for rule in rules.values() {
+      analyser.visit_expr(&rule.def);
+    }
+    analyser.has_nullable_repetition
+  }
+
+  fn check_repetition(&mut self, parent: &Box<Expression>, expr: &Box<Expression>, op: &str) {
+    if self.nullable.is_nullable(expr) {
+      self.has_nullable_repetition = true;
+      let mut db = self.cx.struct_span_err(parent.span,
+        format!("The expression repeated by `{}` can succeed without consuming input, \
+          so the repetition never terminates.", op).as_str());
+      if let Expression_::NonTerminalSymbol(id) = expr.node {
+        db.span_note(self.rules.get(&id).unwrap().name.span,
+          format!("The rule `{}` can succeed without consuming input.", id).as_str());
+      }
+      db.note("An expression such as `e?`, `e*`, a predicate or an empty literal succeeds without consuming input.");
+      db.emit();
+    }
+    self.visit_expr(expr);
+  }
+
+  fn check_operator(&mut self, parent: &Box<Expression>, associativity: Associativity, op: &Operator<Expression>) {
+    if self.nullable.is_nullable(&op.expr) {
+      self.has_nullable_repetition = true;
+      let kind = match associativity {
+        Associativity::Prefix => "prefix",
+        Associativity::Postfix => "postfix",
+        Associativity::Left | Associativity::Right => "infix"
+      };
+      let mut db = self.cx.struct_span_err(parent.span,
+        "An operator of the table can succeed without consuming input, \
+        so the operators can be parsed forever.");
+      db.span_note(op.expr.span,
+        format!("The {} operator of the action `{}` can succeed without consuming input.", kind, op.action).as_str());
+      db.emit();
+    }
+  }
+}
+
+impl<'a> Visitor<Expression, ()> for NullableRepetition<'a>
+{
+  unit_visitor_impl!(Expression, str_literal);
+  unit_visitor_impl!(Expression, indentation);
+  unit_visitor_impl!(Expression, non_terminal);
+  unit_visitor_impl!(Expression, character);
+  unit_visitor_impl!(Expression, sequence);
+  unit_visitor_impl!(Expression, choice);
+
+  fn visit_operator_table(&mut self, parent: &Box<Expression>, table: &OperatorTableExpr<Expression>) {
+    for (_, associativity, op) in table.operators() {
+      self.check_operator(parent, associativity, op);
+    }
+    walk_operator_table(self, table);
+  }
+  fn visit_zero_or_more(&mut self, parent: &Box<Expression>, expr: &Box<Expression>) {
+    self.check_repetition(parent, expr, "*");
+  }
+
+  fn visit_one_or_more(&mut self, parent: &Box<Expression>, expr: &Box<Expression>) {
+    self.check_repetition(parent, expr, "+");
+  }
+}
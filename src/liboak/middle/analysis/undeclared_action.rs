@@ -42,20 +42,33 @@ impl<'a> UndeclaredAction<'a>
     }
     analyser.has_undeclared
   }
+
+  fn check_action(&mut self, span: Span, id: Ident) {
+    if !self.grammar.rust_functions.contains_key(&id) {
+      self.cx.span_err(span, "Undeclared action. This must be a function declared in the grammar scope.");
+      self.has_undeclared = true;
+    }
+  }
 }
 
 impl<'a> Visitor<Expression, ()> for UndeclaredAction<'a>
 {
   unit_visitor_impl!(Expression, str_literal);
+  unit_visitor_impl!(Expression, indentation);
   unit_visitor_impl!(Expression, character);
   unit_visitor_impl!(Expression, sequence);
   unit_visitor_impl!(Expression, choice);
   unit_visitor_impl!(Expression, non_terminal);
 
   fn visit_semantic_action(&mut self, parent: &Box<Expression>, _expr: &Box<Expression>, id: Ident) {
-    if !self.grammar.rust_functions.contains_key(&id) {
-      self.cx.span_err(parent.span, "Undeclared action. This must be a function declared in the grammar scope.");
-      self.has_undeclared = true;
+    self.check_action(parent.span, id);
+  }
+
+  /// The undeclared action of an operator is reported on the expression of this operator rather than on the whole table.
+  fn visit_operator_table(&mut self, _parent: &Box<Expression>, table: &OperatorTableExpr<Expression>) {
+    for (_, _, op) in table.operators() {
+      self.check_action(op.expr.span, op.action);
     }
+    walk_operator_table(self, table);
   }
 }
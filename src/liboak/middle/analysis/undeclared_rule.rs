@@ -15,12 +15,11 @@
 
 use middle::analysis::ast::*;
 use monad::partial::Partial::*;
-use std::collections::HashMap;
 
 pub struct UndeclaredRule<'a>
 {
   cx: &'a ExtCtxt<'a>,
-  rules: &'a HashMap<Ident, Rule>,
+  rules: &'a OrderedMap<Ident, Rule>,
   has_undeclared: bool
 }
 
@@ -50,9 +49,11 @@ impl<'a> UndeclaredRule<'a>
 impl<'a> Visitor<Expression, ()> for UndeclaredRule<'a>
 {
   unit_visitor_impl!(Expression, str_literal);
+  unit_visitor_impl!(Expression, indentation);
   unit_visitor_impl!(Expression, character);
   unit_visitor_impl!(Expression, sequence);
   unit_visitor_impl!(Expression, choice);
+  unit_visitor_impl!(Expression, operator_table);
 
   fn visit_non_terminal_symbol(&mut self, parent: &Box<Expression>, id: Ident) {
     if !self.rules.contains_key(&id) {
@@ -0,0 +1,296 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An alternative of a choice is unreachable if an earlier alternative succeeds on every input it can match, since the choice stops at the first alternative that succeeds. For example, in `"=" / "=="`, the second alternative is never tried on `==`. It is reported as a warning.
+//!
+//! An alternative `e1` shadows the inputs starting with a literal or with a character of a class, a later alternative `e2` is unreachable if every input it matches is shadowed by `e1`. This analysis is conservative and only detects the simple cases.
+
+use ast::Expression_::*;
+use middle::analysis::ast::*;
+use monad::partial::Partial::*;
+use std::char;
+use std::collections::HashMap;
+
+pub struct UnreachableAlternative<'a>
+{
+  cx: &'a ExtCtxt<'a>,
+  matcher: Matcher<'a>
+}
+
+impl<'a> UnreachableAlternative<'a>
+{
+  pub fn analyse(cx: &'a ExtCtxt<'a>, grammar: Grammar) -> Partial<Grammar> {
+    {
+      let mut analyser = UnreachableAlternative {
+        cx: cx,
+        matcher: Matcher::new(&grammar.rules)
+      };
+      for rule in grammar.rules.values() {
+        analyser.visit_expr(&rule.def);
+      }
+    }
+    Value(grammar)
+  }
+
+  fn check_choice(&mut self, alternatives: &Vec<Box<Expression>>) {
+    for (i, alternative) in alternatives.iter().enumerate() {
+      let matched = self.matcher.matched_prefix(alternative);
+      for previous in &alternatives[..i] {
+        let shadows = self.matcher.shadows(previous);
+        if let Some(shadow) = shadows.into_iter().find(|shadow| shadow.covers(&matched)) {
+          let mut db = self.cx.struct_span_warn(alternative.span,
+            "This alternative is unreachable: an earlier alternative succeeds on every input it matches.");
+          db.span_note(previous.span, shadow.description().as_str());
+          db.emit();
+          break;
+        }
+      }
+    }
+  }
+}
+
+impl<'a> Visitor<Expression, ()> for UnreachableAlternative<'a>
+{
+  unit_visitor_impl!(Expression, str_literal);
+  unit_visitor_impl!(Expression, indentation);
+  unit_visitor_impl!(Expression, non_terminal);
+  unit_visitor_impl!(Expression, character);
+  unit_visitor_impl!(Expression, sequence);
+  unit_visitor_impl!(Expression, operator_table);
+
+  fn visit_choice(&mut self, _parent: &Box<Expression>, exprs: &Vec<Box<Expression>>) {
+    self.check_choice(exprs);
+    walk_exprs(self, exprs);
+  }
+}
+
+/// Inputs on which an expression always succeeds.
+#[derive(Clone)]
+enum Shadow
+{
+  /// Every input starting with this literal, every input if it is empty.
+  Prefix(String),
+  /// Every input starting with a character of the class.
+  Class(Vec<CharacterInterval>)
+}
+
+impl Shadow
+{
+  fn is_always(&self) -> bool {
+    match self {
+      &Shadow::Prefix(ref prefix) => prefix.is_empty(),
+      _ => false
+    }
+  }
+
+  fn covers(&self, matched: &MatchedPrefix) -> bool {
+    match self {
+      &Shadow::Prefix(ref prefix) => matched.literal.starts_with(prefix.as_str()),
+      &Shadow::Class(ref class) => {
+        match matched.literal.chars().next() {
+          Some(c) => class_contains(class, c),
+          None => matched.first_class.as_ref().map_or(false, |first| class_includes(class, first))
+        }
+      }
+    }
+  }
+
+  fn description(&self) -> String {
+    match self {
+      &Shadow::Prefix(ref prefix) if prefix.is_empty() =>
+        format!("This alternative always succeeds."),
+      &Shadow::Prefix(ref prefix) =>
+        format!("This alternative succeeds on every input starting with `{}`.", prefix),
+      &Shadow::Class(ref class) =>
+        format!("This alternative succeeds on every input starting with a character of `{}`.",
+          CharacterClassExpr { intervals: class.clone() })
+    }
+  }
+}
+
+/// What is known about the beginning of the inputs matched by an expression.
+struct MatchedPrefix
+{
+  /// Every input matched starts with this literal.
+  literal: String,
+  /// If `literal` is empty, every input matched starts with a character of this class.
+  first_class: Option<Vec<CharacterInterval>>
+}
+
+fn class_contains(class: &Vec<CharacterInterval>, c: char) -> bool {
+  class.iter().any(|interval| interval.lo <= c && c <= interval.hi)
+}
+
+fn class_includes(class: &Vec<CharacterInterval>, other: &Vec<CharacterInterval>) -> bool {
+  other.iter().all(|o| class.iter().any(|interval| interval.lo <= o.lo && o.hi <= interval.hi))
+}
+
+fn any_char_class() -> Vec<CharacterInterval> {
+  vec![CharacterInterval { lo: '\u{0}', hi: char::MAX }]
+}
+
+/// Computes the shadows and the matched prefixes of expressions. The rules are followed and a rule already being examined is considered to match nothing. The results are computed once per rule.
+struct Matcher<'a>
+{
+  rules: &'a OrderedMap<Ident, Rule>,
+  rule_shadows: HashMap<Ident, Vec<Shadow>>,
+  rule_literal_prefixes: HashMap<Ident, (String, bool)>,
+  rule_first_classes: HashMap<Ident, Option<Vec<CharacterInterval>>>,
+  rule_stack: Vec<Ident>
+}
+
+impl<'a> Matcher<'a>
+{
+  fn new(rules: &'a OrderedMap<Ident, Rule>) -> Matcher<'a> {
+    Matcher {
+      rules: rules,
+      rule_shadows: HashMap::new(),
+      rule_literal_prefixes: HashMap::new(),
+      rule_first_classes: HashMap::new(),
+      rule_stack: vec![]
+    }
+  }
+
+  fn in_rule<R, F>(&mut self, id: Ident, default: R, f: F) -> R where
+   F: FnOnce(&mut Matcher<'a>, &'a Box<Expression>) -> R
+  {
+    let rules = self.rules;
+    match rules.get(&id) {
+      Some(rule) => {
+        self.rule_stack.push(id);
+        let result = f(self, &rule.def);
+        self.rule_stack.pop();
+        result
+      }
+      None => default
+    }
+  }
+
+  fn shadows_of_rule(&mut self, id: Ident) -> Vec<Shadow> {
+    if let Some(shadows) = self.rule_shadows.get(&id) {
+      return shadows.clone();
+    }
+    if self.rule_stack.contains(&id) {
+      return vec![];
+    }
+    let shadows = self.in_rule(id, vec![], |matcher, def| matcher.shadows(def));
+    self.rule_shadows.insert(id, shadows.clone());
+    shadows
+  }
+
+  fn literal_prefix_of_rule(&mut self, id: Ident) -> (String, bool) {
+    if let Some(prefix) = self.rule_literal_prefixes.get(&id) {
+      return prefix.clone();
+    }
+    if self.rule_stack.contains(&id) {
+      return (String::new(), false);
+    }
+    let prefix = self.in_rule(id, (String::new(), false), |matcher, def| matcher.literal_prefix(def));
+    self.rule_literal_prefixes.insert(id, prefix.clone());
+    prefix
+  }
+
+  fn first_class_of_rule(&mut self, id: Ident) -> Option<Vec<CharacterInterval>> {
+    if let Some(class) = self.rule_first_classes.get(&id) {
+      return class.clone();
+    }
+    if self.rule_stack.contains(&id) {
+      return None;
+    }
+    let class = self.in_rule(id, None, |matcher, def| matcher.first_class(def));
+    self.rule_first_classes.insert(id, class.clone());
+    class
+  }
+
+  fn always_succeeds(&mut self, expr: &Box<Expression>) -> bool {
+    self.shadows(expr).iter().any(Shadow::is_always)
+  }
+
+  fn shadows(&mut self, expr: &Box<Expression>) -> Vec<Shadow> {
+    match &expr.node {
+      &StrLiteral(ref lit) => vec![Shadow::Prefix(lit.clone())],
+      &AnySingleChar => vec![Shadow::Class(any_char_class())],
+      &CharacterClass(ref class) => vec![Shadow::Class(class.intervals.clone())],
+      &NonTerminalSymbol(id) => self.shadows_of_rule(id),
+      &Sequence(ref exprs) => {
+        if exprs[1..].iter().all(|expr| self.always_succeeds(expr)) {
+          self.shadows(&exprs[0])
+        }
+        else {
+          vec![]
+        }
+      }
+      &Choice(ref exprs) => exprs.iter().flat_map(|expr| self.shadows(expr)).collect(),
+      &ZeroOrMore(_) | &Optional(_) => vec![Shadow::Prefix(String::new())],
+      &OneOrMore(ref expr) | &AndPredicate(ref expr)
+    | &SemanticAction(ref expr, _) | &Recovery(ref expr, _) => self.shadows(expr),
+      &OperatorTable(ref table) => self.shadows(&table.operand),
+      &NotPredicate(_) | &Indentation(_) => vec![]
+    }
+  }
+
+  fn matched_prefix(&mut self, expr: &Box<Expression>) -> MatchedPrefix {
+    let (literal, _) = self.literal_prefix(expr);
+    MatchedPrefix {
+      literal: literal,
+      first_class: self.first_class(expr)
+    }
+  }
+
+  /// Literal starting every input matched by `expr`, the boolean is `true` if `expr` always matches exactly this literal.
+  fn literal_prefix(&mut self, expr: &Box<Expression>) -> (String, bool) {
+    match &expr.node {
+      &StrLiteral(ref lit) => (lit.clone(), true),
+      &CharacterClass(ref class) if class.intervals.len() == 1 && class.intervals[0].lo == class.intervals[0].hi =>
+        (class.intervals[0].lo.to_string(), true),
+      &NonTerminalSymbol(id) => self.literal_prefix_of_rule(id),
+      &Sequence(ref exprs) => {
+        let mut literal = String::new();
+        for expr in exprs {
+          let (prefix, complete) = self.literal_prefix(expr);
+          literal.push_str(prefix.as_str());
+          if !complete {
+            return (literal, false);
+          }
+        }
+        (literal, true)
+      }
+      &OneOrMore(ref expr) => (self.literal_prefix(expr).0, false),
+      &SemanticAction(ref expr, _) => self.literal_prefix(expr),
+      _ => (String::new(), false)
+    }
+  }
+
+  /// Class of the first character of every input matched by `expr`.
+  fn first_class(&mut self, expr: &Box<Expression>) -> Option<Vec<CharacterInterval>> {
+    match &expr.node {
+      &AnySingleChar => Some(any_char_class()),
+      &CharacterClass(ref class) => Some(class.intervals.clone()),
+      &NonTerminalSymbol(id) => self.first_class_of_rule(id),
+      &Sequence(ref exprs) => self.first_class(&exprs[0]),
+      &Choice(ref exprs) => {
+        let mut class = vec![];
+        for expr in exprs {
+          match self.first_class(expr) {
+            Some(intervals) => class.extend(intervals.into_iter()),
+            None => return None
+          }
+        }
+        Some(class)
+      }
+      &OneOrMore(ref expr) | &SemanticAction(ref expr, _) => self.first_class(expr),
+      _ => None
+    }
+  }
+}
@@ -0,0 +1,207 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reports the rules that cannot be reached from the start rules and the functions of the grammar scope referenced nowhere.
+//!
+//! The start rules are the rules marked with `#[start]`, or the first rule of the grammar if no rule is marked. A function is used if it is a semantic action of any rule, reached or not, if it is called by another item of the grammar scope or if it is public. The warnings are silenced with `#[allow(unused_rule)]` on a rule and with `#[allow(dead_code)]` on a function.
+
+use middle::analysis::ast::*;
+use monad::partial::Partial::*;
+use rust;
+use rust::{MetaItemKind, Name, TokenTree, Token, Visibility};
+use std::collections::HashSet;
+
+pub struct Unused<'a>
+{
+  grammar: &'a Grammar,
+  reached_rules: HashSet<Ident>,
+  /// Rules reached but not yet visited.
+  pending: Vec<Ident>
+}
+
+impl<'a> Unused<'a>
+{
+  pub fn analyse(cx: &'a ExtCtxt<'a>, grammar: Grammar) -> Partial<Grammar> {
+    {
+      let mut analyser = Unused {
+        grammar: &grammar,
+        reached_rules: HashSet::new(),
+        pending: vec![]
+      };
+      analyser.reach_from_start_rules();
+      analyser.report_unused_rules(cx);
+      analyser.report_unused_actions(cx);
+    }
+    Value(grammar)
+  }
+
+  fn reach_from_start_rules(&mut self) {
+    let grammar = self.grammar;
+    let rules = &grammar.rules;
+    let mut start_rules: Vec<Ident> = rules.values()
+      .filter(|rule| rule.attributes.start)
+      .map(|rule| rule.name.node)
+      .collect();
+    if start_rules.is_empty() {
+      start_rules.extend(rules.keys().next().cloned());
+    }
+    for id in start_rules {
+      self.reach(id);
+    }
+    while let Some(id) = self.pending.pop() {
+      if let Some(rule) = rules.get(&id) {
+        self.visit_expr(&rule.def);
+      }
+    }
+  }
+
+  fn reach(&mut self, id: Ident) {
+    if self.reached_rules.insert(id) {
+      self.pending.push(id);
+    }
+  }
+
+  fn report_unused_rules(&self, cx: &ExtCtxt) {
+    for rule in self.grammar.rules.values() {
+      if !self.reached_rules.contains(&rule.name.node) && !rule.attributes.allow_unused {
+        let mut db = cx.struct_span_warn(rule.name.span, format!(
+          "The rule `{}` is never used from the start rules.", rule.name.node).as_str());
+        db.help("Mark the entry points of the grammar with `#[start]`, or silence this warning with `#[allow(unused_rule)]`.");
+        db.emit();
+      }
+    }
+  }
+
+  fn report_unused_actions(&self, cx: &ExtCtxt) {
+    let grammar = self.grammar;
+    let mut used_actions = UsedActions { actions: HashSet::new() };
+    for rule in grammar.rules.values() {
+      used_actions.visit_expr(&rule.def);
+    }
+    for (id, item) in grammar.rust_functions.iter() {
+      if !used_actions.actions.contains(id)
+       && item.vis != Visibility::Public
+       && !self.referenced_by_other_items(*id)
+       && !allows_dead_code(&item.attrs)
+      {
+        cx.span_warn(item.span, format!(
+          "The function `{}` is never used.", id).as_str());
+      }
+    }
+  }
+
+  /// `true` if the function `id` is referenced by an item of the grammar scope other than itself.
+  fn referenced_by_other_items(&self, id: Ident) -> bool {
+    let grammar = self.grammar;
+    let functions = grammar.rust_functions.iter()
+      .filter(|&(&fun, _)| fun != id)
+      .map(|(_, item)| item);
+    functions.chain(grammar.rust_items.iter())
+      .any(|item| {
+        let mut references = References { names: HashSet::new() };
+        rust::visit::walk_item(&mut references, item);
+        references.names.contains(&id.name)
+      })
+  }
+}
+
+fn allows_dead_code(attrs: &Vec<Attribute>) -> bool {
+  attrs.iter().any(|attr| {
+    match &attr.node.value.node {
+      &MetaItemKind::List(ref name, ref items) if *name == "allow" => {
+        items.iter().any(|item| match &item.node {
+          &MetaItemKind::Word(ref lint) => *lint == "dead_code",
+          _ => false
+        })
+      }
+      _ => false
+    }
+  })
+}
+
+impl<'a> Visitor<Expression, ()> for Unused<'a>
+{
+  unit_visitor_impl!(Expression, str_literal);
+  unit_visitor_impl!(Expression, indentation);
+  unit_visitor_impl!(Expression, character);
+  unit_visitor_impl!(Expression, sequence);
+  unit_visitor_impl!(Expression, choice);
+  unit_visitor_impl!(Expression, operator_table);
+
+  fn visit_non_terminal_symbol(&mut self, _parent: &Box<Expression>, id: Ident) {
+    self.reach(id);
+  }
+}
+
+/// Collects the semantic actions of the rules.
+struct UsedActions
+{
+  actions: HashSet<Ident>
+}
+
+impl Visitor<Expression, ()> for UsedActions
+{
+  unit_visitor_impl!(Expression, str_literal);
+  unit_visitor_impl!(Expression, indentation);
+  unit_visitor_impl!(Expression, non_terminal);
+  unit_visitor_impl!(Expression, character);
+  unit_visitor_impl!(Expression, sequence);
+  unit_visitor_impl!(Expression, choice);
+
+  fn visit_semantic_action(&mut self, _parent: &Box<Expression>, expr: &Box<Expression>, id: Ident) {
+    self.actions.insert(id);
+    self.visit_expr(expr);
+  }
+
+  fn visit_operator_table(&mut self, _parent: &Box<Expression>, table: &OperatorTableExpr<Expression>) {
+    for (_, _, op) in table.operators() {
+      self.actions.insert(op.action);
+    }
+    walk_operator_table(self, table);
+  }
+}
+
+/// Collects the names appearing in the paths of a Rust item and in the token trees of its macro invocations, such as the arguments of `format!`.
+struct References
+{
+  names: HashSet<Name>
+}
+
+impl References
+{
+  fn visit_tts(&mut self, tts: &[TokenTree]) {
+    for tt in tts {
+      match tt {
+        &TokenTree::Token(_, Token::Ident(id, _)) => { self.names.insert(id.name); }
+        &TokenTree::Delimited(_, ref delimited) => self.visit_tts(&delimited.tts),
+        &TokenTree::Sequence(_, ref sequence) => self.visit_tts(&sequence.tts),
+        _ => ()
+      }
+    }
+  }
+}
+
+impl<'v> rust::visit::Visitor<'v> for References
+{
+  fn visit_path(&mut self, path: &'v rust::Path, _id: rust::NodeId) {
+    for segment in &path.segments {
+      self.names.insert(segment.identifier.name);
+    }
+    rust::visit::walk_path(self, path);
+  }
+
+  fn visit_mac(&mut self, mac: &'v rust::Mac) {
+    self.visit_tts(&mac.node.tts);
+  }
+}
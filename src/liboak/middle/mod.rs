@@ -14,7 +14,7 @@
 
 //! This module performs analysis on the PEG and gives a type to each expressions in the AST.
 
-//! The `analysis` module performs some verifications on the grammar description and the `typing` module gives a type to each rule and expression.
+//! The `analysis` module performs some verifications on the grammar description and the `typing` module gives a type to each rule and expression. The `optimisation` module then simplifies the typed grammar.
 
 use middle::typing::ast::*;
 use monad::partial::Partial;
@@ -23,6 +23,7 @@ pub use front::ast::Grammar as FGrammar;
 
 pub mod analysis;
 pub mod typing;
+mod optimisation;
 
 pub fn analyse(cx: &ExtCtxt, fgrammar: FGrammar) -> Partial<Grammar> {
   if !at_least_one_rule_declared(cx, &fgrammar) {
@@ -32,6 +33,7 @@ pub fn analyse(cx: &ExtCtxt, fgrammar: FGrammar) -> Partial<Grammar> {
   Partial::Value(fgrammar)
     .and_then(|grammar| analysis::analyse(cx, grammar))
     .and_then(|grammar| typing::type_inference(cx, grammar))
+    .and_then(|grammar| optimisation::optimise(grammar))
 }
 
 fn at_least_one_rule_declared(cx: &ExtCtxt, fgrammar: &FGrammar) -> bool {
@@ -0,0 +1,223 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Flattening simplifies the structure of the expressions, bottom-up:
+//! * A sequence nested in a sequence is replaced by its sub-expressions, `a (b c)` becomes `a b c`.
+//! * A choice nested in a choice is replaced by its alternatives, `a / (b / c)` becomes `a / b / c`.
+//! * The adjacent string literals of a sequence are merged, `"a" "b"` becomes `"ab"`. The merged literals are kept in `Expression::merged_literals` and matched one after the other, so a failure is still reported at the beginning of the literal that failed.
+//!
+//! The sub-expressions of a nested expression take its place and thus its evaluation context, a sub-expression with a value is only moved if it is already evaluated in this context. A nested sequence must also produce at most one value, otherwise its value is a tuple inside the tuple of the enclosing sequence.
+
+use middle::typing::ast::*;
+use monad::partial::Partial;
+use rust;
+use std::mem;
+
+pub fn flatten_expressions(mut grammar: Grammar) -> Partial<Grammar> {
+  for rule in grammar.rules.values_mut() {
+    flatten_expr(&mut rule.def);
+  }
+  Partial::Value(grammar)
+}
+
+fn flatten_expr(expr: &mut Box<Expression>) {
+  let (is_sequence, is_choice) = match &mut expr.node {
+    &mut Sequence(ref mut exprs) => {
+      flatten_exprs(exprs);
+      (true, false)
+    }
+    &mut Choice(ref mut exprs) => {
+      flatten_exprs(exprs);
+      (false, true)
+    }
+      &mut ZeroOrMore(ref mut expr)
+    | &mut OneOrMore(ref mut expr)
+    | &mut Optional(ref mut expr)
+    | &mut NotPredicate(ref mut expr)
+    | &mut AndPredicate(ref mut expr)
+    | &mut SemanticAction(ref mut expr, _)
+    | &mut Recovery(ref mut expr, _) => {
+      flatten_expr(expr);
+      (false, false)
+    }
+    &mut OperatorTable(ref mut table) => {
+      flatten_expr(&mut table.operand);
+      for level in table.levels.iter_mut() {
+        for op in level.operators.iter_mut() {
+          flatten_expr(&mut op.expr);
+        }
+      }
+      (false, false)
+    }
+    _ => (false, false)
+  };
+  if is_sequence {
+    flatten_sequence(expr);
+  }
+  else if is_choice {
+    flatten_choice(expr);
+  }
+}
+
+fn flatten_exprs(exprs: &mut Vec<Box<Expression>>) {
+  for expr in exprs.iter_mut() {
+    flatten_expr(expr);
+  }
+}
+
+fn flatten_choice(expr: &mut Box<Expression>) {
+  if let &mut Choice(ref mut alternatives) = &mut expr.node {
+    if alternatives.iter().any(is_nested_choice) {
+      let nested = mem::replace(alternatives, vec![]);
+      for alt in nested {
+        if is_nested_choice(&alt) {
+          alternatives.extend(sub_exprs(alt).into_iter());
+        }
+        else {
+          alternatives.push(alt);
+        }
+      }
+    }
+  }
+}
+
+/// The alternatives of a nested choice must have the unit type if the choice has the unit type, otherwise its type might change.
+fn is_nested_choice(expr: &Box<Expression>) -> bool {
+  match &expr.node {
+    &Choice(ref alternatives) => {
+      alternatives.iter().all(|alt| alt.is_unit() == expr.is_unit() && can_take_place(alt, expr))
+    }
+    _ => false
+  }
+}
+
+/// `true` if `sub_expr` can be evaluated in the context of `expr`, which is the case if it has the same context or the unit type.
+fn can_take_place(sub_expr: &Box<Expression>, expr: &Box<Expression>) -> bool {
+  sub_expr.context == expr.context || sub_expr.is_unit()
+}
+
+/// The sub-expressions of the sequence or the choice `expr`, evaluated in the context of `expr`.
+fn sub_exprs(expr: Box<Expression>) -> Vec<Box<Expression>> {
+  let expr = *expr;
+  match expr.node {
+    Sequence(mut exprs) | Choice(mut exprs) => {
+      for sub_expr in exprs.iter_mut() {
+        sub_expr.context = expr.context;
+      }
+      exprs
+    }
+    _ => unreachable!()
+  }
+}
+
+fn flatten_sequence(expr: &mut Box<Expression>) {
+  let indexes = match expr.ty_clone() {
+    ExprTy::Tuple(indexes) => indexes,
+    _ => return
+  };
+  let exprs = match &mut expr.node {
+    &mut Sequence(ref mut exprs) => mem::replace(exprs, vec![]),
+    _ => return
+  };
+  let (exprs, indexes) = splice_sequences(exprs, indexes);
+  let (mut exprs, indexes) = merge_literals(exprs, indexes);
+  expr.to_tuple_type(indexes);
+  if exprs.len() == 1 && expr.is_unit() && is_literal(&exprs[0]) {
+    let mut literal = exprs.pop().unwrap();
+    literal.span = expr.span;
+    literal.context = expr.context;
+    *expr = literal;
+  }
+  else {
+    expr.node = Sequence(exprs);
+  }
+}
+
+/// Replaces the nested sequences by their sub-expressions. The new tuple indexes of the enclosing sequence are returned.
+fn splice_sequences(exprs: Vec<Box<Expression>>, indexes: Vec<usize>) -> (Vec<Box<Expression>>, Vec<usize>) {
+  let mut spliced = vec![];
+  let mut positions = vec![];
+  for (idx, expr) in exprs.into_iter().enumerate() {
+    let valued = indexes.contains(&idx);
+    match nested_sequence_value(&expr, valued) {
+      Some(value_idx) => {
+        positions.push(spliced.len() + value_idx);
+        spliced.extend(sub_exprs(expr).into_iter());
+      }
+      None => {
+        positions.push(spliced.len());
+        spliced.push(expr);
+      }
+    }
+  }
+  let indexes = indexes.into_iter().map(|idx| positions[idx]).collect();
+  (spliced, indexes)
+}
+
+/// If `expr` is a sequence that can be spliced, returns the index of its sub-expression producing its value (`0` if its value is not used).
+fn nested_sequence_value(expr: &Box<Expression>, valued: bool) -> Option<usize> {
+  match &expr.node {
+    &Sequence(ref exprs) if exprs.iter().all(|sub_expr| can_take_place(sub_expr, expr)) => {
+      match expr.ty_clone() {
+        ExprTy::Tuple(ref indexes) if valued && indexes.len() == 1 => Some(indexes[0]),
+        ExprTy::Tuple(_) if !valued => Some(0),
+        _ => None
+      }
+    }
+    _ => None
+  }
+}
+
+fn merge_literals(exprs: Vec<Box<Expression>>, indexes: Vec<usize>) -> (Vec<Box<Expression>>, Vec<usize>) {
+  let mut merged: Vec<Box<Expression>> = vec![];
+  let mut positions = vec![];
+  for expr in exprs {
+    let appended = match merged.last_mut() {
+      Some(last) => append_literal(last, &expr),
+      None => false
+    };
+    if !appended {
+      merged.push(expr);
+    }
+    positions.push(merged.len() - 1);
+  }
+  let indexes = indexes.into_iter().map(|idx| positions[idx]).collect();
+  (merged, indexes)
+}
+
+/// Appends `expr` to `last` if both are literals with the unit type evaluated in the same context. The literals merged into `last` are recorded in the order of the sequence.
+fn append_literal(last: &mut Box<Expression>, expr: &Box<Expression>) -> bool {
+  if last.context != expr.context || !expr.is_unit() || !last.is_unit() {
+    return false;
+  }
+  let (last_lit, lit) = match (&last.node, &expr.node) {
+    (&StrLiteral(ref last_lit), &StrLiteral(ref lit)) => (last_lit.clone(), lit.clone()),
+    _ => return false
+  };
+  let mut pieces = last.merged_literals.take().unwrap_or_else(|| vec![last_lit.clone()]);
+  pieces.extend(expr.merged_literals.clone().unwrap_or_else(|| vec![lit.clone()]).into_iter());
+  last.node = StrLiteral(last_lit + lit.as_str());
+  last.merged_literals = Some(pieces);
+  if last.span.hi <= expr.span.lo {
+    last.span = rust::mk_sp(last.span.lo, expr.span.hi);
+  }
+  true
+}
+
+fn is_literal(expr: &Box<Expression>) -> bool {
+  match &expr.node {
+    &StrLiteral(_) => true,
+    _ => false
+  }
+}
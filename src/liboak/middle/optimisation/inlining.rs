@@ -0,0 +1,140 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Inlining replaces the calls to a small rule by its definition, such as `lparen` in `lparen = "(" spacing`. A rule is inlined if its definition has at most `MAX_INLINED_SIZE` expressions, contains neither a choice nor an operator table, is not memoised and is not recursive. The functions of an inlined rule are still generated since they are part of the API of the grammar.
+//!
+//! The definitions are copied before inlining so a call is replaced once, by the definition given in the grammar. An error occurring inside an inlined rule is not reported as occurring while parsing this rule anymore.
+
+use middle::typing::ast::*;
+use monad::partial::Partial;
+
+const MAX_INLINED_SIZE: usize = 4;
+
+pub fn inline_rules(mut grammar: Grammar) -> Partial<Grammar> {
+  let inlined = inlinable_rules(&grammar.rules);
+  if !inlined.is_empty() {
+    for rule in grammar.rules.values_mut() {
+      inline_expr(&inlined, &mut rule.def);
+    }
+  }
+  Partial::Value(grammar)
+}
+
+fn inlinable_rules(rules: &OrderedMap<Ident, Rule>) -> HashMap<Ident, Box<Expression>> {
+  rules.values()
+    .filter(|rule| is_inlinable(rules, rule))
+    .map(|rule| (rule.name.node, rule.def.clone()))
+    .collect()
+}
+
+fn is_inlinable(rules: &OrderedMap<Ident, Rule>, rule: &Rule) -> bool {
+  !rule.attributes.memo
+  && size(&rule.def).map_or(false, |size| size <= MAX_INLINED_SIZE)
+  && !reaches(rules, &rule.def, rule.name.node, &mut vec![])
+}
+
+/// Number of expressions in `expr`, `None` if it contains a choice or an operator table.
+fn size(expr: &Box<Expression>) -> Option<usize> {
+  match &expr.node {
+    &Sequence(ref exprs) => {
+      let mut size_seq = 1;
+      for expr in exprs {
+        match size(expr) {
+          Some(size_expr) => size_seq += size_expr,
+          None => return None
+        }
+      }
+      Some(size_seq)
+    }
+      &ZeroOrMore(ref expr)
+    | &OneOrMore(ref expr)
+    | &Optional(ref expr)
+    | &NotPredicate(ref expr)
+    | &AndPredicate(ref expr)
+    | &SemanticAction(ref expr, _)
+    | &Recovery(ref expr, _) => size(expr).map(|size_expr| size_expr + 1),
+    &Choice(_) | &OperatorTable(_) => None,
+    _ => Some(1)
+  }
+}
+
+/// `true` if the rule `target` can be called from `expr`.
+fn reaches(rules: &OrderedMap<Ident, Rule>, expr: &Box<Expression>, target: Ident, visited: &mut Vec<Ident>) -> bool {
+  match &expr.node {
+    &NonTerminalSymbol(id) => {
+      if id == target {
+        true
+      }
+      else if visited.contains(&id) {
+        false
+      }
+      else {
+        visited.push(id);
+        rules.get(&id).map_or(false, |rule| reaches(rules, &rule.def, target, visited))
+      }
+    }
+      &Sequence(ref exprs)
+    | &Choice(ref exprs) => exprs.iter().any(|expr| reaches(rules, expr, target, visited)),
+      &ZeroOrMore(ref expr)
+    | &OneOrMore(ref expr)
+    | &Optional(ref expr)
+    | &NotPredicate(ref expr)
+    | &AndPredicate(ref expr)
+    | &SemanticAction(ref expr, _)
+    | &Recovery(ref expr, _) => reaches(rules, expr, target, visited),
+    &OperatorTable(ref table) => {
+      reaches(rules, &table.operand, target, visited)
+      || table.operators().into_iter().any(|(_, _, op)| reaches(rules, &op.expr, target, visited))
+    }
+    _ => false
+  }
+}
+
+fn inline_expr(inlined: &HashMap<Ident, Box<Expression>>, expr: &mut Box<Expression>) {
+  let def = match &expr.node {
+    &NonTerminalSymbol(id) => inlined.get(&id).cloned(),
+    _ => None
+  };
+  match def {
+    Some(def) => *expr = def,
+    None => inline_node(inlined, &mut expr.node)
+  }
+}
+
+fn inline_node(inlined: &HashMap<Ident, Box<Expression>>, node: &mut ExpressionNode) {
+  match node {
+      &mut Sequence(ref mut exprs)
+    | &mut Choice(ref mut exprs) => {
+      for expr in exprs.iter_mut() {
+        inline_expr(inlined, expr);
+      }
+    }
+      &mut ZeroOrMore(ref mut expr)
+    | &mut OneOrMore(ref mut expr)
+    | &mut Optional(ref mut expr)
+    | &mut NotPredicate(ref mut expr)
+    | &mut AndPredicate(ref mut expr)
+    | &mut SemanticAction(ref mut expr, _)
+    | &mut Recovery(ref mut expr, _) => inline_expr(inlined, expr),
+    &mut OperatorTable(ref mut table) => {
+      inline_expr(inlined, &mut table.operand);
+      for level in table.levels.iter_mut() {
+        for op in level.operators.iter_mut() {
+          inline_expr(inlined, &mut op.expr);
+        }
+      }
+    }
+    _ => ()
+  }
+}
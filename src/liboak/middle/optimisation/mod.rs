@@ -0,0 +1,39 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The optimisation passes rewrite the typed grammar so that the generated parsers build the same values and report the same errors with less code. The type of every expression is preserved.
+//!
+//! They are disabled with `#![no_optimisation]` and when the generated functions are observed, that is with `#![cst]`, `#![trace]` or `#![profile]`, and when the errors record the rules with `#![error_context]`. The structurally identical sub-expressions are identified by the last pass so that they share their generated functions, and the choices are dispatched on their next character during the code generation (see `back::first_set`).
+
+use middle::typing::ast::*;
+use middle::optimisation::inlining::*;
+use middle::optimisation::flattening::*;
+use middle::optimisation::sharing::*;
+use monad::partial::Partial;
+
+mod inlining;
+mod flattening;
+mod sharing;
+
+pub fn optimise(grammar: Grammar) -> Partial<Grammar> {
+  if grammar.attributes.is_optimised() {
+    Partial::Value(grammar)
+      .and_then(|grammar| inline_rules(grammar))
+      .and_then(|grammar| flatten_expressions(grammar))
+      .and_then(|grammar| share_expressions(grammar))
+  }
+  else {
+    Partial::Value(grammar)
+  }
+}
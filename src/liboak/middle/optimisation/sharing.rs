@@ -0,0 +1,116 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sharing identifies the structurally identical expressions, bottom-up: two expressions are identical if they have the same kind and data (literal and its merged literals, class, rule, action,...), the same type, the same evaluation context and identical sub-expressions. Each expression receives the identifier of its class in `Expression::shared_id`, and the functions of only one expression per class are generated (see `back::code_gen`).
+
+use middle::typing::ast::*;
+use monad::partial::Partial;
+
+pub fn share_expressions(mut grammar: Grammar) -> Partial<Grammar> {
+  let mut sharing = Sharing {
+    ids: HashMap::new()
+  };
+  for rule in grammar.rules.values_mut() {
+    sharing.share_expr(&mut rule.def);
+  }
+  Partial::Value(grammar)
+}
+
+/// The structure of an expression where the sub-expressions are replaced by their identifiers.
+#[derive(PartialEq, Eq, Hash)]
+enum Shape
+{
+  StrLiteral(String, Option<Vec<String>>),
+  AnySingleChar,
+  CharacterClass(Vec<(char, char)>),
+  NonTerminalSymbol(Ident),
+  Sequence(Vec<usize>),
+  Choice(Vec<usize>),
+  ZeroOrMore(usize),
+  OneOrMore(usize),
+  Optional(usize),
+  NotPredicate(usize),
+  AndPredicate(usize),
+  SemanticAction(usize, Ident),
+  Recovery(usize, String),
+  OperatorTable(usize, Vec<(Associativity, Vec<(usize, Ident)>)>),
+  Indentation(IndentKind)
+}
+
+#[derive(PartialEq, Eq, Hash)]
+struct ExprKey
+{
+  shape: Shape,
+  ty: ExprTy,
+  context: EvaluationContext,
+  invisible: bool
+}
+
+struct Sharing
+{
+  ids: HashMap<ExprKey, usize>
+}
+
+impl Sharing
+{
+  fn share_expr(&mut self, expr: &mut Box<Expression>) -> usize {
+    let key = ExprKey {
+      shape: self.shape(expr),
+      ty: expr.ty_clone(),
+      context: expr.context,
+      invisible: expr.is_invisible()
+    };
+    let next_id = self.ids.len();
+    let id = *self.ids.entry(key).or_insert(next_id);
+    expr.shared_id = Some(id);
+    id
+  }
+
+  fn share_exprs(&mut self, exprs: &mut Vec<Box<Expression>>) -> Vec<usize> {
+    exprs.iter_mut().map(|expr| self.share_expr(expr)).collect()
+  }
+
+  fn shape(&mut self, expr: &mut Box<Expression>) -> Shape {
+    let merged_literals = expr.merged_literals.clone();
+    match &mut expr.node {
+      &mut StrLiteral(ref lit) => Shape::StrLiteral(lit.clone(), merged_literals),
+      &mut AnySingleChar => Shape::AnySingleChar,
+      &mut CharacterClass(ref classes) => {
+        Shape::CharacterClass(classes.intervals.iter().map(|i| (i.lo, i.hi)).collect())
+      }
+      &mut NonTerminalSymbol(id) => Shape::NonTerminalSymbol(id),
+      &mut Sequence(ref mut exprs) => Shape::Sequence(self.share_exprs(exprs)),
+      &mut Choice(ref mut exprs) => Shape::Choice(self.share_exprs(exprs)),
+      &mut ZeroOrMore(ref mut expr) => Shape::ZeroOrMore(self.share_expr(expr)),
+      &mut OneOrMore(ref mut expr) => Shape::OneOrMore(self.share_expr(expr)),
+      &mut Optional(ref mut expr) => Shape::Optional(self.share_expr(expr)),
+      &mut NotPredicate(ref mut expr) => Shape::NotPredicate(self.share_expr(expr)),
+      &mut AndPredicate(ref mut expr) => Shape::AndPredicate(self.share_expr(expr)),
+      &mut SemanticAction(ref mut expr, action) => Shape::SemanticAction(self.share_expr(expr), action),
+      &mut Recovery(ref mut expr, ref sync) => Shape::Recovery(self.share_expr(expr), sync.clone()),
+      &mut OperatorTable(ref mut table) => {
+        let operand = self.share_expr(&mut table.operand);
+        let mut levels = vec![];
+        for level in table.levels.iter_mut() {
+          let operators = level.operators.iter_mut()
+            .map(|op| (self.share_expr(&mut op.expr), op.action))
+            .collect();
+          levels.push((level.associativity, operators));
+        }
+        Shape::OperatorTable(operand, levels)
+      }
+      &mut Indentation(kind) => Shape::Indentation(kind)
+    }
+  }
+}
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-pub use middle::analysis::ast::GrammarAttributes;
+pub use middle::analysis::ast::{GrammarAttributes, RuleAttributes, AstType, AstConstructor};
 pub use ast::*;
 pub use ast::Expression_::*;
 
@@ -31,19 +31,21 @@ pub type Rule = Rule_<Expression>;
 pub struct Grammar_<Expr>
 {
   pub name: Ident,
-  pub rules: HashMap<Ident, Rule_<Expr>>,
-  pub rust_functions: HashMap<Ident, RItem>,
+  pub rules: OrderedMap<Ident, Rule_<Expr>>,
+  pub rust_functions: OrderedMap<Ident, RItem>,
   pub rust_items: Vec<RItem>,
+  pub ast_types: Vec<AstType>,
   pub attributes: GrammarAttributes
 }
 
 pub struct Rule_<Expr>
 {
   pub name: SpannedIdent,
-  pub def: Box<Expr>
+  pub def: Box<Expr>,
+  pub attributes: RuleAttributes
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub enum EvaluationContext
 {
   UnValued,
@@ -61,13 +63,18 @@ impl EvaluationContext
 pub type ExpressionNode = Expression_<Expression>;
 
 // Explicitly typed expression.
+#[derive(Clone)]
 pub struct Expression
 {
   pub span: Span,
   pub node: ExpressionNode,
   pub invisible: RefCell<bool>,
   pub ty: RefCell<ExprTy>,
-  pub context: EvaluationContext
+  pub context: EvaluationContext,
+  /// The structurally identical expressions have the same identifier, `None` if the expressions are not shared (see `middle::optimisation::sharing`).
+  pub shared_id: Option<usize>,
+  /// The literals merged into this `StrLiteral` by the flattening, in the order of the sequence, `None` if the literal is not merged (see `middle::optimisation::flattening`).
+  pub merged_literals: Option<Vec<String>>
 }
 
 impl ExprNode for Expression
@@ -85,7 +92,9 @@ impl Expression
       node: node,
       invisible: RefCell::new(false),
       ty: RefCell::new(ty),
-      context: UnValued
+      context: UnValued,
+      shared_id: None,
+      merged_literals: None
     };
     if expr.is_by_default_invisible() {
       expr.to_invisible_type();
@@ -105,6 +114,7 @@ impl Expression
     match self.node {
       NonTerminalSymbol(_) => true,
       Choice(_) => true,
+      OperatorTable(_) => true,
       _ => self.ty.borrow().is_projection()
     }
   }
@@ -128,13 +138,13 @@ impl Expression
 
   fn is_by_default_invisible(&self) -> bool {
     match &self.node {
-      &StrLiteral(_) | &NotPredicate(_) | &AndPredicate(_) => true,
+      &StrLiteral(_) | &Indentation(_) | &NotPredicate(_) | &AndPredicate(_) => true,
       _ => false
     }
   }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum ExprTy
 {
   /// The type of the expression is given with a trivial mapping between expressions and types.
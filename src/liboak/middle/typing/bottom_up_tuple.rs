@@ -26,18 +26,18 @@ pub fn bottom_up_tuple_inference(grammar: Grammar)
 
 pub struct BottomUpTupleInference<'a>
 {
-  rules: &'a HashMap<Ident, Rule>,
+  rules: &'a OrderedMap<Ident, Rule>,
   visited: HashMap<Ident, bool>
 }
 
 impl<'a> BottomUpTupleInference<'a>
 {
-  fn transform(rules: &'a HashMap<Ident, Rule>) {
+  fn transform(rules: &'a OrderedMap<Ident, Rule>) {
     let mut bottom_up_tuple = BottomUpTupleInference::new(rules);
     bottom_up_tuple.visit_rules();
   }
 
-  fn new(rules: &'a HashMap<Ident, Rule>) -> BottomUpTupleInference<'a> {
+  fn new(rules: &'a OrderedMap<Ident, Rule>) -> BottomUpTupleInference<'a> {
     let mut visited = HashMap::with_capacity(rules.len());
     for id in rules.keys() {
       visited.insert(id.clone(), false);
@@ -66,9 +66,11 @@ impl<'a> BottomUpTupleInference<'a>
 impl<'a> Visitor<Expression, ()> for BottomUpTupleInference<'a>
 {
   unit_visitor_impl!(Expression, str_literal);
+  unit_visitor_impl!(Expression, indentation);
   unit_visitor_impl!(Expression, character);
   unit_visitor_impl!(Expression, sequence);
   unit_visitor_impl!(Expression, choice);
+  unit_visitor_impl!(Expression, operator_table);
 
   fn visit_non_terminal_symbol(&mut self, parent: &Box<Expression>, ident: Ident) {
     let rule = &self.rules[&ident];
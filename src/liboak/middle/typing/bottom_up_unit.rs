@@ -15,7 +15,7 @@
 //! Bottom-up unit inference consists of propagating invisible unit types up in the expressions.
 //!
 //! The typing rules are of the form `expr:ty => expr':ty'` which means that if `expr` has type `ty` then `expr'` has type `ty'`:
-//! * Basic combinators (`e*`, `e+`, `e?`):
+//! * Basic combinators (`e*`, `e+`, `e?`, `e ?! "sync"`):
 //!    * `f(e:(^)) => f(e):(^)`
 //!    * `f(e:t) => f(e):Identity`
 //! * Syntactic predicates (`&e`, `!e`):
@@ -33,6 +33,7 @@
 //!    * `e:t / e':t => (e / e'): t` if `t` is equal to `()` or `(^)`
 //!    * `e:t / e':t' => (e / e'):Ìdentity` if `t=t'`
 //!    * `e:(^) / e':() => (e / e'): ()`
+//! * Operator table: `%precedence(e:t) {..} => %precedence(e) {..}: t` if `t` is equal to `()` or `(^)`, otherwise `Identity`.
 //! * Explicit typing operator `->`:
 //!    * `e:t -> () => e:()`
 //!    * `e:t -> (^) => e:(^)`
@@ -52,7 +53,7 @@ pub fn bottom_up_unit_inference(grammar: &mut Grammar) {
 
 trait BottomUpAnalysis
 {
-  fn visit_rules(&mut self, rules: &HashMap<Ident, Rule>) {
+  fn visit_rules(&mut self, rules: &OrderedMap<Ident, Rule>) {
     for rule in rules.values() {
       self.visit_rule(rule);
     }
@@ -66,13 +67,15 @@ trait BottomUpAnalysis
     match &expr.node {
         &ZeroOrMore(ref sub)
       | &OneOrMore(ref sub)
-      | &Optional(ref sub) => self.propagate_from_inner(expr, sub),
+      | &Optional(ref sub)
+      | &Recovery(ref sub, _) => self.propagate_from_inner(expr, sub),
         &SemanticAction(ref sub, id) => self.visit_semantic_action(expr, sub, id),
         &NotPredicate(ref sub)
       | &AndPredicate(ref sub) => self.visit_syntactic_predicate(expr, sub),
       &NonTerminalSymbol(id) => self.visit_non_terminal(expr, id),
       &Sequence(ref subs) => self.visit_sequence(expr, subs),
       &Choice(ref subs) => self.visit_choice(expr, subs),
+      &OperatorTable(ref table) => self.visit_operator_table(expr, table),
       _ => ()
     }
   }
@@ -146,6 +149,19 @@ trait BottomUpAnalysis
     }
   }
 
+  fn visit_operator_table(&mut self, parent: &Box<Expression>, table: &OperatorTableExpr<Expression>) {
+    for (_, _, op) in table.operators() {
+      self.visit_expr(&op.expr);
+    }
+    self.visit_expr(&table.operand);
+    if table.operand.is_invisible() {
+      parent.to_invisible_type();
+    }
+    else if table.operand.is_unit() {
+      parent.to_unit_type();
+    }
+  }
+
   fn visit_exprs(&mut self, exprs: &Vec<Box<Expression>>) {
     for expr in exprs {
       self.visit_expr(expr);
@@ -157,7 +173,7 @@ struct IntraRule;
 
 impl IntraRule
 {
-  pub fn propagate(rules: &HashMap<Ident, Rule>) {
+  pub fn propagate(rules: &OrderedMap<Ident, Rule>) {
     IntraRule.visit_rules(rules);
   }
 }
@@ -166,13 +182,13 @@ impl BottomUpAnalysis for IntraRule {}
 
 struct InterRule<'a>
 {
-  rules: &'a HashMap<Ident, Rule>,
+  rules: &'a OrderedMap<Ident, Rule>,
   visited: HashMap<Ident, bool>
 }
 
 impl<'a> InterRule<'a>
 {
-  pub fn propagate(rules: &'a HashMap<Ident, Rule>) {
+  pub fn propagate(rules: &'a OrderedMap<Ident, Rule>) {
     let mut visited = HashMap::with_capacity(rules.len());
     for id in rules.keys() {
       visited.insert(*id, false);
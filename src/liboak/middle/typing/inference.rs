@@ -33,14 +33,14 @@ pub struct InferenceEngine<'r>
 
 impl<'r> InferenceEngine<'r>
 {
-  pub fn infer(grammar: &'r mut Grammar, arules: HashMap<Ident, ARule>) {
+  pub fn infer(grammar: &'r mut Grammar, arules: OrderedMap<Ident, ARule>) {
     let mut engine = InferenceEngine {
       grammar: grammar
     };
     engine.infer_rules_type(arules);
   }
 
-  fn infer_rules_type(&mut self, arules: HashMap<Ident, ARule>) {
+  fn infer_rules_type(&mut self, arules: OrderedMap<Ident, ARule>) {
     for (id, rule) in arules.into_iter() {
       let typed_rule = self.infer_rule_type(rule);
       self.grammar.rules.insert(id, typed_rule);
@@ -51,7 +51,8 @@ impl<'r> InferenceEngine<'r>
     let expr = self.infer_expr_type(rule.def);
     Rule{
       name: rule.name,
-      def: expr
+      def: expr,
+      attributes: rule.attributes
     }
   }
 
@@ -62,14 +63,17 @@ impl<'r> InferenceEngine<'r>
       AnySingleChar => self.infer_identity_expr(sp, AnySingleChar),
       CharacterClass(c) => self.infer_identity_expr(sp, CharacterClass(c)),
       StrLiteral(s) => self.infer_unit_expr(sp, StrLiteral(s)),
+      Indentation(kind) => self.infer_unit_expr(sp, Indentation(kind)),
       NotPredicate(sub) => self.infer_sub_unit_expr(sp, sub, |e| NotPredicate(e)),
       AndPredicate(sub) => self.infer_sub_unit_expr(sp, sub, |e| AndPredicate(e)),
       NonTerminalSymbol(ident) => self.infer_rule_type_ph(sp, ident),
       ZeroOrMore(sub) => self.infer_sub_expr(sp, sub, |e| ZeroOrMore(e), Identity),
       OneOrMore(sub) => self.infer_sub_expr(sp, sub, |e| OneOrMore(e), Identity),
       Optional(sub) =>  self.infer_sub_expr(sp, sub, |e| Optional(e), Identity),
+      Recovery(sub, sync) => self.infer_sub_expr(sp, sub, |e| Recovery(e, sync.clone()), Identity),
       Sequence(sub) => self.infer_tuple_expr(sp, sub),
       Choice(sub) => self.infer_choice_expr(sp, sub),
+      OperatorTable(table) => self.infer_operator_table(sp, table),
       workaround => { // Waiting for Rust FIX: collaterally moved values.
         if let SemanticAction(sub, ident) = workaround {
           self.infer_semantic_action(sp, sub, ident)
@@ -146,6 +150,12 @@ impl<'r> InferenceEngine<'r>
     box Expression::new(sp, Choice(nodes), Identity)
   }
 
+  /// The type of the table is the type of its operand, the operators are only recognized.
+  fn infer_operator_table(&self, sp: Span, table: OperatorTableExpr<AExpression>) -> Box<Expression> {
+    let table = table.map_exprs(|expr| self.infer_expr_type(expr.clone()));
+    box Expression::new(sp, OperatorTable(table), Identity)
+  }
+
   fn infer_semantic_action(&self, sp: Span, expr: Box<AExpression>,
     action_name: Ident) -> Box<Expression>
   {
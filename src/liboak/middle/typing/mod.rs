@@ -32,9 +32,10 @@ mod recursive_type;
 pub fn type_inference(cx: &ExtCtxt, agrammar: AGrammar) -> Partial<Grammar> {
   let mut grammar = Grammar {
     name: agrammar.name,
-    rules: HashMap::with_capacity(agrammar.rules.len()),
+    rules: OrderedMap::with_capacity(agrammar.rules.len()),
     rust_functions: agrammar.rust_functions,
     rust_items: agrammar.rust_items,
+    ast_types: agrammar.ast_types,
     attributes: agrammar.attributes
   };
   InferenceEngine::infer(&mut grammar, agrammar.rules);
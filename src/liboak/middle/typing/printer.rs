@@ -34,7 +34,7 @@ impl Printer
     self.visit_rules(&grammar.rules);
   }
 
-  fn visit_rules(&mut self, rules: &HashMap<Ident, Rule>) {
+  fn visit_rules(&mut self, rules: &OrderedMap<Ident, Rule>) {
     for rule in rules.values() {
       self.visit_rule(rule);
     }
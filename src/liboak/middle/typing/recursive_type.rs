@@ -31,7 +31,7 @@ pub fn recursive_type_analysis(cx: &ExtCtxt, grammar: Grammar)
 pub struct RecursiveType<'a>
 {
   cx: &'a ExtCtxt<'a>,
-  rules: &'a HashMap<Ident, Rule>,
+  rules: &'a OrderedMap<Ident, Rule>,
   visited: HashMap<Ident, bool>,
   current_inline_path: Vec<Ident>,
   cycle_detected: bool,
@@ -41,13 +41,13 @@ pub struct RecursiveType<'a>
 
 impl<'a> RecursiveType<'a>
 {
-  fn analyse(cx: &'a ExtCtxt, rules: &'a HashMap<Ident, Rule>) -> bool {
+  fn analyse(cx: &'a ExtCtxt, rules: &'a OrderedMap<Ident, Rule>) -> bool {
     let mut inlining_loop = RecursiveType::new(cx, rules);
     inlining_loop.visit_rules();
     !inlining_loop.cycle_detected
   }
 
-  fn new(cx: &'a ExtCtxt, rules: &'a HashMap<Ident, Rule>) -> RecursiveType<'a> {
+  fn new(cx: &'a ExtCtxt, rules: &'a OrderedMap<Ident, Rule>) -> RecursiveType<'a> {
     let mut visited = HashMap::with_capacity(rules.len());
     for id in rules.keys() {
       visited.insert(id.clone(), false);
@@ -111,6 +111,7 @@ impl<'a> RecursiveType<'a>
 impl<'a> Visitor<Expression, ()> for RecursiveType<'a>
 {
   unit_visitor_impl!(Expression, str_literal);
+  unit_visitor_impl!(Expression, indentation);
   unit_visitor_impl!(Expression, character);
   unit_visitor_impl!(Expression, sequence);
   unit_visitor_impl!(Expression, choice);
@@ -145,6 +146,11 @@ impl<'a> Visitor<Expression, ()> for RecursiveType<'a>
     }
   }
 
+  /// Operators are only recognized so they can not be part of a recursive type.
+  fn visit_operator_table(&mut self, _parent: &Box<Expression>, table: &OperatorTableExpr<Expression>) {
+    self.visit_expr(&table.operand);
+  }
+
   /// Base case: Semantic actions always have type given by the user, so recursivity is handled by the user.
   fn visit_semantic_action(&mut self, _parent: &Box<Expression>,
     _expr: &Box<Expression>, _id: Ident)
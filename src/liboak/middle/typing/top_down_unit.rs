@@ -29,7 +29,7 @@ struct TopDownUnitInference;
 
 impl TopDownUnitInference
 {
-  fn visit_rules(rules: &mut HashMap<Ident, Rule>) {
+  fn visit_rules(rules: &mut OrderedMap<Ident, Rule>) {
     for (_, rule) in rules.iter_mut() {
       TopDownUnitInference::visit_rule(rule);
     }
@@ -59,13 +59,25 @@ impl ContextExprVisitor
         &mut ZeroOrMore(ref mut expr)
       | &mut OneOrMore(ref mut expr)
       | &mut Optional(ref mut expr)
+      | &mut Recovery(ref mut expr, _)
       | &mut SemanticAction(ref mut expr, _) => ContextExprVisitor::visit_expr(&mut *expr, context),
         &mut NotPredicate(ref mut expr)
       | &mut AndPredicate(ref mut expr) => ContextExprVisitor::visit_expr(&mut *expr, UnValued),
+      &mut OperatorTable(ref mut table) => ContextExprVisitor::visit_operator_table(table, context),
       _ => ()
     }
   }
 
+  /// Operators are only recognized, their values are never built.
+  fn visit_operator_table(table: &mut OperatorTableExpr<Expression>, context: EvaluationContext) {
+    ContextExprVisitor::visit_expr(&mut *table.operand, context);
+    for level in table.levels.iter_mut() {
+      for op in level.operators.iter_mut() {
+        ContextExprVisitor::visit_expr(&mut *op.expr, UnValued);
+      }
+    }
+  }
+
   fn visit_exprs(exprs: &mut Vec<Box<Expression>>, context: EvaluationContext) {
     assert!(exprs.len() > 0);
     for expr in exprs.iter_mut() {
@@ -0,0 +1,261 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `OrderedMap` is a map iterating over its entries in their insertion order. The rules and the functions of the grammar are stored in declaration order so the generated code and the diagnostics do not depend on the order of a `HashMap`, which varies between runs.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::iter::FromIterator;
+use std::mem;
+use std::ops::Index;
+use std::slice;
+use std::vec;
+
+pub struct OrderedMap<K, V>
+{
+  entries: Vec<(K, V)>,
+  /// Position of each key in `entries`.
+  positions: HashMap<K, usize>
+}
+
+impl<K, V> OrderedMap<K, V> where
+ K: Hash + Eq + Clone
+{
+  pub fn new() -> OrderedMap<K, V> {
+    OrderedMap::with_capacity(0)
+  }
+
+  pub fn with_capacity(capacity: usize) -> OrderedMap<K, V> {
+    OrderedMap {
+      entries: Vec::with_capacity(capacity),
+      positions: HashMap::with_capacity(capacity)
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  /// If the key is already present, its value is replaced but it keeps its position.
+  pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+    if let Some(&pos) = self.positions.get(&key) {
+      return Some(mem::replace(&mut self.entries[pos].1, value));
+    }
+    self.positions.insert(key.clone(), self.entries.len());
+    self.entries.push((key, value));
+    None
+  }
+
+  pub fn contains_key(&self, key: &K) -> bool {
+    self.positions.contains_key(key)
+  }
+
+  pub fn get(&self, key: &K) -> Option<&V> {
+    self.positions.get(key).map(|&pos| &self.entries[pos].1)
+  }
+
+  pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+    match self.positions.get(key) {
+      Some(&pos) => Some(&mut self.entries[pos].1),
+      None => None
+    }
+  }
+
+  pub fn iter(&self) -> Iter<K, V> {
+    Iter { entries: self.entries.iter() }
+  }
+
+  pub fn iter_mut(&mut self) -> IterMut<K, V> {
+    IterMut { entries: self.entries.iter_mut() }
+  }
+
+  pub fn keys(&self) -> Keys<K, V> {
+    Keys { entries: self.entries.iter() }
+  }
+
+  pub fn values(&self) -> Values<K, V> {
+    Values { entries: self.entries.iter() }
+  }
+
+  pub fn values_mut(&mut self) -> ValuesMut<K, V> {
+    ValuesMut { entries: self.entries.iter_mut() }
+  }
+
+  /// Removes every entry, they are returned in insertion order.
+  pub fn drain(&mut self) -> vec::IntoIter<(K, V)> {
+    self.positions.clear();
+    mem::replace(&mut self.entries, vec![]).into_iter()
+  }
+}
+
+impl<'a, K, V> Index<&'a K> for OrderedMap<K, V> where
+ K: Hash + Eq + Clone
+{
+  type Output = V;
+
+  fn index(&self, key: &K) -> &V {
+    self.get(key).expect("BUG: The key is not present in the ordered map.")
+  }
+}
+
+impl<K, V> FromIterator<(K, V)> for OrderedMap<K, V> where
+ K: Hash + Eq + Clone
+{
+  fn from_iter<I: IntoIterator<Item=(K, V)>>(iter: I) -> OrderedMap<K, V> {
+    let iter = iter.into_iter();
+    let mut map = OrderedMap::with_capacity(iter.size_hint().0);
+    for (key, value) in iter {
+      map.insert(key, value);
+    }
+    map
+  }
+}
+
+impl<K, V> IntoIterator for OrderedMap<K, V>
+{
+  type Item = (K, V);
+  type IntoIter = vec::IntoIter<(K, V)>;
+
+  fn into_iter(self) -> vec::IntoIter<(K, V)> {
+    self.entries.into_iter()
+  }
+}
+
+pub struct Iter<'a, K: 'a, V: 'a>
+{
+  entries: slice::Iter<'a, (K, V)>
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V>
+{
+  type Item = (&'a K, &'a V);
+
+  fn next(&mut self) -> Option<(&'a K, &'a V)> {
+    self.entries.next().map(|&(ref key, ref value)| (key, value))
+  }
+}
+
+pub struct IterMut<'a, K: 'a, V: 'a>
+{
+  entries: slice::IterMut<'a, (K, V)>
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V>
+{
+  type Item = (&'a K, &'a mut V);
+
+  fn next(&mut self) -> Option<(&'a K, &'a mut V)> {
+    self.entries.next().map(|&mut (ref key, ref mut value)| (key, value))
+  }
+}
+
+pub struct Keys<'a, K: 'a, V: 'a>
+{
+  entries: slice::Iter<'a, (K, V)>
+}
+
+impl<'a, K, V> Iterator for Keys<'a, K, V>
+{
+  type Item = &'a K;
+
+  fn next(&mut self) -> Option<&'a K> {
+    self.entries.next().map(|&(ref key, _)| key)
+  }
+}
+
+pub struct Values<'a, K: 'a, V: 'a>
+{
+  entries: slice::Iter<'a, (K, V)>
+}
+
+impl<'a, K, V> Iterator for Values<'a, K, V>
+{
+  type Item = &'a V;
+
+  fn next(&mut self) -> Option<&'a V> {
+    self.entries.next().map(|&(_, ref value)| value)
+  }
+}
+
+pub struct ValuesMut<'a, K: 'a, V: 'a>
+{
+  entries: slice::IterMut<'a, (K, V)>
+}
+
+impl<'a, K, V> Iterator for ValuesMut<'a, K, V>
+{
+  type Item = &'a mut V;
+
+  fn next(&mut self) -> Option<&'a mut V> {
+    self.entries.next().map(|&mut (_, ref mut value)| value)
+  }
+}
+
+#[cfg(test)]
+mod test
+{
+  use super::*;
+
+  fn letters() -> OrderedMap<char, usize> {
+    vec![('c', 0), ('a', 1), ('b', 2)].into_iter().collect()
+  }
+
+  #[test]
+  fn iteration_follows_insertion_order() {
+    let mut map = letters();
+    assert_eq!(map.keys().cloned().collect::<Vec<_>>(), vec!['c', 'a', 'b']);
+    assert_eq!(map.values().cloned().collect::<Vec<_>>(), vec![0, 1, 2]);
+    assert_eq!(map.iter().map(|(&k, &v)| (k, v)).collect::<Vec<_>>(), vec![('c', 0), ('a', 1), ('b', 2)]);
+    for (_, value) in map.iter_mut() {
+      *value += 10;
+    }
+    for value in map.values_mut() {
+      *value += 10;
+    }
+    assert_eq!(map.into_iter().collect::<Vec<_>>(), vec![('c', 20), ('a', 21), ('b', 22)]);
+  }
+
+  #[test]
+  fn insert_existing_key_keeps_position() {
+    let mut map = letters();
+    assert_eq!(map.insert('a', 5), Some(1));
+    assert_eq!(map.insert('d', 3), None);
+    assert_eq!(map.len(), 4);
+    assert_eq!(map[&'a'], 5);
+    assert_eq!(map.get(&'d'), Some(&3));
+    assert!(map.contains_key(&'d') && !map.contains_key(&'e'));
+    assert_eq!(map.keys().cloned().collect::<Vec<_>>(), vec!['c', 'a', 'b', 'd']);
+  }
+
+  #[test]
+  fn from_iter_with_duplicates() {
+    let map: OrderedMap<char, usize> = vec![('b', 0), ('a', 1), ('b', 2), ('c', 3), ('a', 4)]
+      .into_iter().collect();
+    assert_eq!(map.iter().map(|(&k, &v)| (k, v)).collect::<Vec<_>>(), vec![('b', 2), ('a', 4), ('c', 3)]);
+  }
+
+  #[test]
+  fn drain_empties_the_map() {
+    let mut map = letters();
+    assert_eq!(map.drain().collect::<Vec<_>>(), vec![('c', 0), ('a', 1), ('b', 2)]);
+    assert!(map.is_empty());
+    assert_eq!(map.get(&'a'), None);
+    map.insert('a', 3);
+    assert_eq!(map.iter().map(|(&k, &v)| (k, v)).collect::<Vec<_>>(), vec![('a', 3)]);
+  }
+}
@@ -24,6 +24,7 @@ pub use syntax::ext::base::{ExtCtxt,MacResult,MacEager,DummyResult};
 pub use syntax::ext::quote::rt::ToTokens;
 pub use syntax::ext::build::AstBuilder;
 pub use syntax::ext::base::SyntaxExtension;
+pub use syntax::visit;
 
 pub use syntax::parse::str_lit;
 pub use syntax::parse::parser::Parser;
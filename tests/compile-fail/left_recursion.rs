@@ -0,0 +1,38 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![feature(plugin)]
+#![plugin(oak)]
+
+grammar! left_recursion {
+  expr //~ ERROR Left recursion detected. The rule `expr` can call itself without consuming input
+    = expr add_op term
+    / term
+
+  // `a` calls itself through `b` and through `c` then `b`.
+  a //~ ERROR Left recursion detected. The rule `a` can call itself without consuming input
+    = b "x"
+    / c "y"
+  b = a / "b"
+  c = spacing b
+
+  // The recursive call is not left-recursive since input is consumed before.
+  parens = "(" parens ")" / term
+
+  term = ["0-9"]+ spacing
+  add_op = "+" spacing
+  spacing = [" "]* -> ()
+}
+
+fn main() {}
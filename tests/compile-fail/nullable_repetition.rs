@@ -0,0 +1,52 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![feature(plugin)]
+#![plugin(oak)]
+
+grammar! nullable_repetition {
+  // `SAMEDENT` succeeds without consuming input but `statement` always consumes input.
+  program = (SAMEDENT statement)* spacing
+
+  statement
+    = (spacing)* pass_kw //~ ERROR The expression repeated by `*` can succeed without consuming input
+    / ("a"?)+ "b" //~ ERROR The expression repeated by `+` can succeed without consuming input
+    / (!";" ["a-z"])* ";"
+    / (spacing identifier)+ ";"
+
+  // The operators are parsed in a loop (postfix and infix) or recursively (prefix) so they must consume input.
+  prefix_table = %precedence(number) { //~ ERROR An operator of the table can succeed without consuming input
+    left: "+" spacing > add;
+    prefix: "-"? spacing > neg;
+  }
+
+  postfix_table = %precedence(number) { //~ ERROR An operator of the table can succeed without consuming input
+    postfix: ("!" spacing)* > fact;
+  }
+
+  number = ["0-9"]+ spacing > to_number
+  identifier = ["a-z"]+ spacing
+  pass_kw = "pass" spacing
+  spacing = [" "]* -> ()
+
+  fn to_number(digits: Vec<char>) -> u32 {
+    digits.into_iter().fold(0, |n, d| n * 10 + d.to_digit(10).unwrap())
+  }
+
+  fn add(left: u32, right: u32) -> u32 { left + right }
+  fn neg(expr: u32) -> u32 { 0 - expr }
+  fn fact(expr: u32) -> u32 { (1..expr + 1).fold(1, |f, n| f * n) }
+}
+
+fn main() {}
@@ -0,0 +1,56 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// The public functions printed by `#![show_api]` follow the declaration order of the rules, the recognizer of a rule is generated before its parser.
+
+// error-pattern:fn recognize_sum<
+// error-pattern:fn parse_sum<
+// error-pattern:fn recognize_plus<
+// error-pattern:fn parse_plus<
+// error-pattern:fn recognize_number<
+// error-pattern:fn parse_number<
+// error-pattern:fn recognize_digits<
+// error-pattern:fn parse_digits<
+// error-pattern:fn recognize_spacing<
+// error-pattern:fn parse_spacing<
+
+#![feature(plugin)]
+#![plugin(oak)]
+
+extern crate oak_runtime;
+
+grammar! show_api {
+  #![show_api]
+
+  sum = spacing number (plus number)* > add
+  plus = "+" spacing
+  number = digits spacing > to_number
+  digits = ["0-9"]+
+  spacing = [" "]* -> ()
+
+  use std::str::FromStr;
+
+  fn add(first: u32, rest: Vec<u32>) -> u32 {
+    rest.into_iter().fold(first, |sum, n| sum + n)
+  }
+
+  fn to_number(raw_text: Vec<char>) -> u32 {
+    u32::from_str(&*raw_text.into_iter().collect::<String>()).unwrap()
+  }
+}
+
+fn main() {
+  // The grammar compiles, this error makes the compilation fail so the printed API is checked against the error patterns.
+  let _: () = 0;
+}
@@ -0,0 +1,34 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![feature(plugin)]
+#![plugin(oak)]
+
+grammar! undeclared_action {
+  program = spacing expression
+
+  // The undeclared action of an operator is reported on the line of this operator.
+  expression = %precedence(number) {
+    left: "+" spacing > add, "-" spacing > sub; //~ ERROR Undeclared action
+    prefix: "-" spacing > neg;
+  }
+
+  number = ["0-9"]+ spacing > to_number //~ ERROR Undeclared action
+  spacing = [" "]* -> ()
+
+  fn add(left: u32, right: u32) -> u32 { left + right }
+  fn neg(expr: u32) -> u32 { 0 - expr }
+}
+
+fn main() {}
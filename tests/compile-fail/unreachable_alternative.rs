@@ -0,0 +1,50 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![feature(plugin)]
+#![plugin(oak)]
+
+extern crate oak_runtime;
+
+grammar! unreachable_alternative {
+  program = assign_op letter optional repeated keyword
+
+  assign_op
+    = "="
+    / "==" //~ WARN This alternative is unreachable
+
+  letter
+    = ["a-z"]
+    / ["b-c"] //~ WARN This alternative is unreachable
+
+  optional
+    = "a"?
+    / "b" //~ WARN This alternative is unreachable
+
+  repeated
+    = "a"*
+    / "b" //~ WARN This alternative is unreachable
+
+  // `"if"` does not shadow `"ifx"` since it fails when followed by an identifier character.
+  keyword
+    = "if" !ident_char
+    / "ifx"
+
+  ident_char = ["a-zA-Z0-9_"]
+}
+
+fn main() {
+  // The grammar only produces warnings, this error makes the compilation fail as expected by the test.
+  let _: () = 0; //~ ERROR mismatched types
+}
@@ -0,0 +1,63 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![feature(plugin)]
+#![plugin(oak)]
+
+extern crate oak_runtime;
+
+grammar! unused {
+  program = spacing number
+
+  number = ["0-9"]+ spacing > to_number
+
+  // The rule is never called but its action is not reported.
+  hex_number //~ WARN The rule `hex_number` is never used from the start rules
+    = "0x" ["0-9a-f"]+ spacing > hex_to_number
+
+  spacing = [" "]* -> ()
+
+  fn to_number(digits: Vec<char>) -> u32 {
+    parse_radix(digits, 10)
+  }
+
+  fn hex_to_number(digits: Vec<char>) -> u32 {
+    parse_radix(digits, 16)
+  }
+
+  // Called by the actions.
+  fn parse_radix(digits: Vec<char>, radix: u32) -> u32 {
+    digits.into_iter().fold(0, |n, d| n * radix + d.to_digit(radix).unwrap())
+  }
+
+  // Public functions can be called outside of the grammar.
+  pub fn describe(n: u32) -> String {
+    format!("{} ({})", n, parity(n))
+  }
+
+  // Only called inside a macro.
+  fn parity(n: u32) -> &'static str {
+    if n % 2 == 0 { "even" } else { "odd" }
+  }
+
+  // Only called by itself.
+  fn factorial(n: u32) -> u32 { //~ WARN The function `factorial` is never used
+    if n == 0 { 1 } else { n * factorial(n - 1) }
+  }
+}
+
+fn main() {
+  // The grammar only produces warnings, this error makes the compilation fail as expected by the test.
+  let _: () = 0; //~ ERROR mismatched types
+}
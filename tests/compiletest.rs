@@ -0,0 +1,32 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The grammars of `tests/compile-fail` must be rejected by Oak with the errors annotated by `//~ ERROR` (and the warnings by `//~ WARN`). A test without annotations must instead print the lines matching its `// error-pattern:` headers, in order.
+
+extern crate compiletest_rs as compiletest;
+
+use std::path::PathBuf;
+
+fn run_mode(mode: &'static str) {
+  let mut config = compiletest::default_config();
+  config.mode = mode.parse().ok().expect("Invalid mode");
+  config.src_base = PathBuf::from(format!("tests/{}", mode));
+  config.target_rustcflags = Some("-L target/debug/ -L target/debug/deps/".to_string());
+  compiletest::run_tests(&config);
+}
+
+#[test]
+fn compile_fail() {
+  run_mode("compile-fail");
+}
@@ -0,0 +1,26 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Identifiers with Greek letters, their classes have enough intervals to be compiled into tables with ASCII and non-ASCII characters.
+
+pub use self::char_class::*;
+
+grammar! char_class {
+  // #![show_api]
+
+  program = spacing (identifier spacing)+
+
+  identifier = ["a-zA-Zα-ωΑ-Ω_"] ["a-zA-Z0-9α-ωΑ-Ω_"]*
+
+  spacing = [" \n\r\t"]* -> ()
+}
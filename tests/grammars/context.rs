@@ -0,0 +1,73 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sequence of variable declarations where identifiers are interned in a table given as parsing context. A forward declaration `let x;` is tried after the first alternative of `declaration` interned `x` and failed, the context keeps the effect of this failed alternative.
+
+pub use self::context::*;
+
+grammar! context {
+  // #![show_api]
+  #![context(Interner)]
+
+  program = spacing declaration*
+
+  declaration
+    = let_kw identifier bind_op number semi_kw > declare
+    / let_kw identifier semi_kw > forward
+
+  identifier = ["a-zA-Z_"] ["a-zA-Z0-9_"]* spacing > intern
+  number = ["0-9"]+ spacing > to_number
+  spacing = [" \n\r\t"]* -> ()
+
+  let_kw = "let" spacing
+  bind_op = "=" spacing
+  semi_kw = ";" spacing
+
+  use std::str::FromStr;
+  use std::collections::HashMap;
+
+  pub struct Interner {
+    pub symbols: HashMap<String, usize>,
+    /// Number of calls to `intern`.
+    pub interned: usize
+  }
+
+  impl Interner {
+    pub fn new() -> Interner {
+      Interner { symbols: HashMap::new(), interned: 0 }
+    }
+  }
+
+  fn intern(context: &mut Interner, first: char, rest: Vec<char>) -> usize {
+    let mut name = String::new();
+    name.push(first);
+    name.extend(rest.into_iter());
+    context.interned += 1;
+    let next = context.symbols.len();
+    *context.symbols.entry(name).or_insert(next)
+  }
+
+  fn to_number(raw_text: Vec<char>) -> u32 {
+    let raw_text: String = raw_text.into_iter().collect();
+    u32::from_str(&*raw_text).unwrap()
+  }
+
+  fn declare(symbol: usize, value: u32) -> (usize, u32) {
+    (symbol, value)
+  }
+
+  fn forward(symbol: usize) -> (usize, u32) {
+    (symbol, 0)
+  }
+}
@@ -0,0 +1,51 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! S-expressions with comments, the concrete syntax tree keeps the spaces and the comments
+//! discarded by the parsers.
+
+pub use self::cst::*;
+
+grammar! cst {
+  // #![show_api]
+  #![cst]
+
+  program = spacing sexpr*
+
+  sexpr
+    = atom > atom_expr
+    / lparen sexpr* rparen > list_expr
+
+  atom = ["a-zA-Z0-9_+*/-"]+ spacing > to_string
+
+  spacing = (blank / comment)* -> (^)
+  blank = [" \n\r\t"]
+  comment = ";" (!"\n" .)*
+
+  lparen = "(" spacing
+  rparen = ")" spacing
+
+  #[derive(Debug)]
+  pub enum SExpr {
+    Atom(String),
+    List(Vec<SExpr>)
+  }
+
+  fn atom_expr(atom: String) -> SExpr { SExpr::Atom(atom) }
+  fn list_expr(sexprs: Vec<SExpr>) -> SExpr { SExpr::List(sexprs) }
+
+  fn to_string(raw_text: Vec<char>) -> String {
+    raw_text.into_iter().collect()
+  }
+}
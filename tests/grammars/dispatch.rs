@@ -0,0 +1,70 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! JSON-like values where the alternatives of `value` start with distinct characters, so the choice is dispatched on the next character. The grammar `undispatched` is the same grammar compiled without optimisations, its choices are tried in order.
+
+pub use self::dispatch::*;
+
+grammar! dispatch {
+  // #![show_api]
+
+  program = spacing value
+
+  value = (number
+    / string
+    / array
+    / true_kw
+    / false_kw
+    / null_kw) -> ()
+
+  number = ["0-9"]+ spacing
+  string = "\"" (!"\"" .)* "\"" spacing
+  array = lbracket (value (comma value)*)? rbracket
+
+  true_kw = "true" spacing
+  false_kw = "false" spacing
+  null_kw = "null" spacing
+
+  spacing = [" \n\r\t"]* -> ()
+
+  lbracket = "[" spacing
+  rbracket = "]" spacing
+  comma = "," spacing
+}
+
+grammar! undispatched {
+  #![no_optimisation]
+
+  program = spacing value
+
+  value = (number
+    / string
+    / array
+    / true_kw
+    / false_kw
+    / null_kw) -> ()
+
+  number = ["0-9"]+ spacing
+  string = "\"" (!"\"" .)* "\"" spacing
+  array = lbracket (value (comma value)*)? rbracket
+
+  true_kw = "true" spacing
+  false_kw = "false" spacing
+  null_kw = "null" spacing
+
+  spacing = [" \n\r\t"]* -> ()
+
+  lbracket = "[" spacing
+  rbracket = "]" spacing
+  comma = "," spacing
+}
@@ -0,0 +1,34 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Let bindings where the errors record the rules being parsed.
+
+pub use self::error_context::*;
+
+grammar! error_context {
+  // #![show_api]
+  #![error_context]
+
+  program = spacing let_binding+
+
+  let_binding = let_kw identifier bind_op number semi_colon
+
+  identifier = ["a-z"]+ spacing
+  number = ["0-9"]+ spacing
+  spacing = [" \n\r\t"]* -> ()
+
+  let_kw = "let" spacing
+  bind_op = "=" spacing
+  semi_colon = ";" spacing
+}
@@ -0,0 +1,72 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Subset of `Calc` where the AST types are generated from the rules marked with `#[ast]`.
+
+pub use self::generated_ast::*;
+
+grammar! generated_ast {
+  // #![show_api]
+
+  program = spacing expression
+
+  // struct Expression(Box<Term>, Vec<Term>)
+  #[ast]
+  expression = term (add_op term)*
+
+  // enum Term { Product(Box<Product>), Let(Vec<char>, Box<Expression>, Box<Expression>), Expression(Box<Expression>) }
+  #[ast]
+  term
+    = product
+    / let_kw identifier bind_op expression in_kw expression
+    / lparen expression rparen
+
+  // struct Product(Box<Number>, Option<(Box<MulOp>, Box<Product>)>)
+  #[ast]
+  product = number (mul_op product)?
+
+  // struct Number(Vec<char>)
+  #[ast]
+  number = ["0-9"]+ spacing
+
+  // struct MulOp
+  #[ast]
+  mul_op = "*" spacing
+
+  identifier = ["a-zA-Z_"]+ spacing
+  spacing = [" \n\r\t"]* -> (^)
+
+  let_kw = "let" spacing
+  in_kw = "in" spacing
+  bind_op = "=" spacing
+  add_op = "+" spacing
+  lparen = "(" spacing
+  rparen = ")" spacing
+
+  pub fn count_numbers(expr: &Expression) -> usize {
+    let &Expression(ref first, ref rest) = expr;
+    ::std::iter::once(&**first).chain(rest.iter()).map(|term| {
+      match term {
+        &Term::Product(ref product) => count_product_numbers(product),
+        &Term::Let(_, ref value, ref body) => count_numbers(value) + count_numbers(body),
+        &Term::Expression(ref expr) => count_numbers(expr)
+      }
+    }).fold(0, |count, n| count + n)
+  }
+
+  fn count_product_numbers(product: &Product) -> usize {
+    let &Product(_, ref rest) = product;
+    1 + rest.as_ref().map_or(0, |&(_, ref product)| count_product_numbers(product))
+  }
+}
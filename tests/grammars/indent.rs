@@ -0,0 +1,41 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Python-like statements where blocks are delimited by their indentation.
+
+pub use self::indent::*;
+
+grammar! indent {
+  // #![show_api]
+
+  program = (SAMEDENT statement)* blank_lines
+
+  statement
+    = pass_kw newline
+    / while_kw identifier colon newline block
+    / if_kw identifier colon newline block (SAMEDENT else_kw colon newline block)?
+
+  block = INDENT statement (SAMEDENT statement)* DEDENT
+
+  identifier = ["a-z"]+ spacing
+  newline = "\n"
+  spacing = [" "]* -> ()
+  blank_lines = [" \n"]* -> ()
+
+  pass_kw = "pass" spacing
+  while_kw = "while" spacing
+  if_kw = "if" spacing
+  else_kw = "else" spacing
+  colon = ":" spacing
+}
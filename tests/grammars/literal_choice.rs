@@ -0,0 +1,30 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Keywords sharing prefixes, the choice of literals is compiled into a trie. The input `inif` is read as `in` followed by `if`.
+
+pub use self::literal_choice::*;
+
+grammar! literal_choice {
+  // #![show_api]
+
+  program = spacing (keyword spacing)+
+
+  keyword
+    = "in"
+    / "if"
+    / "impl"
+    / "let"
+
+  spacing = [" \n\r\t"]* -> ()
+}
@@ -0,0 +1,60 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Arithmetic expressions where the alternatives share a common prefix, `term` and `factor` are
+//! memoised to avoid parsing them several times at the same position.
+
+pub use self::memo::*;
+
+grammar! memo {
+  // #![show_api]
+
+  program = spacing expression
+
+  expression
+    = term add_op expression > add
+    / term sub_op expression > sub
+    / term
+
+  #[memo]
+  term
+    = factor mul_op term > mul
+    / factor
+
+  #[memo]
+  factor
+    = number
+    / lparen expression rparen
+
+  digit = ["0-9"]
+  number = digit+ spacing > to_number
+  spacing = [" \n\r\t"]* -> ()
+
+  add_op = "+" spacing
+  sub_op = "-" spacing
+  mul_op = "*" spacing
+  lparen = "(" spacing
+  rparen = ")" spacing
+
+  use std::str::FromStr;
+
+  fn to_number(raw_text: Vec<char>) -> i64 {
+    let raw_text: String = raw_text.into_iter().collect();
+    i64::from_str(&*raw_text).unwrap()
+  }
+
+  fn add(x: i64, y: i64) -> i64 { x + y }
+  fn sub(x: i64, y: i64) -> i64 { x - y }
+  fn mul(x: i64, y: i64) -> i64 { x * y }
+}
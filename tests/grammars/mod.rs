@@ -16,3 +16,17 @@ pub mod ntcc;
 pub mod type_name;
 pub mod calc;
 pub mod recursive_type;
+pub mod precedence;
+pub mod memo;
+pub mod context;
+pub mod indent;
+pub mod generated_ast;
+pub mod cst;
+pub mod recovery;
+pub mod trace;
+pub mod profile;
+pub mod dispatch;
+pub mod literal_choice;
+pub mod char_class;
+pub mod optimisation;
+pub mod error_context;
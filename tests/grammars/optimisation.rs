@@ -0,0 +1,163 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Small rules such as `bind_op` and `spacing` are inlined, `":" "="` is merged into a single literal and the nested sequence and choice are flattened. The entries are printed back from their values to check that the optimisations preserve them. The grammar `unoptimised` is the same grammar compiled without optimisations, it must build the same values and report the same errors.
+
+pub use self::optimisation::*;
+
+grammar! optimisation {
+  // #![show_api]
+
+  program = spacing (entry semicolon)*
+
+  entry = key (bind_op value) > make_entry
+
+  value
+    = (number / boolean)
+    / list
+
+  key = ["a-z"]+ spacing > to_string
+  number = digits spacing > to_number
+  digits = ["0-9"]+
+  boolean
+    = "true" spacing > make_true
+    / "false" spacing > make_false
+  list = lbracket (value (comma value)*)? rbracket > make_list
+
+  bind_op = ":" "=" spacing
+  semicolon = ";" spacing
+  lbracket = "[" spacing
+  rbracket = "]" spacing
+  comma = "," spacing
+  spacing = [" \n\r\t"]* -> ()
+
+  use std::str::FromStr;
+  use self::Value::*;
+
+  #[derive(Debug)]
+  pub enum Value {
+    Number(u32),
+    Boolean(bool),
+    List(Vec<Value>)
+  }
+
+  fn to_string(raw_text: Vec<char>) -> String {
+    raw_text.into_iter().collect()
+  }
+
+  fn to_number(raw_text: Vec<char>) -> Value {
+    Number(u32::from_str(&*to_string(raw_text)).unwrap())
+  }
+
+  fn make_true() -> Value {
+    Boolean(true)
+  }
+
+  fn make_false() -> Value {
+    Boolean(false)
+  }
+
+  fn make_list(values: Option<(Value, Vec<Value>)>) -> Value {
+    List(match values {
+      Some((first, rest)) => Some(first).into_iter().chain(rest.into_iter()).collect(),
+      None => vec![]
+    })
+  }
+
+  fn make_entry(key: String, value: Value) -> String {
+    format!("{}:={};", key, to_source(&value))
+  }
+
+  fn to_source(value: &Value) -> String {
+    match value {
+      &Number(n) => n.to_string(),
+      &Boolean(b) => b.to_string(),
+      &List(ref values) => format!("[{}]",
+        values.iter().map(to_source).collect::<Vec<_>>().join(","))
+    }
+  }
+}
+
+grammar! unoptimised {
+  #![no_optimisation]
+
+  program = spacing (entry semicolon)*
+
+  entry = key (bind_op value) > make_entry
+
+  value
+    = (number / boolean)
+    / list
+
+  key = ["a-z"]+ spacing > to_string
+  number = digits spacing > to_number
+  digits = ["0-9"]+
+  boolean
+    = "true" spacing > make_true
+    / "false" spacing > make_false
+  list = lbracket (value (comma value)*)? rbracket > make_list
+
+  bind_op = ":" "=" spacing
+  semicolon = ";" spacing
+  lbracket = "[" spacing
+  rbracket = "]" spacing
+  comma = "," spacing
+  spacing = [" \n\r\t"]* -> ()
+
+  use std::str::FromStr;
+  use self::Value::*;
+
+  #[derive(Debug)]
+  pub enum Value {
+    Number(u32),
+    Boolean(bool),
+    List(Vec<Value>)
+  }
+
+  fn to_string(raw_text: Vec<char>) -> String {
+    raw_text.into_iter().collect()
+  }
+
+  fn to_number(raw_text: Vec<char>) -> Value {
+    Number(u32::from_str(&*to_string(raw_text)).unwrap())
+  }
+
+  fn make_true() -> Value {
+    Boolean(true)
+  }
+
+  fn make_false() -> Value {
+    Boolean(false)
+  }
+
+  fn make_list(values: Option<(Value, Vec<Value>)>) -> Value {
+    List(match values {
+      Some((first, rest)) => Some(first).into_iter().chain(rest.into_iter()).collect(),
+      None => vec![]
+    })
+  }
+
+  fn make_entry(key: String, value: Value) -> String {
+    format!("{}:={};", key, to_source(&value))
+  }
+
+  fn to_source(value: &Value) -> String {
+    match value {
+      &Number(n) => n.to_string(),
+      &Boolean(b) => b.to_string(),
+      &List(ref values) => format!("[{}]",
+        values.iter().map(to_source).collect::<Vec<_>>().join(","))
+    }
+  }
+}
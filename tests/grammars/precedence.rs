@@ -0,0 +1,88 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Arithmetic expressions of `Calc` described with an operator-precedence table.
+
+pub use self::precedence::*;
+
+grammar! precedence {
+  // #![show_api]
+
+  program = spacing expression
+
+  expression = %precedence(factor) {
+    left: add_op > add_expr, sub_op > sub_expr;
+    left: mul_op > mul_expr, div_op > div_expr;
+    prefix: sub_op > neg_expr;
+    right: exp_op > exp_expr;
+    postfix: fact_op > fact_expr;
+  }
+
+  factor
+    = number > number_expr
+    / lparen expression rparen
+
+  digit = ["0-9"]
+  number = digit+ spacing > to_number
+  spacing = [" \n\r\t"]* -> ()
+
+  add_op = "+" spacing
+  sub_op = "-" spacing
+  mul_op = "*" spacing
+  div_op = "/" spacing
+  exp_op = "^" spacing
+  fact_op = "!" spacing
+  lparen = "(" spacing
+  rparen = ")" spacing
+
+  use std::str::FromStr;
+  use self::Expression::*;
+  use self::BinOp::*;
+
+  pub type PExpr = Box<Expression>;
+
+  #[derive(Debug)]
+  pub enum Expression {
+    Number(u32),
+    BinaryExpr(BinOp, PExpr, PExpr),
+    Neg(PExpr),
+    Fact(PExpr)
+  }
+
+  #[derive(Debug)]
+  pub enum BinOp {
+    Add, Sub, Mul, Div, Exp
+  }
+
+  fn to_number(raw_text: Vec<char>) -> u32 {
+    let raw_text: String = raw_text.into_iter().collect();
+    u32::from_str(&*raw_text).unwrap()
+  }
+
+  fn number_expr(value: u32) -> PExpr {
+    Box::new(Number(value))
+  }
+
+  fn binary_expr(op: BinOp, left: PExpr, right: PExpr) -> PExpr {
+    Box::new(BinaryExpr(op, left, right))
+  }
+
+  fn add_expr(left: PExpr, right: PExpr) -> PExpr { binary_expr(Add, left, right) }
+  fn sub_expr(left: PExpr, right: PExpr) -> PExpr { binary_expr(Sub, left, right) }
+  fn mul_expr(left: PExpr, right: PExpr) -> PExpr { binary_expr(Mul, left, right) }
+  fn div_expr(left: PExpr, right: PExpr) -> PExpr { binary_expr(Div, left, right) }
+  fn exp_expr(left: PExpr, right: PExpr) -> PExpr { binary_expr(Exp, left, right) }
+  fn neg_expr(expr: PExpr) -> PExpr { Box::new(Neg(expr)) }
+  fn fact_expr(expr: PExpr) -> PExpr { Box::new(Fact(expr)) }
+}
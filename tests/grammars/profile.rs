@@ -0,0 +1,38 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sums of products written without factoring the common prefix of the alternatives, so terms are re-read when the parser backtracks. The invocations of the generated functions are counted by a profiler.
+
+pub use self::profile::*;
+
+grammar! profile {
+  // #![show_api]
+  #![profile]
+
+  program = spacing expression
+
+  expression
+    = term plus expression
+    / term
+
+  term
+    = number times term
+    / number
+
+  number = ["0-9"]+ spacing
+  spacing = [" \n\r\t"]* -> ()
+
+  plus = "+" spacing
+  times = "*" spacing
+}
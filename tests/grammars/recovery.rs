@@ -0,0 +1,64 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sequence of variable declarations where an invalid declaration is skipped up to the next `;`
+//! and replaced by an error statement.
+
+pub use self::recovery::*;
+
+grammar! recovery {
+  // #![show_api]
+
+  program = spacing statement*
+
+  statement = declaration ?! ";" spacing > statement_or_error
+
+  declaration = let_kw identifier bind_op number semi_kw > declare
+
+  identifier = ["a-zA-Z_"] ["a-zA-Z0-9_"]* spacing > to_identifier
+  number = ["0-9"]+ spacing > to_number
+  spacing = [" \n\r\t"]* -> ()
+
+  let_kw = "let" spacing
+  bind_op = "=" spacing
+  semi_kw = ";" spacing
+
+  use std::str::FromStr;
+
+  #[derive(Debug, PartialEq)]
+  pub enum Statement {
+    Declaration(String, u32),
+    Error
+  }
+
+  fn to_identifier(first: char, rest: Vec<char>) -> String {
+    let mut name = String::new();
+    name.push(first);
+    name.extend(rest.into_iter());
+    name
+  }
+
+  fn to_number(raw_text: Vec<char>) -> u32 {
+    let raw_text: String = raw_text.into_iter().collect();
+    u32::from_str(&*raw_text).unwrap()
+  }
+
+  fn declare(name: String, value: u32) -> Statement {
+    Statement::Declaration(name, value)
+  }
+
+  fn statement_or_error(declaration: Option<Statement>) -> Statement {
+    declaration.unwrap_or(Statement::Error)
+  }
+}
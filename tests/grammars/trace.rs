@@ -0,0 +1,36 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Nested lists of numbers where the rules tried are reported to a tracer.
+
+pub use self::trace::*;
+
+grammar! trace {
+  // #![show_api]
+  #![trace]
+
+  program = spacing list
+
+  list = lbracket (item (comma item)*)? rbracket
+  item
+    = number
+    / list
+
+  number = ["0-9"]+ spacing
+  spacing = [" \n\r\t"]* -> ()
+
+  lbracket = "[" spacing
+  rbracket = "]" spacing
+  comma = "," spacing
+}
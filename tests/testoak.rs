@@ -49,10 +49,152 @@ fn test_data_directory()
     type_name::recognize_type_names(content.stream())));
   test_engine.register("calc", Box::new(|content|
     calc::recognize_program(content.stream())));
+  test_engine.register("precedence", Box::new(|content|
+    precedence::recognize_program(content.stream())));
+  test_engine.register("memo", Box::new(|content|
+    memo::recognize_program(content.stream(), &mut memo::Memo::new())));
+  test_engine.register("incremental", Box::new(|content| {
+    // The input is first parsed after a prefix which is then removed, the memoised states following the prefix are moved and reused.
+    let prefixed = format!("1 * {}", content);
+    let mut memo = memo::Memo::new();
+    memo::parse_program(IncrementalStream::new(prefixed.as_str().stream()), &mut memo);
+    memo.edit(&TextEdit::new(0..4, ""));
+    let state = memo::parse_program(IncrementalStream::new(content.stream()), &mut memo)
+      .map_stream(IncrementalStream::into_inner);
+    let expected = memo::parse_program(content.stream(), &mut memo::Memo::new());
+    assert_eq!(state.success.as_ref().map(|success| (success.stream.offset(), success.data)),
+      expected.success.as_ref().map(|success| (success.stream.offset(), success.data)));
+    state.map(|success| ParseSuccess::new(success.stream, ()))
+  }));
+  test_engine.register("context", Box::new(|content| {
+    let mut interner = context::Interner::new();
+    let state = context::parse_program(content.stream(), &mut interner);
+    state.map(|success| {
+      for &(symbol, _) in success.data.iter() {
+        assert!(interner.symbols.values().any(|&interned| interned == symbol));
+      }
+      ParseSuccess::new(success.stream, ())
+    })
+  }));
+  test_engine.register("indent", Box::new(|content|
+    indent::recognize_program(IndentStream::new(content.stream())).map_stream(IndentStream::into_inner)));
+  test_engine.register("generated_ast", Box::new(|content|
+    generated_ast::parse_program(content.stream()).map(|success| {
+      let read = &content[..success.stream.offset()];
+      let numbers = read.split(|c: char| !c.is_digit(10)).filter(|number| !number.is_empty()).count();
+      assert_eq!(generated_ast::count_numbers(&success.data), numbers);
+      ParseSuccess::new(success.stream, ())
+    })));
+  test_engine.register("cst", Box::new(|content|
+    cst::cst_program(content.stream()).map(|success| {
+      assert_eq!(success.data.to_source(content), success.data.text(content));
+      ParseSuccess::new(success.stream, ())
+    })));
+  test_engine.register("recovery", Box::new(|content|
+    recovery::recognize_program(RecoveryStream::new(content.stream())).map_stream(RecoveryStream::into_inner)));
+  test_engine.register("trace", Box::new(|content| {
+    let mut tracer = PrintTracer::new(vec![]);
+    let state = trace::recognize_program(content.stream(), &mut tracer);
+    let trace = String::from_utf8(tracer.into_inner()).unwrap();
+    assert!(trace.starts_with("program at 1:1\n"));
+    assert!(trace.lines().last().unwrap().starts_with("program "));
+    state
+  }));
+  test_engine.register("profile", Box::new(|content| {
+    let mut profiler = Profiler::new();
+    let state = profile::recognize_program(content.stream(), &mut profiler);
+    let entries = profiler.entries();
+    assert!(entries.iter().any(|entry| entry.function == "recognize_program" && entry.invocations == 1));
+    assert!(entries.iter().any(|entry| entry.function.contains("_in_rule_term_")));
+    if state.is_successful() {
+      assert!(entries.iter().any(|entry| entry.function == "recognize_number" && entry.bytes_reread > 0));
+    }
+    state
+  }));
+  test_engine.register("dispatch", Box::new(|content| {
+    let state = dispatch::recognize_program(content.stream());
+    let chain = dispatch::undispatched::recognize_program(content.stream());
+    // The alternatives skipped by the dispatch still report what they expected.
+    assert_eq!(state.error.farthest_read.offset(), chain.error.farthest_read.offset());
+    assert_eq!(state.error.unique_expected(), chain.error.unique_expected());
+    state
+  }));
+  test_engine.register("literal_choice", Box::new(|content|
+    literal_choice::recognize_program(content.stream())));
+  test_engine.register("char_class", Box::new(|content|
+    char_class::recognize_program(content.stream())));
+  test_engine.register("optimisation", Box::new(|content| {
+    let state = optimisation::parse_program(content.stream());
+    let unoptimised = optimisation::unoptimised::parse_program(content.stream());
+    // The optimisations preserve the values and the errors.
+    let entries = |state: &ParseState<_, Vec<String>>|
+      state.success.as_ref().map(|success| (success.stream.offset(), success.data.clone()));
+    assert_eq!(entries(&state), entries(&unoptimised));
+    assert_eq!(state.error.farthest_read.offset(), unoptimised.error.farthest_read.offset());
+    assert_eq!(state.error.unique_expected(), unoptimised.error.unique_expected());
+    state.map(|success| {
+      let printed: String = success.data.concat();
+      // The entries read are printed back without the spaces.
+      let read = &content[..success.stream.offset()];
+      let compact: String = read.chars().filter(|c| !c.is_whitespace()).collect();
+      assert_eq!(printed, compact);
+      ParseSuccess::new(success.stream, ())
+    })
+  }));
+  test_engine.register("error_context", Box::new(|content| {
+    let state = error_context::recognize_program(content.stream());
+    if state.success.as_ref().map_or(true, |success| success.partial_read()) {
+      let enclosing = state.error.enclosing_rule().map(|context| context.rule);
+      assert_eq!(enclosing, Some("let_binding"));
+    }
+    state
+  }));
 
   test_engine.run();
 }
 
+#[test]
+fn context_is_not_restored_on_backtracking()
+{
+  let mut interner = context::Interner::new();
+  let result = context::parse_program("let x; let y = 1;".stream(), &mut interner).into_result();
+  match result {
+    Ok((ref success, _)) if success.full_read() => {
+      assert_eq!(success.data, vec![(0, 0), (1, 1)]);
+    }
+    _ => panic!("`let x; let y = 1;` must be entirely parsed.")
+  }
+  assert_eq!(interner.symbols.len(), 2);
+  // `x` is interned by the first alternative of `declaration`, which fails on `;`, and again by the second one.
+  assert_eq!(interner.interned, 3);
+}
+
+#[test]
+fn error_context_of_rules()
+{
+  let error = error_context::recognize_program("let x = 1;\nlet y 2;".stream()).error;
+  assert_eq!(error.context.iter().map(|context| context.rule).collect::<Vec<_>>(),
+    vec!["bind_op", "let_binding", "program"]);
+  assert!(format!("{}", error).ends_with(", while parsing let binding (started at 2:1)."));
+  // The rules are only recorded with `#![error_context]`.
+  let error = calc::recognize_program("let x 1 in x".stream()).error;
+  assert!(error.context.is_empty());
+}
+
+#[test]
+fn generated_ast_values()
+{
+  use generated_ast::{Expression, Term, Product, Number, MulOp};
+  let number = |digit| Box::new(Number(vec![digit]));
+  let ast = generated_ast::parse_program("1*2 + (3)".stream()).unwrap_data();
+  assert_eq!(ast, Expression(
+    Box::new(Term::Product(Box::new(Product(number('1'),
+      Some((Box::new(MulOp), Box::new(Product(number('2'), None)))))))),
+    vec![Term::Expression(Box::new(Expression(
+      Box::new(Term::Product(Box::new(Product(number('3'), None)))),
+      vec![])))]));
+}
+
 struct TestEngine
 {
   test_path: PathBuf,